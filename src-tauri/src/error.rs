@@ -0,0 +1,223 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+pub type Result<T, E = ToolboxError> = std::result::Result<T, E>;
+
+// 所有命令统一返回的错误。前端按 code 区分处理（如弹出验证码、提示重试），
+// message 直接展示给用户，details 保留底层错误便于排查。
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolboxError {
+    InvalidInput(String),
+    NotFound(String),
+    Conflict(String),
+    Config(String),
+    WrongPassword,
+    CaptchaRequired,
+    SessionExpired,
+    Network {
+        message: String,
+        details: Option<String>,
+    },
+    Timeout {
+        message: String,
+        details: Option<String>,
+    },
+    Parse {
+        message: String,
+        details: Option<String>,
+    },
+    DatabaseLocked {
+        details: Option<String>,
+    },
+    DatabaseTooNew(String),
+    Database {
+        message: String,
+        details: Option<String>,
+    },
+    Io {
+        message: String,
+        details: Option<String>,
+    },
+    Crypto(String),
+    Internal {
+        message: String,
+        details: Option<String>,
+    },
+}
+
+impl ToolboxError {
+    pub fn network(message: impl Into<String>, err: reqwest::Error) -> Self {
+        let message = message.into();
+        let details = Some(err.to_string());
+        if err.is_timeout() {
+            ToolboxError::Timeout { message, details }
+        } else {
+            ToolboxError::Network { message, details }
+        }
+    }
+
+    pub fn database(message: impl Into<String>, err: rusqlite::Error) -> Self {
+        let details = Some(err.to_string());
+        match err.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                ToolboxError::DatabaseLocked { details }
+            }
+            _ => ToolboxError::Database {
+                message: message.into(),
+                details,
+            },
+        }
+    }
+
+    pub fn parse(message: impl Into<String>, err: impl fmt::Display) -> Self {
+        ToolboxError::Parse {
+            message: message.into(),
+            details: Some(err.to_string()),
+        }
+    }
+
+    pub fn io(message: impl Into<String>, err: impl fmt::Display) -> Self {
+        ToolboxError::Io {
+            message: message.into(),
+            details: Some(err.to_string()),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>, err: impl fmt::Display) -> Self {
+        ToolboxError::Internal {
+            message: message.into(),
+            details: Some(err.to_string()),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ToolboxError::InvalidInput(_) => "invalid_input",
+            ToolboxError::NotFound(_) => "not_found",
+            ToolboxError::Conflict(_) => "conflict",
+            ToolboxError::Config(_) => "config",
+            ToolboxError::WrongPassword => "wrong_password",
+            ToolboxError::CaptchaRequired => "captcha_required",
+            ToolboxError::SessionExpired => "session_expired",
+            ToolboxError::Network { .. } => "network",
+            ToolboxError::Timeout { .. } => "timeout",
+            ToolboxError::Parse { .. } => "parse",
+            ToolboxError::DatabaseLocked { .. } => "database_locked",
+            ToolboxError::DatabaseTooNew(_) => "database_too_new",
+            ToolboxError::Database { .. } => "database",
+            ToolboxError::Io { .. } => "io",
+            ToolboxError::Crypto(_) => "crypto",
+            ToolboxError::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ToolboxError::InvalidInput(message)
+            | ToolboxError::NotFound(message)
+            | ToolboxError::Conflict(message)
+            | ToolboxError::Config(message)
+            | ToolboxError::DatabaseTooNew(message)
+            | ToolboxError::Crypto(message) => message,
+            ToolboxError::WrongPassword => "用户名或密码错误",
+            ToolboxError::CaptchaRequired => "登录需要验证码",
+            ToolboxError::SessionExpired => "登录状态已失效",
+            ToolboxError::DatabaseLocked { .. } => "数据库正被占用，请稍后重试",
+            ToolboxError::Network { message, .. }
+            | ToolboxError::Timeout { message, .. }
+            | ToolboxError::Parse { message, .. }
+            | ToolboxError::Database { message, .. }
+            | ToolboxError::Io { message, .. }
+            | ToolboxError::Internal { message, .. } => message,
+        }
+    }
+
+    pub fn details(&self) -> Option<&str> {
+        match self {
+            ToolboxError::Network { details, .. }
+            | ToolboxError::Timeout { details, .. }
+            | ToolboxError::Parse { details, .. }
+            | ToolboxError::DatabaseLocked { details }
+            | ToolboxError::Database { details, .. }
+            | ToolboxError::Io { details, .. }
+            | ToolboxError::Internal { details, .. } => details.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ToolboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details() {
+            Some(details) => write!(f, "{}: {}", self.message(), details),
+            None => f.write_str(self.message()),
+        }
+    }
+}
+
+impl std::error::Error for ToolboxError {}
+
+impl Serialize for ToolboxError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ToolboxError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for ToolboxError {
+    fn from(err: rusqlite::Error) -> Self {
+        ToolboxError::database("数据库操作失败", err)
+    }
+}
+
+impl From<reqwest::Error> for ToolboxError {
+    fn from(err: reqwest::Error) -> Self {
+        ToolboxError::network("网络请求失败", err)
+    }
+}
+
+impl From<std::io::Error> for ToolboxError {
+    fn from(err: std::io::Error) -> Self {
+        ToolboxError::io("文件读写失败", err)
+    }
+}
+
+impl From<tokio::task::JoinError> for ToolboxError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        ToolboxError::internal("后台任务失败", err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let value = serde_json::to_value(ToolboxError::CaptchaRequired).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "code": "captcha_required",
+                "message": "登录需要验证码",
+                "details": null,
+            })
+        );
+
+        let err = ToolboxError::io("保存失败", "disk full");
+        assert_eq!(err.to_string(), "保存失败: disk full");
+        assert_eq!(serde_json::to_value(&err).unwrap()["code"], "io");
+    }
+
+    #[test]
+    fn busy_database_maps_to_locked() {
+        let err = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            None,
+        );
+        assert_eq!(ToolboxError::from(err).code(), "database_locked");
+    }
+}
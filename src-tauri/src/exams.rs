@@ -0,0 +1,218 @@
+use chrono::{NaiveDate, NaiveTime};
+use scraper::{Html, Selector};
+
+use crate::db::ExamEntryInput;
+use crate::error::{Result, ToolboxError};
+use crate::jwxt::JwxtSession;
+
+pub struct ExamFetchResult {
+    pub term: String,
+    pub terms: Vec<String>,
+    pub entries: Vec<ExamEntryInput>,
+}
+
+fn normalize_text(value: &str) -> String {
+    value
+        .replace('\u{a0}', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_terms(html: &str, term_field: &str) -> (Vec<String>, Option<String>) {
+    let document = Html::parse_document(html);
+    let selector = match Selector::parse(&format!("#{} option", term_field)) {
+        Ok(selector) => selector,
+        Err(_) => return (Vec::new(), None),
+    };
+    let mut terms = Vec::new();
+    let mut selected = None;
+    for option in document.select(&selector) {
+        let value = option
+            .value()
+            .attr("value")
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default();
+        if value.is_empty() {
+            continue;
+        }
+        if option.value().attr("selected").is_some() {
+            selected = Some(value.clone());
+        }
+        terms.push(value);
+    }
+    (terms, selected)
+}
+
+// “2025-06-23 09:00~11:00”或“2025-06-23 14:30--16:30”，返回 YYYY-MM-DD HH:MM:SS 格式的起止时间。
+pub(crate) fn parse_exam_time(text: &str) -> Option<(String, String)> {
+    let (date, range) = text.trim().split_once(' ')?;
+    let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()?;
+    let (start, end) = range
+        .split_once('~')
+        .or_else(|| range.split_once("--"))
+        .or_else(|| range.split_once('-'))?;
+    let time = |value: &str| NaiveTime::parse_from_str(value.trim(), "%H:%M").ok();
+    let (start, end) = (date.and_time(time(start)?), date.and_time(time(end)?));
+    if end <= start {
+        return None;
+    }
+    let format = |value: chrono::NaiveDateTime| value.format("%Y-%m-%d %H:%M:%S").to_string();
+    Some((format(start), format(end)))
+}
+
+// 各校考试安排页的列顺序不同，按表头文字定位各列。
+struct ExamColumns {
+    course_code: usize,
+    course_name: usize,
+    teacher: Option<usize>,
+    exam_time: Option<usize>,
+    room: Option<usize>,
+    seat_number: Option<usize>,
+    exam_type: Option<usize>,
+    note: Option<usize>,
+}
+
+impl ExamColumns {
+    fn from_headers(headers: &[String]) -> Option<Self> {
+        let find = |names: &[&str]| {
+            headers
+                .iter()
+                .position(|header| names.iter().any(|name| header.contains(name)))
+        };
+        Some(ExamColumns {
+            course_code: find(&["课程编号", "课程代码"])?,
+            course_name: find(&["课程名称"])?,
+            teacher: find(&["教师"]),
+            exam_time: find(&["考试时间"]),
+            room: find(&["考场", "考试地点", "教室"]),
+            seat_number: find(&["座位号", "座号"]),
+            exam_type: find(&["考试性质", "考试类型"]),
+            note: find(&["备注"]),
+        })
+    }
+}
+
+fn parse_exam_entries(html: &str, term: &str) -> Result<Vec<ExamEntryInput>> {
+    let document = Html::parse_document(html);
+    let row_selector =
+        Selector::parse("#dataList tr").map_err(|e| ToolboxError::internal("选择器无效", e))?;
+    let th_selector = Selector::parse("th").map_err(|e| ToolboxError::internal("选择器无效", e))?;
+    let td_selector = Selector::parse("td").map_err(|e| ToolboxError::internal("选择器无效", e))?;
+
+    let mut columns = None;
+    let mut entries = Vec::new();
+    for row in document.select(&row_selector) {
+        let headers = row
+            .select(&th_selector)
+            .map(|cell| normalize_text(&cell.text().collect::<Vec<_>>().join("")))
+            .collect::<Vec<_>>();
+        if !headers.is_empty() {
+            columns = ExamColumns::from_headers(&headers);
+            continue;
+        }
+        let Some(columns) = columns.as_ref() else {
+            continue;
+        };
+        let cells = row
+            .select(&td_selector)
+            .map(|cell| normalize_text(&cell.text().collect::<Vec<_>>().join("")))
+            .collect::<Vec<_>>();
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|i| cells.get(i))
+                .cloned()
+                .filter(|v| !v.is_empty())
+        };
+        let (Some(course_code), Some(course_name)) = (
+            cell(Some(columns.course_code)),
+            cell(Some(columns.course_name)),
+        ) else {
+            continue;
+        };
+        let exam_time = cell(columns.exam_time);
+        let (starts_at, ends_at) = exam_time
+            .as_deref()
+            .and_then(parse_exam_time)
+            .map_or((None, None), |(start, end)| (Some(start), Some(end)));
+        entries.push(ExamEntryInput {
+            term: term.to_string(),
+            course_code,
+            course_name,
+            teacher: cell(columns.teacher),
+            exam_time,
+            starts_at,
+            ends_at,
+            room: cell(columns.room),
+            seat_number: cell(columns.seat_number),
+            exam_type: cell(columns.exam_type),
+            note: cell(columns.note),
+        });
+    }
+    Ok(entries)
+}
+
+pub async fn fetch_exams(
+    session: &mut JwxtSession,
+    term: Option<String>,
+) -> Result<ExamFetchResult> {
+    session.ensure_login().await?;
+    let profile = session.profile().clone();
+    let exam_url = profile.exam_url();
+    let term_field = profile.fields.exam_term.as_str();
+
+    let initial_html = session.get(&exam_url).await?;
+    let (terms, selected_term) = parse_terms(&initial_html, term_field);
+    let selected_term = term.or(selected_term).unwrap_or_default();
+    if selected_term.is_empty() {
+        return Err(ToolboxError::Parse {
+            message: "未获取到学期信息".to_string(),
+            details: None,
+        });
+    }
+
+    let params = [(term_field, selected_term.as_str())];
+    let html = session.post_form(&exam_url, &params).await?;
+    let entries = parse_exam_entries(&html, &selected_term)?;
+    Ok(ExamFetchResult {
+        term: selected_term,
+        terms,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::assert_golden;
+
+    #[test]
+    fn parses_exam_list_fixture() {
+        let html = include_str!("../tests/fixtures/jwxt/xsksap_list.html");
+        let (terms, selected) = parse_terms(html, "xnxqid");
+        assert_eq!(terms, vec!["2024-2025-2", "2024-2025-1"]);
+        assert_eq!(selected.as_deref(), Some("2024-2025-2"));
+        let entries = parse_exam_entries(html, "2024-2025-2").unwrap();
+        assert_golden(
+            &entries,
+            include_str!("../tests/fixtures/jwxt/xsksap_list.golden.json"),
+        );
+    }
+
+    #[test]
+    fn reads_exam_time_range() {
+        assert_eq!(
+            parse_exam_time("2025-06-23 09:00~11:00"),
+            Some((
+                "2025-06-23 09:00:00".to_string(),
+                "2025-06-23 11:00:00".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_exam_time("2025-06-25 14:30--16:30").map(|(_, end)| end),
+            Some("2025-06-25 16:30:00".to_string())
+        );
+        assert_eq!(parse_exam_time("第18周 星期三"), None);
+        assert_eq!(parse_exam_time("2025-06-23 11:00~09:00"), None);
+    }
+}
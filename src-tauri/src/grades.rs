@@ -1,14 +1,15 @@
-use base64::{engine::general_purpose, Engine as _};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+pub mod analytics;
+pub mod audit;
+pub mod compare;
+pub mod transcript;
+pub mod watch;
+
 use scraper::{Html, Selector};
 use std::collections::HashSet;
 
 use crate::db::{GradeRecordInput, PlanCourseInput};
-
-const LOGIN_URL: &str = "http://jwxt.ahut.edu.cn/jsxsd/xk/LoginToXk";
-const GRADES_URL: &str = "http://jwxt.ahut.edu.cn/jsxsd/kscj/cjcx_list";
-const MINOR_URL: &str = "http://jwxt.ahut.edu.cn/jsxsd/pyfa/fxpyfa_query";
-const MAJOR_URL: &str = "http://jwxt.ahut.edu.cn/jsxsd/pyfa/pyfa_query";
+use crate::error::{Result, ToolboxError};
+use crate::jwxt::JwxtSession;
 
 pub struct GradeFetchResult {
     pub grades: Vec<GradeRecordInput>,
@@ -18,66 +19,6 @@ pub struct GradeFetchResult {
     pub minor_plan: Vec<PlanCourseInput>,
 }
 
-fn build_client() -> Result<reqwest::Client, String> {
-    let headers = [
-        ("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36"),
-        ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"),
-        ("Accept-Language", "zh-CN,zh-TW;q=0.9,zh-HK;q=0.8,zh;q=0.7"),
-    ];
-
-    let mut default_headers = HeaderMap::new();
-    for (key, value) in &headers {
-        let header_name = HeaderName::from_bytes(key.as_bytes())
-            .map_err(|e| format!("Invalid header name {}: {}", key, e))?;
-        let header_value =
-            HeaderValue::from_str(value).map_err(|e| format!("Invalid header value: {}", e))?;
-        default_headers.insert(header_name, header_value);
-    }
-
-    reqwest::Client::builder()
-        .default_headers(default_headers)
-        .cookie_store(true)
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))
-}
-
-fn encode_login_params(username: &str, password: &str) -> String {
-    let username_encoded = general_purpose::STANDARD.encode(username.as_bytes());
-    let password_encoded = general_purpose::STANDARD.encode(password.as_bytes());
-    format!("{}%%%{}", username_encoded, password_encoded)
-}
-
-async fn login(username: &str, password: &str, client: &reqwest::Client) -> Result<(), String> {
-    let encoded_value = encode_login_params(username, password);
-    let params = [
-        ("userAccount", username),
-        ("userPassword", ""),
-        ("encoded", encoded_value.as_str()),
-        ("pwdstr1", ""),
-        ("pwdstr2", ""),
-    ];
-
-    let response = client
-        .post(LOGIN_URL)
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| format!("Login request failed: {}", e))?;
-
-    let text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read login response: {}", e))?;
-
-    if text.contains("用户名或密码错误") {
-        return Err("用户名或密码错误".to_string());
-    }
-    if text.contains("验证码") {
-        return Err("登录需要验证码".to_string());
-    }
-    Ok(())
-}
-
 fn normalize_text(value: &str) -> String {
     value
         .replace('\u{a0}', " ")
@@ -98,12 +39,12 @@ fn parse_grades(
     html: &str,
     minor_codes: &HashSet<String>,
     minor_names: &HashSet<String>,
-) -> Result<Vec<GradeRecordInput>, String> {
+) -> Result<Vec<GradeRecordInput>> {
     let document = Html::parse_document(html);
-    let row_selector = Selector::parse("#dataList tr")
-        .map_err(|e| format!("Invalid selector: {}", e))?;
-    let cell_selector = Selector::parse("td")
-        .map_err(|e| format!("Invalid selector: {}", e))?;
+    let row_selector =
+        Selector::parse("#dataList tr").map_err(|e| ToolboxError::internal("选择器无效", e))?;
+    let cell_selector =
+        Selector::parse("td").map_err(|e| ToolboxError::internal("选择器无效", e))?;
 
     let mut results = Vec::new();
     for row in document.select(&row_selector) {
@@ -162,12 +103,12 @@ fn parse_grades(
     Ok(results)
 }
 
-fn parse_plan_courses(html: &str, is_minor: bool) -> Result<Vec<PlanCourseInput>, String> {
+fn parse_plan_courses(html: &str, is_minor: bool) -> Result<Vec<PlanCourseInput>> {
     let document = Html::parse_document(html);
-    let row_selector = Selector::parse("#dataList tr")
-        .map_err(|e| format!("Invalid selector: {}", e))?;
-    let cell_selector = Selector::parse("td")
-        .map_err(|e| format!("Invalid selector: {}", e))?;
+    let row_selector =
+        Selector::parse("#dataList tr").map_err(|e| ToolboxError::internal("选择器无效", e))?;
+    let cell_selector =
+        Selector::parse("td").map_err(|e| ToolboxError::internal("选择器无效", e))?;
     let mut results = Vec::new();
     for row in document.select(&row_selector) {
         let cells: Vec<String> = row
@@ -214,31 +155,21 @@ fn parse_plan_courses(html: &str, is_minor: bool) -> Result<Vec<PlanCourseInput>
 }
 
 async fn fetch_plan_courses(
-    client: &reqwest::Client,
+    session: &mut JwxtSession,
     url: &str,
     is_minor: bool,
-) -> Result<Vec<PlanCourseInput>, String> {
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| format!("Plan request failed: {}", e))?;
-    let text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read plan response: {}", e))?;
+) -> Result<Vec<PlanCourseInput>> {
+    let text = session.get(url).await?;
     parse_plan_courses(&text, is_minor)
 }
 
-pub async fn fetch_grades(
-    username: &str,
-    password: &str,
-) -> Result<GradeFetchResult, String> {
-    let client = build_client()?;
-    login(username, password, &client).await?;
+pub async fn fetch_grades(session: &mut JwxtSession) -> Result<GradeFetchResult> {
+    session.ensure_login().await?;
 
-    let minor_plan = fetch_plan_courses(&client, MINOR_URL, true).await.unwrap_or_default();
-    let major_plan = fetch_plan_courses(&client, MAJOR_URL, false).await.unwrap_or_default();
+    let profile = session.profile().clone();
+    // 培养方案取不到时整次同步失败，不能当作空方案去覆盖已保存的方案和辅修标记。
+    let minor_plan = fetch_plan_courses(session, &profile.minor_plan_url(), true).await?;
+    let major_plan = fetch_plan_courses(session, &profile.major_plan_url(), false).await?;
     let mut minor_codes_set = HashSet::new();
     let mut minor_names_set = HashSet::new();
     for item in &minor_plan {
@@ -249,18 +180,14 @@ pub async fn fetch_grades(
     let mut minor_names: Vec<String> = minor_names_set.iter().cloned().collect();
     minor_codes.sort();
     minor_names.sort();
-    let params = [("kksj", ""), ("kcxz", ""), ("kcmc", ""), ("xsfs", "all")];
-    let response = client
-        .post(GRADES_URL)
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| format!("Grade request failed: {}", e))?;
-
-    let text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read grade response: {}", e))?;
+    let fields = &profile.fields;
+    let params = [
+        (fields.grade_term.as_str(), ""),
+        (fields.grade_nature.as_str(), ""),
+        (fields.grade_course_name.as_str(), ""),
+        (fields.grade_display.as_str(), fields.grade_display_value.as_str()),
+    ];
+    let text = session.post_form(&profile.grades_url(), &params).await?;
 
     let grades = parse_grades(&text, &minor_codes_set, &minor_names_set)?;
     Ok(GradeFetchResult {
@@ -271,3 +198,58 @@ pub async fn fetch_grades(
         minor_plan,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::assert_golden;
+
+    fn minor_sets(codes: &[&str], names: &[&str]) -> (HashSet<String>, HashSet<String>) {
+        (
+            codes.iter().map(|v| v.to_string()).collect(),
+            names.iter().map(|v| v.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn parses_grade_list_fixture() {
+        let html = include_str!("../tests/fixtures/jwxt/cjcx_list.html");
+        let (codes, names) = minor_sets(&["F0901001"], &[]);
+        let grades = parse_grades(html, &codes, &names).unwrap();
+        assert_golden(&grades, include_str!("../tests/fixtures/jwxt/cjcx_list.golden.json"));
+    }
+
+    #[test]
+    fn grade_minor_flag_matches_course_name() {
+        let html = include_str!("../tests/fixtures/jwxt/cjcx_list.html");
+        let (codes, names) = minor_sets(&[], &["金融学原理"]);
+        let grades = parse_grades(html, &codes, &names).unwrap();
+        let minors = grades
+            .iter()
+            .filter(|g| g.is_minor)
+            .map(|g| g.course_code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(minors, vec!["F0901001"]);
+    }
+
+    #[test]
+    fn parses_empty_grade_list() {
+        let html = include_str!("../tests/fixtures/jwxt/cjcx_list_empty.html");
+        let grades = parse_grades(html, &HashSet::new(), &HashSet::new()).unwrap();
+        assert!(grades.is_empty());
+    }
+
+    #[test]
+    fn parses_major_plan_fixture() {
+        let html = include_str!("../tests/fixtures/jwxt/pyfa_query.html");
+        let courses = parse_plan_courses(html, false).unwrap();
+        assert_golden(&courses, include_str!("../tests/fixtures/jwxt/pyfa_query.golden.json"));
+    }
+
+    #[test]
+    fn parses_minor_plan_fixture() {
+        let html = include_str!("../tests/fixtures/jwxt/fxpyfa_query.html");
+        let courses = parse_plan_courses(html, true).unwrap();
+        assert_golden(&courses, include_str!("../tests/fixtures/jwxt/fxpyfa_query.golden.json"));
+    }
+}
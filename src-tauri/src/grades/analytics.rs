@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::db::GradeRecord;
+use crate::error::{Result, ToolboxError};
+
+const UNCLASSIFIED_NATURE: &str = "未分类";
+
+// 等级制成绩对应的百分制分数与绩点。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradeLevel {
+    pub label: String,
+    pub score: f32,
+    pub gpa: f32,
+}
+
+// 成绩换算规则。百分制成绩绩点 = (成绩 - gpa_offset) / 10，不及格记 0；
+// 补考通过的绩点不超过 makeup_gpa_cap。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GradeScale {
+    pub levels: Vec<GradeLevel>,
+    pub pass_score: f32,
+    pub gpa_offset: f32,
+    pub makeup_gpa_cap: Option<f32>,
+}
+
+impl Default for GradeScale {
+    fn default() -> Self {
+        let levels = [
+            ("优秀", 95.0, 4.5),
+            ("优", 95.0, 4.5),
+            ("良好", 85.0, 3.5),
+            ("良", 85.0, 3.5),
+            ("中等", 75.0, 2.5),
+            ("中", 75.0, 2.5),
+            ("及格", 65.0, 1.5),
+            ("合格", 65.0, 1.5),
+            ("通过", 65.0, 1.5),
+            ("不及格", 0.0, 0.0),
+            ("不合格", 0.0, 0.0),
+        ];
+        GradeScale {
+            levels: levels
+                .iter()
+                .map(|(label, score, gpa)| GradeLevel {
+                    label: label.to_string(),
+                    score: *score,
+                    gpa: *gpa,
+                })
+                .collect(),
+            pass_score: 60.0,
+            gpa_offset: 50.0,
+            makeup_gpa_cap: Some(1.0),
+        }
+    }
+}
+
+impl GradeScale {
+    pub fn validate(&self) -> Result<()> {
+        if self
+            .levels
+            .iter()
+            .any(|level| level.label.trim().is_empty())
+        {
+            return Err(ToolboxError::InvalidInput(
+                "成绩等级名称不能为空".to_string(),
+            ));
+        }
+        if self
+            .levels
+            .iter()
+            .any(|level| level.score < 0.0 || level.gpa < 0.0)
+        {
+            return Err(ToolboxError::InvalidInput(
+                "成绩等级的分数和绩点不能为负".to_string(),
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.pass_score) {
+            return Err(ToolboxError::InvalidInput(
+                "及格线需在 0 到 100 之间".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // 成绩为空或无法换算时返回 None。
+    pub fn passed(&self, record: &GradeRecord) -> Option<bool> {
+        self.evaluate(record).map(|grade| grade.passed)
+    }
+
+    fn evaluate(&self, record: &GradeRecord) -> Option<Evaluated> {
+        let raw = record.score.as_deref()?.trim();
+        let (score, gpa) = match raw.parse::<f64>() {
+            Ok(score) if score >= self.pass_score as f64 => {
+                (score, (score - self.gpa_offset as f64).max(0.0) / 10.0)
+            }
+            Ok(score) => (score, 0.0),
+            // 不在映射表里的文本（如“缓考”）不参与统计
+            Err(_) => {
+                let level = self.levels.iter().find(|level| level.label == raw)?;
+                (level.score as f64, level.gpa as f64)
+            }
+        };
+        let passed = score >= self.pass_score as f64;
+        let is_makeup = is_makeup(record);
+        let gpa = match self.makeup_gpa_cap {
+            Some(cap) if is_makeup => gpa.min(cap as f64),
+            _ => gpa,
+        };
+        Some(Evaluated {
+            score,
+            gpa,
+            passed,
+            credit: record.credit.unwrap_or(0.0).max(0.0) as f64,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeStats {
+    pub course_count: usize,
+    pub failed_count: usize,
+    pub attempted_credits: f64,
+    pub earned_credits: f64,
+    pub weighted_gpa: Option<f64>,
+    pub mean_score: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeBreakdown {
+    pub overall: GradeStats,
+    pub major: GradeStats,
+    pub minor: GradeStats,
+    pub by_nature: BTreeMap<String, GradeStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TermGradeSummary {
+    pub term: String,
+    #[serde(flatten)]
+    pub breakdown: GradeBreakdown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeSummary {
+    pub username: String,
+    pub terms: Vec<TermGradeSummary>,
+    pub cumulative: GradeBreakdown,
+}
+
+pub(crate) struct Evaluated {
+    pub(crate) score: f64,
+    pub(crate) gpa: f64,
+    passed: bool,
+    credit: f64,
+}
+
+#[derive(Default)]
+struct StatsAccumulator {
+    course_count: usize,
+    failed_count: usize,
+    attempted_credits: f64,
+    earned_credits: f64,
+    gpa_points: f64,
+    score_sum: f64,
+}
+
+impl StatsAccumulator {
+    fn add(&mut self, grade: &Evaluated) {
+        self.course_count += 1;
+        self.attempted_credits += grade.credit;
+        self.gpa_points += grade.gpa * grade.credit;
+        self.score_sum += grade.score;
+        if grade.passed {
+            self.earned_credits += grade.credit;
+        } else {
+            self.failed_count += 1;
+        }
+    }
+
+    fn finish(&self) -> GradeStats {
+        GradeStats {
+            course_count: self.course_count,
+            failed_count: self.failed_count,
+            attempted_credits: round(self.attempted_credits, 2),
+            earned_credits: round(self.earned_credits, 2),
+            weighted_gpa: (self.attempted_credits > 0.0)
+                .then(|| round(self.gpa_points / self.attempted_credits, 3)),
+            mean_score: (self.course_count > 0)
+                .then(|| round(self.score_sum / self.course_count as f64, 2)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BreakdownAccumulator {
+    overall: StatsAccumulator,
+    major: StatsAccumulator,
+    minor: StatsAccumulator,
+    by_nature: BTreeMap<String, StatsAccumulator>,
+}
+
+impl BreakdownAccumulator {
+    fn add(&mut self, record: &GradeRecord, grade: &Evaluated) {
+        self.overall.add(grade);
+        if record.is_minor {
+            self.minor.add(grade);
+        } else {
+            self.major.add(grade);
+        }
+        let nature = record
+            .course_nature
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(UNCLASSIFIED_NATURE);
+        self.by_nature
+            .entry(nature.to_string())
+            .or_default()
+            .add(grade);
+    }
+
+    fn finish(&self) -> GradeBreakdown {
+        GradeBreakdown {
+            overall: self.overall.finish(),
+            major: self.major.finish(),
+            minor: self.minor.finish(),
+            by_nature: self
+                .by_nature
+                .iter()
+                .map(|(nature, stats)| (nature.clone(), stats.finish()))
+                .collect(),
+        }
+    }
+}
+
+pub(crate) fn round(value: f64, digits: i32) -> f64 {
+    let factor = 10f64.powi(digits);
+    (value * factor).round() / factor
+}
+
+fn is_makeup(record: &GradeRecord) -> bool {
+    record
+        .makeup_term
+        .as_deref()
+        .is_some_and(|term| !term.trim().is_empty())
+}
+
+// 补考成绩挂在原开课学期下，实际考试发生在 makeup_term。
+pub(crate) fn effective_term(record: &GradeRecord) -> &str {
+    match record.makeup_term.as_deref().map(str::trim) {
+        Some(term) if !term.is_empty() => term,
+        _ => record.term.as_str(),
+    }
+}
+
+fn is_better(candidate: &Evaluated, current: &Evaluated) -> bool {
+    (candidate.passed, candidate.gpa, candidate.score)
+        > (current.passed, current.gpa, current.score)
+}
+
+// 按课程代码取每门课最好的一次考试，无法换算的成绩不参与。
+pub(crate) fn best_attempts<'a>(
+    records: &'a [GradeRecord],
+    scale: &GradeScale,
+) -> BTreeMap<&'a str, (&'a GradeRecord, Evaluated)> {
+    let mut best: BTreeMap<&str, (&GradeRecord, Evaluated)> = BTreeMap::new();
+    for record in records {
+        let Some(grade) = scale.evaluate(record) else {
+            continue;
+        };
+        match best.get(record.course_code.as_str()) {
+            Some((_, current)) if !is_better(&grade, current) => {}
+            _ => {
+                best.insert(record.course_code.as_str(), (record, grade));
+            }
+        }
+    }
+    best
+}
+
+// 学期统计按实际考试学期计入每一次考试；累计统计中同一课程多次考试只取最好的一次，
+// 避免补考、重修后学分被重复计算。
+pub fn summarize(username: &str, records: &[GradeRecord], scale: &GradeScale) -> GradeSummary {
+    let mut terms: BTreeMap<String, BreakdownAccumulator> = BTreeMap::new();
+    for record in records {
+        let Some(grade) = scale.evaluate(record) else {
+            continue;
+        };
+        terms
+            .entry(effective_term(record).to_string())
+            .or_default()
+            .add(record, &grade);
+    }
+
+    let mut cumulative = BreakdownAccumulator::default();
+    for (record, grade) in best_attempts(records, scale).values() {
+        cumulative.add(record, grade);
+    }
+
+    GradeSummary {
+        username: username.to_string(),
+        terms: terms
+            .iter()
+            .map(|(term, stats)| TermGradeSummary {
+                term: term.clone(),
+                breakdown: stats.finish(),
+            })
+            .collect(),
+        cumulative: cumulative.finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::grade_record;
+
+    // 与 cjcx_list fixture 相同的六条成绩，其中大学物理B 挂科后补考通过。
+    fn fixture_records() -> Vec<GradeRecord> {
+        vec![
+            grade_record("2023-2024-1", "B0101001", "92")
+                .credit(5.0)
+                .nature("学科基础课")
+                .build(),
+            grade_record("2023-2024-1", "B0201002", "优秀")
+                .credit(3.5)
+                .nature("通识教育课")
+                .build(),
+            grade_record("2023-2024-2", "B0301003", "不及格")
+                .credit(3.0)
+                .nature("学科基础课")
+                .build(),
+            grade_record("2023-2024-2", "B0301003", "61")
+                .credit(3.0)
+                .nature("学科基础课")
+                .makeup_term("2024-2025-1")
+                .build(),
+            grade_record("2024-2025-1", "F0901001", "合格")
+                .nature("专业课")
+                .minor()
+                .build(),
+            grade_record("2024-2025-1", "T1001001", "78.5")
+                .credit(1.5)
+                .nature("通识教育选修课")
+                .build(),
+        ]
+    }
+
+    #[test]
+    fn term_stats_place_makeup_in_makeup_term() {
+        let summary = summarize("2023001", &fixture_records(), &GradeScale::default());
+        let terms = summary
+            .terms
+            .iter()
+            .map(|t| t.term.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec!["2023-2024-1", "2023-2024-2", "2024-2025-1"]);
+
+        let first = &summary.terms[0].breakdown.overall;
+        assert_eq!(first.earned_credits, 8.5);
+        assert_eq!(first.weighted_gpa, Some(4.324));
+        assert_eq!(first.mean_score, Some(93.5));
+
+        let failed = &summary.terms[1].breakdown.overall;
+        assert_eq!(
+            (failed.attempted_credits, failed.earned_credits),
+            (3.0, 0.0)
+        );
+        assert_eq!(failed.failed_count, 1);
+
+        let makeup = &summary.terms[2].breakdown;
+        assert_eq!(makeup.overall.weighted_gpa, Some(1.581));
+        assert_eq!(makeup.major.weighted_gpa, Some(1.617));
+        assert_eq!(makeup.minor.weighted_gpa, Some(1.5));
+    }
+
+    #[test]
+    fn cumulative_counts_each_course_once() {
+        let summary = summarize("2023001", &fixture_records(), &GradeScale::default());
+        let total = &summary.cumulative;
+        assert_eq!(total.overall.course_count, 5);
+        assert_eq!(total.overall.failed_count, 0);
+        assert_eq!(total.overall.attempted_credits, 15.0);
+        assert_eq!(total.overall.earned_credits, 15.0);
+        assert_eq!(total.overall.weighted_gpa, Some(3.135));
+        assert_eq!(total.overall.mean_score, Some(78.3));
+        assert_eq!(total.major.weighted_gpa, Some(3.387));
+        assert_eq!(total.minor.earned_credits, 2.0);
+        assert_eq!(total.by_nature["学科基础课"].earned_credits, 8.0);
+        assert_eq!(total.by_nature["专业课"].course_count, 1);
+    }
+
+    #[test]
+    fn custom_scale_changes_level_points() {
+        let mut scale = GradeScale::default();
+        for level in scale.levels.iter_mut().filter(|l| l.label == "优秀") {
+            level.gpa = 4.0;
+        }
+        scale.makeup_gpa_cap = None;
+        let records = vec![
+            grade_record("2023-2024-1", "B0201002", "优秀")
+                .nature("通识教育课")
+                .build(),
+            grade_record("2023-2024-1", "B0301003", "70")
+                .nature("学科基础课")
+                .makeup_term("2023-2024-2")
+                .build(),
+            grade_record("2023-2024-1", "X0000001", "缓考")
+                .nature("学科基础课")
+                .build(),
+        ];
+        let summary = summarize("2023001", &records, &scale);
+        assert_eq!(summary.cumulative.overall.course_count, 2);
+        assert_eq!(summary.cumulative.overall.weighted_gpa, Some(3.0));
+
+        scale.levels[0].label = " ".to_string();
+        assert_eq!(scale.validate().unwrap_err().code(), "invalid_input");
+    }
+}
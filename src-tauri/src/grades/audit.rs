@@ -0,0 +1,438 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::analytics::{effective_term, round, GradeScale};
+use crate::db::{GradeRecord, GraduationRequirement, PlanCourse};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementBucket {
+    Required,
+    Elective,
+    General,
+    Minor,
+}
+
+impl RequirementBucket {
+    pub const ALL: [RequirementBucket; 4] = [
+        RequirementBucket::Required,
+        RequirementBucket::Elective,
+        RequirementBucket::General,
+        RequirementBucket::Minor,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RequirementBucket::Required => "required",
+            RequirementBucket::Elective => "elective",
+            RequirementBucket::General => "general",
+            RequirementBucket::Minor => "minor",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|bucket| bucket.as_str() == value)
+    }
+
+    // 辅修课单独成类；课程性质带“通识”的归通识教育，其余按必修/选修属性划分。
+    fn classify(
+        is_minor: bool,
+        course_nature: Option<&str>,
+        course_attr: Option<&str>,
+        general_type: Option<&str>,
+    ) -> Self {
+        let nature = course_nature.unwrap_or_default();
+        if is_minor {
+            RequirementBucket::Minor
+        } else if nature.contains("通识") || general_type.is_some_and(|v| !v.trim().is_empty()) {
+            RequirementBucket::General
+        } else {
+            match course_attr.map(str::trim) {
+                Some(attr) if attr.contains("必修") => RequirementBucket::Required,
+                Some(attr) if !attr.is_empty() => RequirementBucket::Elective,
+                _ if nature.contains("选修") => RequirementBucket::Elective,
+                _ => RequirementBucket::Required,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutstandingCourse {
+    pub term: String,
+    pub course_code: String,
+    pub course_name: String,
+    pub credit: f64,
+    pub bucket: RequirementBucket,
+    pub failed: bool,
+}
+
+// 选修、通识的培养方案列的是可选课程池，总学分不等于毕业要求，未手动设置时要求学分
+// 及缺口均为空，表示未知。
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketAudit {
+    pub bucket: RequirementBucket,
+    pub required_credits: Option<f64>,
+    pub custom_requirement: bool,
+    pub earned_credits: f64,
+    pub planned_credits: f64,
+    pub shortfall: Option<f64>,
+    pub projected_shortfall: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TermPlan {
+    pub term: String,
+    pub credits: f64,
+    pub courses: Vec<OutstandingCourse>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraduationAudit {
+    pub username: String,
+    pub last_graded_term: Option<String>,
+    pub buckets: Vec<BucketAudit>,
+    pub overdue: Vec<OutstandingCourse>,
+    pub term_plan: Vec<TermPlan>,
+    // 有类别缺口时为 false；其余类别中有要求未知的为空
+    pub can_graduate_on_time: Option<bool>,
+}
+
+// 对照培养方案核算各类学分。已通过（含补考通过）的课程按课程代码去重计入已修学分；
+// 挂科或开课学期已过仍未修的必修课列为逾期，需要重修，不计入按期毕业的预估。
+// 开课学期已过而没选的选修课只是没选，不算逾期。
+pub fn audit(
+    username: &str,
+    plan: &[PlanCourse],
+    records: &[GradeRecord],
+    requirements: &[GraduationRequirement],
+    scale: &GradeScale,
+) -> GraduationAudit {
+    let mut earned: HashMap<RequirementBucket, f64> = HashMap::new();
+    let mut passed_codes = HashSet::new();
+    let mut passed_names = HashSet::new();
+    let mut failed_codes = HashSet::new();
+    let mut last_graded_term: Option<&str> = None;
+
+    for record in records {
+        let Some(passed) = scale.passed(record) else {
+            continue;
+        };
+        let term = effective_term(record);
+        if Some(term) > last_graded_term {
+            last_graded_term = Some(term);
+        }
+        if !passed {
+            failed_codes.insert(record.course_code.as_str());
+            continue;
+        }
+        passed_names.insert(record.course_name.as_str());
+        if passed_codes.insert(record.course_code.as_str()) {
+            let bucket = RequirementBucket::classify(
+                record.is_minor,
+                record.course_nature.as_deref(),
+                record.course_attr.as_deref(),
+                record.general_type.as_deref(),
+            );
+            *earned.entry(bucket).or_default() += record.credit.unwrap_or(0.0).max(0.0) as f64;
+        }
+    }
+
+    let mut plan_credits: HashMap<RequirementBucket, f64> = HashMap::new();
+    let mut planned: HashMap<RequirementBucket, f64> = HashMap::new();
+    let mut overdue = Vec::new();
+    let mut terms: BTreeMap<String, Vec<OutstandingCourse>> = BTreeMap::new();
+
+    for course in plan {
+        let bucket = RequirementBucket::classify(
+            course.is_minor,
+            course.course_nature.as_deref(),
+            course.course_attr.as_deref(),
+            None,
+        );
+        let credit = course.credit.unwrap_or(0.0).max(0.0) as f64;
+        *plan_credits.entry(bucket).or_default() += credit;
+        if passed_codes.contains(course.course_code.as_str())
+            || passed_names.contains(course.course_name.as_str())
+        {
+            continue;
+        }
+        let failed = failed_codes.contains(course.course_code.as_str());
+        let elective = matches!(
+            bucket,
+            RequirementBucket::Elective | RequirementBucket::General
+        ) && !course
+            .course_attr
+            .as_deref()
+            .is_some_and(|attr| attr.contains("必修"));
+        let past = last_graded_term.is_some_and(|last| course.term.as_str() <= last);
+        if elective && past && !failed {
+            continue;
+        }
+        let outstanding = OutstandingCourse {
+            term: course.term.clone(),
+            course_code: course.course_code.clone(),
+            course_name: course.course_name.clone(),
+            credit,
+            bucket,
+            failed,
+        };
+        if failed || past {
+            overdue.push(outstanding);
+        } else {
+            *planned.entry(bucket).or_default() += credit;
+            terms
+                .entry(course.term.clone())
+                .or_default()
+                .push(outstanding);
+        }
+    }
+
+    let overrides: HashMap<RequirementBucket, f64> = requirements
+        .iter()
+        .filter_map(|req| {
+            RequirementBucket::parse(&req.bucket).map(|bucket| (bucket, req.credits as f64))
+        })
+        .collect();
+
+    let buckets = RequirementBucket::ALL
+        .into_iter()
+        .map(|bucket| {
+            let custom = overrides.get(&bucket).copied();
+            let required = match bucket {
+                RequirementBucket::Required | RequirementBucket::Minor => Some(
+                    custom.unwrap_or_else(|| plan_credits.get(&bucket).copied().unwrap_or(0.0)),
+                ),
+                RequirementBucket::Elective | RequirementBucket::General => custom,
+            };
+            let earned = earned.get(&bucket).copied().unwrap_or(0.0);
+            let planned = planned.get(&bucket).copied().unwrap_or(0.0);
+            BucketAudit {
+                bucket,
+                required_credits: required.map(|v| round(v, 2)),
+                custom_requirement: custom.is_some(),
+                earned_credits: round(earned, 2),
+                planned_credits: round(planned, 2),
+                shortfall: required.map(|v| round((v - earned).max(0.0), 2)),
+                projected_shortfall: required.map(|v| round((v - earned - planned).max(0.0), 2)),
+            }
+        })
+        .collect::<Vec<_>>();
+    let can_graduate_on_time = if buckets
+        .iter()
+        .any(|b| b.projected_shortfall.is_some_and(|v| v > 0.0))
+    {
+        Some(false)
+    } else if buckets.iter().any(|b| b.projected_shortfall.is_none()) {
+        None
+    } else {
+        Some(true)
+    };
+
+    GraduationAudit {
+        username: username.to_string(),
+        last_graded_term: last_graded_term.map(str::to_string),
+        can_graduate_on_time,
+        buckets,
+        overdue,
+        term_plan: terms
+            .into_iter()
+            .map(|(term, courses)| TermPlan {
+                term,
+                credits: round(courses.iter().map(|c| c.credit).sum(), 2),
+                courses,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::grade_record;
+
+    fn plan_course(
+        term: &str,
+        code: &str,
+        credit: f32,
+        nature: Option<&str>,
+        attr: &str,
+        is_minor: bool,
+    ) -> PlanCourse {
+        PlanCourse {
+            id: 0,
+            term: term.to_string(),
+            course_code: code.to_string(),
+            course_name: code.to_string(),
+            credit: Some(credit),
+            total_hours: None,
+            exam_mode: None,
+            course_nature: nature.map(str::to_string),
+            course_attr: Some(attr.to_string()),
+            is_minor,
+        }
+    }
+
+    // 与 pyfa_query / fxpyfa_query fixture 一致的培养方案。
+    fn fixture_plan() -> Vec<PlanCourse> {
+        vec![
+            plan_course(
+                "2023-2024-1",
+                "B0101001",
+                5.0,
+                Some("学科基础课"),
+                "必修",
+                false,
+            ),
+            plan_course(
+                "2023-2024-1",
+                "B0201002",
+                3.5,
+                Some("通识教育课"),
+                "必修",
+                false,
+            ),
+            plan_course(
+                "2024-2025-2",
+                "C0501010",
+                2.0,
+                Some("实践环节"),
+                "必修",
+                false,
+            ),
+            plan_course(
+                "2025-2026-1",
+                "C0502020",
+                2.5,
+                Some("专业选修课"),
+                "选修",
+                false,
+            ),
+            plan_course("2024-2025-1", "F0901001", 2.0, None, "必修", true),
+            plan_course("2024-2025-2", "F0901002", 3.0, None, "必修", true),
+        ]
+    }
+
+    fn fixture_grades() -> Vec<GradeRecord> {
+        vec![
+            grade_record("2023-2024-1", "B0101001", "92")
+                .credit(5.0)
+                .nature("学科基础课")
+                .attr("必修")
+                .build(),
+            grade_record("2023-2024-1", "B0201002", "优秀")
+                .credit(3.5)
+                .nature("通识教育课")
+                .attr("必修")
+                .build(),
+            grade_record("2023-2024-2", "B0301003", "不及格")
+                .credit(3.0)
+                .nature("学科基础课")
+                .attr("必修")
+                .build(),
+            grade_record("2023-2024-2", "B0301003", "61")
+                .credit(3.0)
+                .nature("学科基础课")
+                .attr("必修")
+                .makeup_term("2024-2025-1")
+                .build(),
+            grade_record("2024-2025-1", "F0901001", "合格")
+                .nature("专业课")
+                .attr("任选")
+                .minor()
+                .build(),
+            grade_record("2024-2025-1", "T1001001", "78.5")
+                .credit(1.5)
+                .nature("通识教育选修课")
+                .attr("公选")
+                .build(),
+        ]
+    }
+
+    fn bucket(audit: &GraduationAudit, bucket: RequirementBucket) -> &BucketAudit {
+        audit.buckets.iter().find(|b| b.bucket == bucket).unwrap()
+    }
+
+    #[test]
+    fn remaining_plan_covers_every_bucket() {
+        let scale = GradeScale::default();
+        let result = audit("2023001", &fixture_plan(), &fixture_grades(), &[], &scale);
+        assert_eq!(result.last_graded_term.as_deref(), Some("2024-2025-1"));
+        assert!(result.overdue.is_empty());
+        // 选修、通识未设置要求，无法判断
+        assert_eq!(result.can_graduate_on_time, None);
+
+        let required = bucket(&result, RequirementBucket::Required);
+        assert_eq!(
+            (required.required_credits, required.earned_credits),
+            (Some(7.0), 8.0)
+        );
+        let general = bucket(&result, RequirementBucket::General);
+        assert_eq!(general.earned_credits, 5.0);
+        assert_eq!((general.required_credits, general.shortfall), (None, None));
+        let minor = bucket(&result, RequirementBucket::Minor);
+        assert_eq!((minor.shortfall, minor.planned_credits), (Some(3.0), 3.0));
+        assert_eq!(minor.projected_shortfall, Some(0.0));
+
+        let plan = result
+            .term_plan
+            .iter()
+            .map(|t| (t.term.as_str(), t.credits, t.courses.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(plan, vec![("2024-2025-2", 5.0, 2), ("2025-2026-1", 2.5, 1)]);
+    }
+
+    #[test]
+    fn failed_course_and_custom_requirement_block_graduation() {
+        let mut plan = fixture_plan();
+        plan.push(plan_course(
+            "2023-2024-2",
+            "B0301003",
+            3.0,
+            Some("学科基础课"),
+            "必修",
+            false,
+        ));
+        // 开课学期已过但没选的选修课不算逾期
+        plan.push(plan_course(
+            "2023-2024-2",
+            "C0503030",
+            2.0,
+            Some("专业选修课"),
+            "选修",
+            false,
+        ));
+        let grades = fixture_grades()
+            .into_iter()
+            .filter(|g| g.makeup_term.is_none())
+            .collect::<Vec<_>>();
+        let requirements = vec![GraduationRequirement {
+            bucket: "elective".to_string(),
+            credits: 6.0,
+        }];
+        let result = audit(
+            "2023001",
+            &plan,
+            &grades,
+            &requirements,
+            &GradeScale::default(),
+        );
+
+        assert_eq!(result.can_graduate_on_time, Some(false));
+        let overdue = result
+            .overdue
+            .iter()
+            .map(|c| (c.course_code.as_str(), c.failed))
+            .collect::<Vec<_>>();
+        assert_eq!(overdue, vec![("B0301003", true)]);
+
+        let required = bucket(&result, RequirementBucket::Required);
+        assert_eq!(required.required_credits, Some(10.0));
+        assert_eq!(required.projected_shortfall, Some(3.0));
+        let elective = bucket(&result, RequirementBucket::Elective);
+        assert!(elective.custom_requirement);
+        assert_eq!(elective.projected_shortfall, Some(3.5));
+    }
+}
@@ -0,0 +1,234 @@
+use serde::Serialize;
+
+use super::analytics::{self, best_attempts, effective_term, round, GradeScale};
+use crate::db::GradeRecord;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedUser {
+    pub username: String,
+    pub display_name: Option<String>,
+}
+
+// 差值均相对第一个账号，第一个账号自身的差值为空。
+#[derive(Debug, Clone, Serialize)]
+pub struct CourseResult {
+    pub username: String,
+    pub term: String,
+    pub score: Option<String>,
+    pub score_value: f64,
+    pub gpa: f64,
+    pub score_delta: Option<f64>,
+    pub gpa_delta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedCourse {
+    pub course_code: String,
+    pub course_name: String,
+    pub credit: Option<f32>,
+    pub results: Vec<CourseResult>,
+}
+
+// term 为该账号第 n 个学期对应的实际学期，还没读到第 n 个学期时为空。
+#[derive(Debug, Clone, Serialize)]
+pub struct TermGpa {
+    pub username: String,
+    pub term: Option<String>,
+    pub weighted_gpa: Option<f64>,
+    pub gpa_delta: Option<f64>,
+}
+
+// 不同年级的账号按在读的第几个学期对齐，从各自第一个有成绩的学期算起。
+#[derive(Debug, Clone, Serialize)]
+pub struct TermTrend {
+    pub term_of_study: usize,
+    pub users: Vec<TermGpa>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeComparison {
+    pub users: Vec<ComparedUser>,
+    pub courses: Vec<SharedCourse>,
+    pub terms: Vec<TermTrend>,
+}
+
+fn delta(value: Option<f64>, baseline: Option<f64>, digits: i32) -> Option<f64> {
+    Some(round(value? - baseline?, digits))
+}
+
+// 共同课程指所有账号都有可换算成绩的课程，每人取该课程最好的一次考试，
+// 按第一个账号的考试学期排列。学期趋势的长度取读过学期最多的账号。
+pub fn compare(users: &[(ComparedUser, Vec<GradeRecord>)], scale: &GradeScale) -> GradeComparison {
+    let best = users
+        .iter()
+        .map(|(_, records)| best_attempts(records, scale))
+        .collect::<Vec<_>>();
+
+    let mut courses = Vec::new();
+    if let Some(first) = best.first() {
+        for (code, (record, baseline)) in first {
+            let attempts = best
+                .iter()
+                .map(|other| other.get(code))
+                .collect::<Option<Vec<_>>>();
+            let Some(attempts) = attempts else {
+                continue;
+            };
+            let results = attempts
+                .iter()
+                .zip(users)
+                .enumerate()
+                .map(|(index, ((record, grade), (user, _)))| {
+                    let is_baseline = index == 0;
+                    CourseResult {
+                        username: user.username.clone(),
+                        term: effective_term(record).to_string(),
+                        score: record.score.clone(),
+                        score_value: grade.score,
+                        gpa: round(grade.gpa, 3),
+                        score_delta: (!is_baseline).then(|| round(grade.score - baseline.score, 2)),
+                        gpa_delta: (!is_baseline).then(|| round(grade.gpa - baseline.gpa, 3)),
+                    }
+                })
+                .collect();
+            courses.push(SharedCourse {
+                course_code: code.to_string(),
+                course_name: record.course_name.clone(),
+                credit: record.credit,
+                results,
+            });
+        }
+    }
+    courses.sort_by(|a, b| {
+        (&a.results[0].term, &a.course_code).cmp(&(&b.results[0].term, &b.course_code))
+    });
+
+    let summaries = users
+        .iter()
+        .map(|(user, records)| analytics::summarize(&user.username, records, scale))
+        .collect::<Vec<_>>();
+    let term_count = summaries
+        .iter()
+        .map(|summary| summary.terms.len())
+        .max()
+        .unwrap_or(0);
+    let terms = (0..term_count)
+        .map(|nth| {
+            let nth_terms = summaries
+                .iter()
+                .map(|summary| summary.terms.get(nth))
+                .collect::<Vec<_>>();
+            let gpa =
+                |index: usize| nth_terms[index].and_then(|t| t.breakdown.overall.weighted_gpa);
+            TermTrend {
+                term_of_study: nth + 1,
+                users: users
+                    .iter()
+                    .zip(&nth_terms)
+                    .enumerate()
+                    .map(|(index, ((user, _), term))| TermGpa {
+                        username: user.username.clone(),
+                        term: term.map(|t| t.term.clone()),
+                        weighted_gpa: gpa(index),
+                        gpa_delta: if index == 0 {
+                            None
+                        } else {
+                            delta(gpa(index), gpa(0), 3)
+                        },
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    GradeComparison {
+        users: users.iter().map(|(user, _)| user.clone()).collect(),
+        courses,
+        terms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::grade_record;
+
+    fn user(username: &str) -> ComparedUser {
+        ComparedUser {
+            username: username.to_string(),
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn lines_up_shared_courses_with_deltas() {
+        let users = vec![
+            (
+                user("2023001"),
+                vec![
+                    grade_record("2023-2024-2", "B0101002", "80")
+                        .credit(5.0)
+                        .build(),
+                    grade_record("2023-2024-1", "B0101001", "90")
+                        .credit(5.0)
+                        .build(),
+                    grade_record("2023-2024-1", "B0201002", "缓考").build(),
+                    grade_record("2023-2024-1", "C0501010", "75")
+                        .credit(5.0)
+                        .build(),
+                ],
+            ),
+            (
+                user("2024002"),
+                vec![
+                    grade_record("2024-2025-1", "B0101001", "55")
+                        .username("2024002")
+                        .credit(5.0)
+                        .build(),
+                    grade_record("2024-2025-2", "B0101001", "70")
+                        .username("2024002")
+                        .credit(5.0)
+                        .build(),
+                    grade_record("2024-2025-1", "B0201002", "88")
+                        .username("2024002")
+                        .build(),
+                    grade_record("2024-2025-2", "B0101002", "85")
+                        .username("2024002")
+                        .credit(5.0)
+                        .build(),
+                ],
+            ),
+        ];
+        let comparison = compare(&users, &GradeScale::default());
+
+        // C0501010 只有一人修过，B0201002 有一人的成绩无法换算，都不算共同课程
+        let codes = comparison
+            .courses
+            .iter()
+            .map(|c| c.course_code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(codes, vec!["B0101001", "B0101002"]);
+
+        let math = &comparison.courses[0].results;
+        assert_eq!((math[0].score_delta, math[0].gpa_delta), (None, None));
+        assert_eq!(math[1].term, "2024-2025-2");
+        assert_eq!(math[1].score.as_deref(), Some("70"));
+        assert_eq!(math[1].score_delta, Some(-20.0));
+        assert_eq!(math[1].gpa_delta, Some(-2.0));
+        assert_eq!(comparison.courses[1].results[1].score_delta, Some(5.0));
+
+        // 两人入学年份不同，按各自的第几个学期对齐
+        assert_eq!(comparison.terms.len(), 2);
+        let first = &comparison.terms[0];
+        assert_eq!(first.term_of_study, 1);
+        let terms = first
+            .users
+            .iter()
+            .map(|u| u.term.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec![Some("2023-2024-1"), Some("2024-2025-1")]);
+        assert_eq!(first.users[0].weighted_gpa, Some(3.25));
+        assert_eq!(first.users[1].weighted_gpa, Some(1.086));
+        assert_eq!(first.users[1].gpa_delta, Some(-2.164));
+    }
+}
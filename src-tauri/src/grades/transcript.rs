@@ -0,0 +1,649 @@
+use printpdf::{
+    IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerIndex, PdfPageIndex,
+};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::analytics::{self, effective_term, GradeScale, GradeStats};
+use crate::db::GradeRecord;
+use crate::error::{Result, ToolboxError};
+
+const COLUMNS: [&str; 7] = [
+    "学期",
+    "课程代码",
+    "课程名称",
+    "学分",
+    "成绩",
+    "绩点",
+    "课程性质",
+];
+
+// 常见系统自带的中文字体，单个 .ttf 优先，其次是字体集 .ttc（嵌入时取其中第一个字体）。
+// printpdf 只能以 TrueType 轮廓嵌入，Noto Sans CJK 这类 CFF 轮廓的字体不在其列。
+const CJK_FONT_CANDIDATES: &[&str] = &[
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\Deng.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Supplemental/Songti.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Csv,
+    Xlsx,
+    Pdf,
+}
+
+impl TranscriptFormat {
+    pub fn from_path(path: &str) -> Result<Self> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("csv") => Ok(TranscriptFormat::Csv),
+            Some("xlsx") => Ok(TranscriptFormat::Xlsx),
+            Some("pdf") => Ok(TranscriptFormat::Pdf),
+            _ => Err(ToolboxError::InvalidInput(
+                "仅支持导出为 .csv、.xlsx 或 .pdf 文件".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+}
+
+impl Cell {
+    fn text(value: impl Into<String>) -> Self {
+        Cell::Text(value.into())
+    }
+
+    fn number(value: Option<f64>) -> Self {
+        value.map_or(Cell::Empty, Cell::Number)
+    }
+
+    fn display(&self) -> String {
+        match self {
+            Cell::Empty => String::new(),
+            Cell::Text(text) => text.clone(),
+            Cell::Number(value) => format!("{}", value),
+        }
+    }
+}
+
+struct TranscriptTerm {
+    term: String,
+    records: Vec<GradeRecord>,
+    stats: GradeStats,
+}
+
+struct TranscriptSection {
+    title: &'static str,
+    terms: Vec<TranscriptTerm>,
+    cumulative: GradeStats,
+}
+
+// 按主修、辅修分节，节内按实际考试学期分组，学期与累计统计均取自 analytics::summarize。
+pub struct Transcript {
+    title: String,
+    sections: Vec<TranscriptSection>,
+    cumulative: GradeStats,
+}
+
+impl Transcript {
+    pub fn new(
+        username: &str,
+        name: Option<&str>,
+        records: &[GradeRecord],
+        scale: &GradeScale,
+    ) -> Self {
+        let summary = analytics::summarize(username, records, scale);
+        let term_stats = summary
+            .terms
+            .iter()
+            .map(|term| (term.term.as_str(), &term.breakdown))
+            .collect::<BTreeMap<_, _>>();
+        let mut sections = Vec::new();
+        for (is_minor, title) in [(false, "主修课程"), (true, "辅修课程")] {
+            let mut grouped: BTreeMap<&str, Vec<GradeRecord>> = BTreeMap::new();
+            for record in records.iter().filter(|r| r.is_minor == is_minor) {
+                grouped
+                    .entry(effective_term(record))
+                    .or_default()
+                    .push(record.clone());
+            }
+            if grouped.is_empty() {
+                continue;
+            }
+            let terms = grouped
+                .into_iter()
+                .map(|(term, mut records)| {
+                    records.sort_by(|a, b| a.course_code.cmp(&b.course_code));
+                    let stats = term_stats.get(term).map(|breakdown| {
+                        if is_minor {
+                            breakdown.minor.clone()
+                        } else {
+                            breakdown.major.clone()
+                        }
+                    });
+                    TranscriptTerm {
+                        term: term.to_string(),
+                        records,
+                        stats: stats.unwrap_or_else(empty_stats),
+                    }
+                })
+                .collect();
+            let cumulative = if is_minor {
+                summary.cumulative.minor.clone()
+            } else {
+                summary.cumulative.major.clone()
+            };
+            sections.push(TranscriptSection {
+                title,
+                terms,
+                cumulative,
+            });
+        }
+        let title = match name.map(str::trim).filter(|v| !v.is_empty()) {
+            Some(name) => format!("成绩单 {}（{}）", name, username),
+            None => format!("成绩单 {}", username),
+        };
+        Transcript {
+            title,
+            sections,
+            cumulative: summary.cumulative.overall,
+        }
+    }
+
+    pub fn render(&self, format: TranscriptFormat, font_path: Option<&str>) -> Result<Vec<u8>> {
+        match format {
+            TranscriptFormat::Csv => Ok(self.to_csv().into_bytes()),
+            TranscriptFormat::Xlsx => self.to_xlsx(),
+            TranscriptFormat::Pdf => self.to_pdf(&load_cjk_font(font_path)?),
+        }
+    }
+
+    fn rows(&self) -> Vec<Vec<Cell>> {
+        let mut rows = vec![vec![Cell::text(&self.title)], Vec::new()];
+        for section in &self.sections {
+            rows.push(vec![Cell::text(section.title)]);
+            rows.push(COLUMNS.iter().map(|c| Cell::text(*c)).collect());
+            for term in &section.terms {
+                for record in &term.records {
+                    let score = record.score.as_deref().unwrap_or_default().trim();
+                    rows.push(vec![
+                        Cell::text(&term.term),
+                        Cell::text(&record.course_code),
+                        Cell::text(&record.course_name),
+                        Cell::number(record.credit.map(|v| v as f64)),
+                        match score.parse::<f64>() {
+                            Ok(value) => Cell::Number(value),
+                            Err(_) if score.is_empty() => Cell::Empty,
+                            Err(_) => Cell::text(score),
+                        },
+                        Cell::number(record.gpa.map(|v| (v as f64 * 100.0).round() / 100.0)),
+                        Cell::text(record.course_nature.clone().unwrap_or_default()),
+                    ]);
+                }
+                rows.push(stats_row(&format!("{} 小计", term.term), &term.stats));
+            }
+            rows.push(stats_row(
+                &format!("{}累计", section.title),
+                &section.cumulative,
+            ));
+            rows.push(Vec::new());
+        }
+        rows.push(stats_row("全部课程累计", &self.cumulative));
+        rows
+    }
+
+    // 带 BOM，Excel 直接打开时按 UTF-8 识别中文。
+    fn to_csv(&self) -> String {
+        let mut out = String::from("\u{feff}");
+        for row in self.rows() {
+            let line = row
+                .iter()
+                .map(|cell| csv_field(&cell.display()))
+                .collect::<Vec<_>>()
+                .join(",");
+            out.push_str(&line);
+            out.push_str("\r\n");
+        }
+        out
+    }
+
+    // 只包含一个工作表的最小 xlsx：文字用 inlineStr 写入，不生成共享字符串表和样式表。
+    fn to_xlsx(&self) -> Result<Vec<u8>> {
+        let mut sheet = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">\
+             <cols><col min=\"1\" max=\"2\" width=\"14\" customWidth=\"1\"/>\
+             <col min=\"3\" max=\"3\" width=\"32\" customWidth=\"1\"/>\
+             <col min=\"7\" max=\"7\" width=\"18\" customWidth=\"1\"/></cols><sheetData>",
+        );
+        for (index, row) in self.rows().iter().enumerate() {
+            let row_number = index + 1;
+            sheet.push_str(&format!("<row r=\"{}\">", row_number));
+            for (column, cell) in row.iter().enumerate() {
+                let reference = format!("{}{}", column_name(column), row_number);
+                match cell {
+                    Cell::Empty => {}
+                    Cell::Text(text) => sheet.push_str(&format!(
+                        "<c r=\"{}\" t=\"inlineStr\"><is><t>{}</t></is></c>",
+                        reference,
+                        escape_xml(text)
+                    )),
+                    Cell::Number(value) => {
+                        sheet.push_str(&format!("<c r=\"{}\"><v>{}</v></c>", reference, value))
+                    }
+                }
+            }
+            sheet.push_str("</row>");
+        }
+        sheet.push_str("</sheetData></worksheet>");
+
+        let parts = [
+            (
+                "[Content_Types].xml",
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                 <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+                 <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+                 <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+                 <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+                 <Override PartName=\"/xl/worksheets/sheet1.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>\
+                 </Types>",
+            ),
+            (
+                "_rels/.rels",
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                 <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+                 <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"xl/workbook.xml\"/>\
+                 </Relationships>",
+            ),
+            (
+                "xl/workbook.xml",
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                 <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+                 xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
+                 <sheets><sheet name=\"成绩单\" sheetId=\"1\" r:id=\"rId1\"/></sheets></workbook>",
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                 <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+                 <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" Target=\"worksheets/sheet1.xml\"/>\
+                 </Relationships>",
+            ),
+            ("xl/worksheets/sheet1.xml", sheet.as_str()),
+        ];
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, content) in parts {
+            zip.start_file(name, options)
+                .map_err(|e| ToolboxError::internal("生成 xlsx 失败", e))?;
+            zip.write_all(content.as_bytes())
+                .map_err(|e| ToolboxError::internal("生成 xlsx 失败", e))?;
+        }
+        let cursor = zip
+            .finish()
+            .map_err(|e| ToolboxError::internal("生成 xlsx 失败", e))?;
+        Ok(cursor.into_inner())
+    }
+
+    fn to_pdf(&self, font_bytes: &[u8]) -> Result<Vec<u8>> {
+        let (doc, page1, layer1) = PdfDocument::new(&self.title, Mm(210.0), Mm(297.0), "L1");
+        let font = doc
+            .add_external_font(font_bytes)
+            .map_err(|e| ToolboxError::internal("PDF 字体初始化失败", e))?;
+        let mut page = PdfPage {
+            doc: &doc,
+            font: &font,
+            current: (page1, layer1),
+            count: 1,
+            y: 280.0,
+        };
+        // 各列左边界（毫米）与课程名称最多显示的字数
+        let columns = [15.0, 40.0, 62.0, 128.0, 142.0, 158.0, 172.0];
+        let name_chars = 24;
+
+        page.line(16.0, 15.0, &self.title);
+        page.y -= 4.0;
+        for row in self.rows().iter().skip(2) {
+            if row.is_empty() {
+                page.y -= 3.0;
+                continue;
+            }
+            if row.len() == 1 {
+                page.y -= 2.0;
+                page.line(12.0, 15.0, &row[0].display());
+                continue;
+            }
+            page.ensure_space();
+            for (column, cell) in row.iter().enumerate() {
+                let mut text = cell.display();
+                if column == 2 && text.chars().count() > name_chars {
+                    text = text.chars().take(name_chars - 1).collect::<String>() + "…";
+                }
+                if !text.is_empty() {
+                    page.text(9.0, columns[column], &text);
+                }
+            }
+            page.y -= 5.5;
+        }
+
+        let mut buf = std::io::BufWriter::new(Cursor::new(Vec::<u8>::new()));
+        doc.save(&mut buf)
+            .map_err(|e| ToolboxError::internal("PDF 生成失败", e))?;
+        let cursor = buf
+            .into_inner()
+            .map_err(|e| ToolboxError::internal("PDF 写入失败", e))?;
+        Ok(cursor.into_inner())
+    }
+}
+
+struct PdfPage<'a> {
+    doc: &'a PdfDocumentReference,
+    font: &'a IndirectFontRef,
+    current: (PdfPageIndex, PdfLayerIndex),
+    count: usize,
+    y: f32,
+}
+
+impl PdfPage<'_> {
+    fn ensure_space(&mut self) {
+        if self.y < 18.0 {
+            self.count += 1;
+            self.current = self
+                .doc
+                .add_page(Mm(210.0), Mm(297.0), format!("L{}", self.count));
+            self.y = 280.0;
+        }
+    }
+
+    fn text(&self, size: f32, x: f32, text: &str) {
+        let layer = self.doc.get_page(self.current.0).get_layer(self.current.1);
+        layer.use_text(text, size, Mm(x), Mm(self.y), self.font);
+    }
+
+    fn line(&mut self, size: f32, x: f32, text: &str) {
+        self.ensure_space();
+        self.text(size, x, text);
+        self.y -= size * 0.6;
+    }
+}
+
+fn empty_stats() -> GradeStats {
+    GradeStats {
+        course_count: 0,
+        failed_count: 0,
+        attempted_credits: 0.0,
+        earned_credits: 0.0,
+        weighted_gpa: None,
+        mean_score: None,
+    }
+}
+
+// 小计行：学分列为修读学分，成绩列为平均分，绩点列为学分加权平均绩点。
+fn stats_row(label: &str, stats: &GradeStats) -> Vec<Cell> {
+    vec![
+        Cell::Empty,
+        Cell::Empty,
+        Cell::text(label),
+        Cell::Number(stats.attempted_credits),
+        Cell::number(stats.mean_score),
+        Cell::number(stats.weighted_gpa),
+        Cell::text(format!("获得学分 {}", stats.earned_credits)),
+    ]
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn column_name(index: usize) -> String {
+    let mut index = index + 1;
+    let mut name = Vec::new();
+    while index > 0 {
+        let rem = (index - 1) % 26;
+        name.push(b'A' + rem as u8);
+        index = (index - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+// PDF 内置字体不含中文字形，需要嵌入一个中文字体；未指定时使用系统自带的。
+fn load_cjk_font(custom: Option<&str>) -> Result<Vec<u8>> {
+    if let Some(path) = custom.map(str::trim).filter(|v| !v.is_empty()) {
+        let bytes = std::fs::read(path).map_err(|e| ToolboxError::io("读取字体文件失败", e))?;
+        return truetype_font(&bytes).ok_or_else(|| {
+            ToolboxError::InvalidInput("字体需为 TrueType 轮廓的 .ttf 或 .ttc 文件".to_string())
+        });
+    }
+    CJK_FONT_CANDIDATES
+        .iter()
+        .find_map(|path| truetype_font(&std::fs::read(path).ok()?))
+        .ok_or_else(|| ToolboxError::NotFound("未找到可用的中文字体，请指定字体文件".to_string()))
+}
+
+// printpdf 把字体原样写入 FontFile2，只认单个 TrueType 字体：字体集取出第一个字体
+// 重新拼成独立的字体文件，没有 glyf 表（CFF 轮廓）的返回空。
+fn truetype_font(bytes: &[u8]) -> Option<Vec<u8>> {
+    let u16_at = |pos: usize| {
+        Some(u16::from_be_bytes(
+            bytes.get(pos..pos + 2)?.try_into().ok()?,
+        ))
+    };
+    let u32_at = |pos: usize| {
+        Some(u32::from_be_bytes(
+            bytes.get(pos..pos + 4)?.try_into().ok()?,
+        ))
+    };
+    let collection = bytes.starts_with(b"ttcf");
+    let base = if collection { u32_at(12)? as usize } else { 0 };
+    let num_tables = u16_at(base + 4)? as usize;
+    let mut tables = Vec::with_capacity(num_tables);
+    for index in 0..num_tables {
+        let record = base + 12 + index * 16;
+        let tag = bytes.get(record..record + 4)?;
+        let offset = u32_at(record + 8)? as usize;
+        let length = u32_at(record + 12)? as usize;
+        tables.push((record, tag, bytes.get(offset..offset + length)?));
+    }
+    if !tables.iter().any(|(_, tag, _)| *tag == b"glyf") {
+        return None;
+    }
+    if !collection {
+        return Some(bytes.to_vec());
+    }
+
+    // 字体集中各表的偏移相对整个文件，拆出来后按新位置重写表目录
+    let header_len = 12 + num_tables * 16;
+    let mut out = bytes.get(base..base + 12)?.to_vec();
+    let mut data = Vec::new();
+    for (record, tag, table) in &tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(bytes.get(record + 4..record + 8)?);
+        out.extend_from_slice(&((header_len + data.len()) as u32).to_be_bytes());
+        out.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        data.extend_from_slice(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    out.extend_from_slice(&data);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::grade_record;
+    use std::io::Read;
+
+    fn transcript() -> Transcript {
+        let records = vec![
+            grade_record("2023-2024-2", "B0301003", "85")
+                .name("大学物理B")
+                .nature("学科基础课")
+                .build(),
+            grade_record("2023-2024-1", "B0101001", "优秀")
+                .name("高等数学A（上）")
+                .nature("学科基础课")
+                .build(),
+            grade_record("2024-2025-1", "F0901001", "75")
+                .name("金融学原理, 双学位")
+                .nature("学科基础课")
+                .minor()
+                .build(),
+        ];
+        Transcript::new("2023001", Some("张三"), &records, &GradeScale::default())
+    }
+
+    #[test]
+    fn format_follows_file_extension() {
+        assert_eq!(
+            TranscriptFormat::from_path("/tmp/成绩.XLSX").unwrap(),
+            TranscriptFormat::Xlsx
+        );
+        assert_eq!(
+            TranscriptFormat::from_path("grades.csv").unwrap(),
+            TranscriptFormat::Csv
+        );
+        assert!(TranscriptFormat::from_path("grades.txt").is_err());
+    }
+
+    #[test]
+    fn csv_groups_terms_with_subtotals() {
+        let csv = transcript().to_csv();
+        let lines = csv
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .collect::<Vec<_>>();
+        assert_eq!(lines[0], "成绩单 张三（2023001）");
+        assert_eq!(lines[2], "主修课程");
+        assert_eq!(lines[3], "学期,课程代码,课程名称,学分,成绩,绩点,课程性质");
+        assert_eq!(
+            lines[4],
+            "2023-2024-1,B0101001,高等数学A（上）,2,优秀,,学科基础课"
+        );
+        assert_eq!(lines[5], ",,2023-2024-1 小计,2,95,4.5,获得学分 2");
+        assert_eq!(lines[8], ",,主修课程累计,4,90,4,获得学分 4");
+        assert_eq!(lines[10], "辅修课程");
+        assert_eq!(
+            lines[12],
+            "2024-2025-1,F0901001,\"金融学原理, 双学位\",2,75,,学科基础课"
+        );
+        assert_eq!(lines.last(), Some(&",,全部课程累计,6,85,3.5,获得学分 6"));
+    }
+
+    #[test]
+    fn xlsx_contains_typed_cells() {
+        let bytes = transcript().to_xlsx().unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert!(archive.by_name("xl/workbook.xml").is_ok());
+        let mut sheet = String::new();
+        archive
+            .by_name("xl/worksheets/sheet1.xml")
+            .unwrap()
+            .read_to_string(&mut sheet)
+            .unwrap();
+        assert!(sheet.contains("<c r=\"C5\" t=\"inlineStr\"><is><t>高等数学A（上）</t></is></c>"));
+        assert!(sheet.contains("<c r=\"D5\"><v>2</v></c>"));
+        assert!(sheet.contains("<c r=\"E7\"><v>85</v></c>"));
+    }
+
+    #[test]
+    fn missing_custom_font_is_reported() {
+        let err = transcript()
+            .render(TranscriptFormat::Pdf, Some("/nonexistent/font.ttf"))
+            .unwrap_err();
+        assert_eq!(err.code(), "io");
+    }
+
+    #[test]
+    fn column_names_follow_spreadsheet_letters() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(27), "AB");
+    }
+
+    // 按 sfnt 格式拼一个只有表目录和表数据的字体，base 为它在整个文件中的位置。
+    fn sfnt(version: &[u8; 4], tables: &[(&[u8; 4], &[u8])], base: usize) -> Vec<u8> {
+        let mut out = version.to_vec();
+        out.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        out.extend_from_slice(&[0; 6]);
+        let mut offset = base + 12 + tables.len() * 16;
+        for (tag, data) in tables {
+            out.extend_from_slice(*tag);
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&(offset as u32).to_be_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for (_, data) in tables {
+            out.extend_from_slice(data);
+            out.resize(out.len().next_multiple_of(4), 0);
+        }
+        out
+    }
+
+    #[test]
+    fn collection_yields_first_truetype_face() {
+        let tables: [(&[u8; 4], &[u8]); 2] = [(b"glyf", &[1, 2, 3]), (b"head", &[4; 8])];
+        let single = sfnt(&[0, 1, 0, 0], &tables, 0);
+        assert_eq!(truetype_font(&single), Some(single.clone()));
+
+        let mut collection = b"ttcf".to_vec();
+        collection.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 1]);
+        collection.extend_from_slice(&16u32.to_be_bytes());
+        collection.extend_from_slice(&sfnt(&[0, 1, 0, 0], &tables, 16));
+        assert_eq!(truetype_font(&collection), Some(single));
+
+        let cff = sfnt(b"OTTO", &[(b"CFF ", &[1, 2, 3])], 0);
+        assert_eq!(truetype_font(&cff), None);
+        assert_eq!(truetype_font(b"ttcf"), None);
+    }
+
+    // 测试字体只有两个方块字形，覆盖 ASCII 和常用中文区段。
+    #[test]
+    fn pdf_embeds_cjk_font() {
+        let font = load_cjk_font(Some(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/fonts/cjk-square.ttf"
+        )))
+        .unwrap();
+        let pdf = transcript().to_pdf(&font).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/FontFile2"));
+        assert!(text.contains("/CIDFontType2"));
+    }
+}
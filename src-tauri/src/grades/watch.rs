@@ -0,0 +1,135 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+
+use crate::db::GradeChange;
+use crate::error::{Result, ToolboxError};
+
+pub const DEFAULT_INTERVAL_MINUTES: u64 = 30;
+
+// 有新成绩时推给前端的内容。
+#[derive(Debug, Clone, Serialize)]
+pub struct GradePublished {
+    pub username: String,
+    pub changes: Vec<GradeChange>,
+}
+
+// 后台同步失败时推给前端的内容，任务本身会在下一轮继续尝试。
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeWatchFailure {
+    pub username: String,
+    pub error: ToolboxError,
+}
+
+pub fn interval(minutes: Option<u64>) -> Result<Duration> {
+    let minutes = minutes.unwrap_or(DEFAULT_INTERVAL_MINUTES);
+    if !(5..=24 * 60).contains(&minutes) {
+        return Err(ToolboxError::InvalidInput(
+            "同步间隔需在 5 分钟到 24 小时之间".to_string(),
+        ));
+    }
+    Ok(Duration::from_secs(minutes * 60))
+}
+
+// 本次同步中真正出了分的课程，只改了绩点或标记的不提醒。
+pub fn published(username: &str, changes: &[GradeChange]) -> Option<GradePublished> {
+    let changes = changes
+        .iter()
+        .filter(|change| change.is_new_score())
+        .cloned()
+        .collect::<Vec<_>>();
+    (!changes.is_empty()).then(|| GradePublished {
+        username: username.to_string(),
+        changes,
+    })
+}
+
+// 每个账号最多一个后台定时同步任务，重复开启时替换旧任务。
+#[derive(Default)]
+pub struct GradeWatchers {
+    tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl GradeWatchers {
+    pub fn start(&self, username: &str, task: JoinHandle<()>) {
+        let mut tasks = self.tasks.lock().unwrap();
+        if let Some(previous) = tasks.insert(username.to_string(), task) {
+            previous.abort();
+        }
+    }
+
+    pub fn stop(&self, username: &str) -> bool {
+        match self.tasks.lock().unwrap().remove(username) {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stop_all(&self) {
+        for (_, task) in self.tasks.lock().unwrap().drain() {
+            task.abort();
+        }
+    }
+
+    pub fn usernames(&self) -> Vec<String> {
+        let mut usernames = self
+            .tasks
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        usernames.sort();
+        usernames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(old: Option<&str>, new: Option<&str>, old_gpa: Option<f32>) -> GradeChange {
+        GradeChange {
+            term: "2024-2025-2".to_string(),
+            course_code: "B0101002".to_string(),
+            course_name: "高等数学A（下）".to_string(),
+            group_name: String::new(),
+            old_score: old.map(str::to_string),
+            new_score: new.map(str::to_string),
+            old_gpa,
+            new_gpa: Some(3.5),
+            old_score_flag: None,
+            new_score_flag: None,
+        }
+    }
+
+    #[test]
+    fn only_new_or_changed_scores_are_published() {
+        let changes = [
+            change(None, Some("85"), None),
+            change(Some("55"), Some("61"), Some(0.0)),
+            change(Some("85"), Some("85"), Some(3.0)),
+            change(None, None, None),
+        ];
+        let notice = published("2020001", &changes).unwrap();
+        let scores = notice
+            .changes
+            .iter()
+            .map(|c| c.new_score.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(scores, vec!["85", "61"]);
+        assert!(published("2020001", &changes[2..]).is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_range_interval() {
+        assert_eq!(interval(None).unwrap(), Duration::from_secs(30 * 60));
+        assert!(interval(Some(1)).is_err());
+        assert!(interval(Some(24 * 60 + 1)).is_err());
+    }
+}
@@ -0,0 +1,499 @@
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::error::{Result, ToolboxError};
+
+// 强智 jsxsd 教务系统的地址、路径与表单字段。默认值对应安徽工业大学，
+// 其他学校可在 jwxt.toml 中覆盖。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JwxtProfile {
+    pub base_url: String,
+    pub login_path: String,
+    pub main_path: String,
+    pub grades_path: String,
+    pub major_plan_path: String,
+    pub minor_plan_path: String,
+    pub schedule_path: String,
+    pub captcha_path: String,
+    pub calendar_path: String,
+    pub exam_path: String,
+    pub fields: JwxtFormFields,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JwxtFormFields {
+    pub login_account: String,
+    pub login_password: String,
+    pub login_encoded: String,
+    pub login_captcha: String,
+    pub grade_term: String,
+    pub grade_nature: String,
+    pub grade_course_name: String,
+    pub grade_display: String,
+    pub grade_display_value: String,
+    pub schedule_term: String,
+    pub schedule_week: String,
+    pub exam_term: String,
+}
+
+impl Default for JwxtProfile {
+    fn default() -> Self {
+        JwxtProfile {
+            base_url: "http://jwxt.ahut.edu.cn".to_string(),
+            login_path: "/jsxsd/xk/LoginToXk".to_string(),
+            main_path: "/jsxsd/framework/xsMain.jsp".to_string(),
+            grades_path: "/jsxsd/kscj/cjcx_list".to_string(),
+            major_plan_path: "/jsxsd/pyfa/pyfa_query".to_string(),
+            minor_plan_path: "/jsxsd/pyfa/fxpyfa_query".to_string(),
+            schedule_path: "/jsxsd/xskb/xskb_list.do".to_string(),
+            captcha_path: "/jsxsd/verifycode.servlet".to_string(),
+            calendar_path: "/jsxsd/jxzl/jxzl_query".to_string(),
+            exam_path: "/jsxsd/xsks/xsksap_list".to_string(),
+            fields: JwxtFormFields::default(),
+        }
+    }
+}
+
+impl Default for JwxtFormFields {
+    fn default() -> Self {
+        JwxtFormFields {
+            login_account: "userAccount".to_string(),
+            login_password: "userPassword".to_string(),
+            login_encoded: "encoded".to_string(),
+            login_captcha: "RANDOMCODE".to_string(),
+            grade_term: "kksj".to_string(),
+            grade_nature: "kcxz".to_string(),
+            grade_course_name: "kcmc".to_string(),
+            grade_display: "xsfs".to_string(),
+            grade_display_value: "all".to_string(),
+            schedule_term: "xnxq01id".to_string(),
+            schedule_week: "zc".to_string(),
+            exam_term: "xnxqid".to_string(),
+        }
+    }
+}
+
+impl JwxtProfile {
+    pub fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.trim().trim_end_matches('/'),
+            path.trim().trim_start_matches('/')
+        )
+    }
+
+    pub fn login_url(&self) -> String {
+        self.url(&self.login_path)
+    }
+
+    pub fn main_url(&self) -> String {
+        self.url(&self.main_path)
+    }
+
+    pub fn grades_url(&self) -> String {
+        self.url(&self.grades_path)
+    }
+
+    pub fn major_plan_url(&self) -> String {
+        self.url(&self.major_plan_path)
+    }
+
+    pub fn minor_plan_url(&self) -> String {
+        self.url(&self.minor_plan_path)
+    }
+
+    pub fn schedule_url(&self) -> String {
+        self.url(&self.schedule_path)
+    }
+
+    pub fn captcha_url(&self) -> String {
+        self.url(&self.captcha_path)
+    }
+
+    pub fn calendar_url(&self) -> String {
+        self.url(&self.calendar_path)
+    }
+
+    pub fn exam_url(&self) -> String {
+        self.url(&self.exam_path)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let base = self.base_url.trim();
+        if !(base.starts_with("http://") || base.starts_with("https://")) {
+            return Err(ToolboxError::Config(
+                "教务系统地址需以 http:// 或 https:// 开头".to_string(),
+            ));
+        }
+        reqwest::Url::parse(base)
+            .map_err(|e| ToolboxError::Config(format!("教务系统地址无效: {}", e)))?;
+        Ok(())
+    }
+}
+
+pub fn resolve_profile_path() -> Result<PathBuf> {
+    let db_path = crate::db::resolve_db_path()?;
+    Ok(db_path.with_file_name("jwxt.toml"))
+}
+
+pub fn load_profile() -> Result<JwxtProfile> {
+    let path = resolve_profile_path()?;
+    if !path.exists() {
+        return Ok(JwxtProfile::default());
+    }
+    let raw = fs::read_to_string(&path).map_err(|e| ToolboxError::io("读取教务配置失败", e))?;
+    let profile: JwxtProfile =
+        toml::from_str(&raw).map_err(|e| ToolboxError::parse("教务配置格式错误", e))?;
+    profile.validate()?;
+    Ok(profile)
+}
+
+pub fn save_profile(profile: &JwxtProfile) -> Result<()> {
+    profile.validate()?;
+    let path = resolve_profile_path()?;
+    let content = toml::to_string_pretty(profile)
+        .map_err(|e| ToolboxError::internal("生成教务配置失败", e))?;
+    fs::write(&path, content).map_err(|e| ToolboxError::io("保存教务配置失败", e))
+}
+
+pub fn default_headers() -> Result<HeaderMap> {
+    let headers = [
+        ("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36"),
+        ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"),
+        ("Accept-Language", "zh-CN,zh-TW;q=0.9,zh-HK;q=0.8,zh;q=0.7"),
+    ];
+
+    let mut default_headers = HeaderMap::new();
+    for (key, value) in &headers {
+        let header_name = HeaderName::from_bytes(key.as_bytes())
+            .map_err(|e| ToolboxError::internal(format!("请求头 {} 无效", key), e))?;
+        let header_value = HeaderValue::from_str(value)
+            .map_err(|e| ToolboxError::internal(format!("请求头 {} 的值无效", key), e))?;
+        default_headers.insert(header_name, header_value);
+    }
+    Ok(default_headers)
+}
+
+pub fn encode_login_params(username: &str, password: &str) -> String {
+    let username_encoded = general_purpose::STANDARD.encode(username.as_bytes());
+    let password_encoded = general_purpose::STANDARD.encode(password.as_bytes());
+    format!("{}%%%{}", username_encoded, password_encoded)
+}
+
+// 登录需要验证码时发给前端的图片，image 为可直接放进 <img src> 的 data URL。
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptchaChallenge {
+    pub username: String,
+    pub image: String,
+}
+
+pub struct JwxtSession {
+    client: reqwest::Client,
+    profile: JwxtProfile,
+    username: String,
+    password: String,
+    logged_in: bool,
+    captcha: Option<CaptchaChallenge>,
+}
+
+impl JwxtSession {
+    pub fn new(profile: JwxtProfile, username: &str, password: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers()?)
+            .cookie_store(true)
+            .build()
+            .map_err(|e| ToolboxError::internal("创建网络客户端失败", e))?;
+        Ok(JwxtSession {
+            client,
+            profile,
+            username: username.to_string(),
+            password: password.to_string(),
+            logged_in: false,
+            captcha: None,
+        })
+    }
+
+    pub fn profile(&self) -> &JwxtProfile {
+        &self.profile
+    }
+
+    pub fn matches(&self, profile: &JwxtProfile, username: &str, password: &str) -> bool {
+        &self.profile == profile && self.username == username && self.password == password
+    }
+
+    pub async fn login(&mut self) -> Result<()> {
+        self.submit_login(None).await
+    }
+
+    // 用用户识别出的验证码完成登录；必须沿用取图时的会话 Cookie，验证码才有效。
+    pub async fn login_with_captcha(&mut self, code: &str) -> Result<()> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Err(ToolboxError::InvalidInput("请输入验证码".to_string()));
+        }
+        self.submit_login(Some(code)).await
+    }
+
+    pub fn take_captcha(&mut self) -> Option<CaptchaChallenge> {
+        self.captcha.take()
+    }
+
+    async fn submit_login(&mut self, captcha: Option<&str>) -> Result<()> {
+        self.logged_in = false;
+        self.captcha = None;
+        let encoded_value = encode_login_params(&self.username, &self.password);
+        let fields = &self.profile.fields;
+        let mut params = vec![
+            (fields.login_account.as_str(), self.username.as_str()),
+            (fields.login_password.as_str(), ""),
+            (fields.login_encoded.as_str(), encoded_value.as_str()),
+            ("pwdstr1", ""),
+            ("pwdstr2", ""),
+        ];
+        if let Some(code) = captcha {
+            params.push((fields.login_captcha.as_str(), code));
+        }
+
+        let response = self
+            .client
+            .post(self.profile.login_url())
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| ToolboxError::network("登录请求失败", e))?;
+
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ToolboxError::network("读取登录响应失败", e))?;
+
+        if text.contains("用户名或密码错误") {
+            return Err(ToolboxError::WrongPassword);
+        }
+        if text.contains("验证码") {
+            self.captcha = Some(self.fetch_captcha().await?);
+            return Err(ToolboxError::CaptchaRequired);
+        }
+        self.logged_in = true;
+        Ok(())
+    }
+
+    async fn fetch_captcha(&self) -> Result<CaptchaChallenge> {
+        let response = self
+            .client
+            .get(self.profile.captcha_url())
+            .send()
+            .await
+            .map_err(|e| ToolboxError::network("获取验证码失败", e))?;
+        let mime = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| value.starts_with("image/"))
+            .unwrap_or("image/jpeg")
+            .to_string();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| ToolboxError::network("获取验证码失败", e))?;
+        Ok(CaptchaChallenge {
+            username: self.username.clone(),
+            image: format!(
+                "data:{};base64,{}",
+                mime,
+                general_purpose::STANDARD.encode(&bytes)
+            ),
+        })
+    }
+
+    pub async fn get(&mut self, url: &str) -> Result<String> {
+        self.send(|client| client.get(url)).await
+    }
+
+    pub async fn post_form<T: Serialize + ?Sized>(
+        &mut self,
+        url: &str,
+        form: &T,
+    ) -> Result<String> {
+        self.send(|client| client.post(url).form(form)).await
+    }
+
+    pub async fn ensure_login(&mut self) -> Result<()> {
+        if self.logged_in {
+            return Ok(());
+        }
+        self.login().await
+    }
+
+    async fn send<F>(&mut self, build: F) -> Result<String>
+    where
+        F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    {
+        self.ensure_login().await?;
+        let (url, text) = self.fetch(&build).await?;
+        if !self.is_login_page(&url, &text) {
+            return Ok(text);
+        }
+
+        // 会话已失效，重新登录后重试一次。
+        self.login().await?;
+        let (url, text) = self.fetch(&build).await?;
+        if self.is_login_page(&url, &text) {
+            self.logged_in = false;
+            return Err(ToolboxError::SessionExpired);
+        }
+        Ok(text)
+    }
+
+    // 会话过期后教务系统会把请求重定向回登录页，或直接返回登录表单。
+    fn is_login_page(&self, url: &reqwest::Url, text: &str) -> bool {
+        let path = url.path().trim_end_matches('/');
+        let login_path = self.profile.login_path.trim().trim_end_matches('/');
+        let app_root = login_path
+            .split('/')
+            .find(|segment| !segment.is_empty())
+            .map(|segment| format!("/{}", segment));
+        let account = &self.profile.fields.login_account;
+        (!login_path.is_empty() && path.ends_with(login_path))
+            || app_root.is_some_and(|root| path == root)
+            || text.contains(&format!("name=\"{}\"", account))
+            || text.contains(&format!("id=\"{}\"", account))
+    }
+
+    async fn fetch<F>(&self, build: &F) -> Result<(reqwest::Url, String)>
+    where
+        F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    {
+        let response = build(&self.client)
+            .send()
+            .await
+            .map_err(|e| ToolboxError::network("请求教务系统失败", e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ToolboxError::NotFound(format!(
+                "教务系统没有该页面：{}",
+                response.url().path()
+            )));
+        }
+        let url = response.url().clone();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ToolboxError::network("读取教务系统响应失败", e))?;
+        Ok((url, text))
+    }
+}
+
+#[derive(Default)]
+pub struct JwxtSessions {
+    sessions: Mutex<HashMap<String, Arc<Mutex<JwxtSession>>>>,
+}
+
+impl JwxtSessions {
+    // 同一账号在多次同步之间复用已登录的会话，密码或学校配置变化时重建。
+    // 等待会话锁时不持有表锁，以免一个账号的长请求卡住其他账号。
+    pub async fn acquire(
+        &self,
+        profile: &JwxtProfile,
+        username: &str,
+        password: &str,
+    ) -> Result<Arc<Mutex<JwxtSession>>> {
+        loop {
+            let existing = self.get(username).await;
+            if let Some(existing) = &existing {
+                if existing.lock().await.matches(profile, username, password) {
+                    return Ok(existing.clone());
+                }
+            }
+            let mut sessions = self.sessions.lock().await;
+            let unchanged = match (sessions.get(username), &existing) {
+                (None, None) => true,
+                (Some(current), Some(existing)) => Arc::ptr_eq(current, existing),
+                _ => false,
+            };
+            // 检查期间其他任务已换上新会话，重新检查一遍
+            if !unchanged {
+                continue;
+            }
+            let session = Arc::new(Mutex::new(JwxtSession::new(
+                profile.clone(),
+                username,
+                password,
+            )?));
+            sessions.insert(username.to_string(), session.clone());
+            return Ok(session);
+        }
+    }
+
+    pub async fn get(&self, username: &str) -> Option<Arc<Mutex<JwxtSession>>> {
+        self.sessions.lock().await.get(username).cloned()
+    }
+
+    pub async fn take_captcha(&self, username: &str) -> Option<CaptchaChallenge> {
+        let session = self.get(username).await?;
+        let challenge = session.lock().await.take_captcha();
+        challenge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_profile_keeps_defaults() {
+        let profile: JwxtProfile = toml::from_str(
+            r#"
+base_url = "https://jw.example.edu.cn/"
+
+[fields]
+grade_term = "xnxqid"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            profile.grades_url(),
+            "https://jw.example.edu.cn/jsxsd/kscj/cjcx_list"
+        );
+        assert_eq!(profile.fields.grade_term, "xnxqid");
+        assert_eq!(profile.fields.schedule_term, "xnxq01id");
+    }
+
+    #[test]
+    fn rejects_profile_without_scheme() {
+        let profile = JwxtProfile {
+            base_url: "jwxt.ahut.edu.cn".to_string(),
+            ..JwxtProfile::default()
+        };
+        assert!(profile.validate().is_err());
+        assert!(JwxtProfile::default().validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn busy_session_does_not_block_other_accounts() {
+        let sessions = JwxtSessions::default();
+        let profile = JwxtProfile::default();
+        let first = sessions.acquire(&profile, "2020001", "a").await.unwrap();
+        let guard = first.lock().await;
+        let other = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            sessions.acquire(&profile, "2020002", "b"),
+        )
+        .await
+        .expect("另一账号不应等待");
+        assert!(other.is_ok());
+        drop(guard);
+
+        let again = sessions.acquire(&profile, "2020001", "a").await.unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        let changed = sessions.acquire(&profile, "2020001", "c").await.unwrap();
+        assert!(!Arc::ptr_eq(&first, &changed));
+    }
+}
@@ -1,22 +1,45 @@
+mod backup;
 mod db;
 mod cracker;
+mod credentials;
+pub mod error;
+mod exams;
 mod grades;
+mod jwxt;
 mod schedule;
 mod project_tree;
 mod tech_stack;
 pub mod semantic_scan;
+#[cfg(test)]
+mod test_support;
 
-use db::Database;
+use db::{Database, SharedDatabase};
+use error::ToolboxError;
 use cracker::{PasswordCracker, CrackProgress};
 use grades::{fetch_grades, GradeFetchResult};
+use grades::analytics::{GradeScale, GradeSummary};
+use grades::audit::{GraduationAudit, RequirementBucket};
+use grades::compare::{ComparedUser, GradeComparison};
+use grades::transcript::{Transcript, TranscriptFormat};
+use grades::watch::{GradeWatchFailure, GradeWatchers};
+use jwxt::{JwxtProfile, JwxtSessions};
 use project_tree::{scan_project_tree, save_tree_to_file};
+use schedule::conflicts::ConflictReport;
+use schedule::upcoming::UpcomingClasses;
+use schedule::weeks::{TermCalendar, TermWeek};
 use tech_stack::{export_tech_stack_json, export_tech_stack_pdf, scan_tech_stack_github, scan_tech_stack_local};
 use semantic_scan::{export_semantic_json, export_semantic_schema, scan_semantic_github, scan_semantic_local};
 use serde::{Deserialize, Serialize};
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+const CAPTCHA_EVENT: &str = "jwxt_captcha";
+const SCHEDULE_PROGRESS_EVENT: &str = "schedule_sync_progress";
+const GRADE_PUBLISHED_EVENT: &str = "grade_published";
+const GRADE_WATCH_ERROR_EVENT: &str = "grade_watch_error";
+
 #[derive(Serialize, Deserialize)]
 struct CrackRequest {
     username: String,
@@ -31,11 +54,29 @@ struct ImportSummary {
     updated: usize,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Serialize)]
 struct GradeSyncSummary {
     inserted: usize,
     updated: usize,
     total: usize,
+    // 新出现或分数、绩点、成绩标记有变化的课程
+    changes: Vec<db::GradeChange>,
+    // 同步前该账号没有任何成绩，本次只作为基线
+    baseline: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ScheduleSyncSummary {
+    term: String,
+    total: usize,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExamSyncSummary {
+    term: String,
+    total: usize,
+    scheduled: usize,
 }
 
 #[derive(Deserialize)]
@@ -44,16 +85,14 @@ struct GradeSyncRequest {
     password: String,
 }
 
-fn open_database() -> Result<Database, String> {
-    let db_path = db::resolve_db_path()?;
-    Database::new(&db_path).map_err(|e| format!("Failed to open database: {}", e))
-}
-
 #[tauri::command]
-async fn crack_password(request: CrackRequest, window: tauri::Window) -> Result<String, String> {
-    let db = open_database()?;
-
-    if let Ok(Some(existing)) = db.get_result_by_username(&request.username) {
+async fn crack_password(
+    request: CrackRequest,
+    window: tauri::Window,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<String, ToolboxError> {
+    let existing = database.lock()?.get_result_by_username(&request.username);
+    if let Ok(Some(existing)) = existing {
         let existing_date = existing
             .password_date
             .as_deref()
@@ -73,29 +112,15 @@ async fn crack_password(request: CrackRequest, window: tauri::Window) -> Result<
         }
     }
 
-    let cracker = PasswordCracker::new(request.username.clone(), request.year);
+    let profile = jwxt::load_profile()?;
+    let cracker = PasswordCracker::new(request.username.clone(), request.year, &profile);
     let passwords = cracker.generate_date_passwords();
     let total_passwords = passwords.len() as i32;
 
-    let headers = [
-        ("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36"),
-        ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"),
-        ("Accept-Language", "zh-CN,zh-TW;q=0.9,zh-HK;q=0.8,zh;q=0.7"),
-    ];
-
-    let mut default_headers = reqwest::header::HeaderMap::new();
-    for (key, value) in &headers {
-        let header_name = reqwest::header::HeaderName::from_bytes(key.as_bytes())
-            .map_err(|e| format!("Invalid header name {}: {}", key, e))?;
-        let header_value = reqwest::header::HeaderValue::from_str(value)
-            .map_err(|e| format!("Invalid header value for {}: {}", key, e))?;
-        default_headers.insert(header_name, header_value);
-    }
-
     let client = reqwest::Client::builder()
-        .default_headers(default_headers)
+        .default_headers(jwxt::default_headers()?)
         .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        .map_err(|e| ToolboxError::internal("创建网络客户端失败", e))?;
 
     let found = Arc::new(Mutex::new(false));
     let start_time = std::time::Instant::now();
@@ -112,7 +137,7 @@ async fn crack_password(request: CrackRequest, window: tauri::Window) -> Result<
                     *found.lock().await = true;
                     
                     let encoded_value = cracker.encode_login_params(password);
-                    let _ = db.insert_result(
+                    let _ = database.lock()?.insert_result(
                         &request.username,
                         request.name.as_deref().unwrap_or(""),
                         "",
@@ -162,31 +187,34 @@ async fn crack_password(request: CrackRequest, window: tauri::Window) -> Result<
 }
 
 #[tauri::command]
-fn get_crack_history() -> Result<Vec<db::PasswordResult>, String> {
-    let db = open_database()?;
+fn get_crack_history(
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::PasswordResult>, ToolboxError> {
+    let db = database.lock()?;
 
     db.get_all_results()
-        .map_err(|e| format!("Failed to query database: {}", e))
 }
 
 #[tauri::command]
-fn import_students(students: Vec<db::StudentImport>) -> Result<ImportSummary, String> {
-    let mut db = open_database()?;
+fn import_students(
+    students: Vec<db::StudentImport>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<ImportSummary, ToolboxError> {
+    let mut db = database.lock()?;
 
-    let (inserted, updated) = db
-        .upsert_students(&students)
-        .map_err(|e| format!("Failed to import students: {}", e))?;
+    let (inserted, updated) = db.upsert_students(&students)?;
 
     Ok(ImportSummary { inserted, updated })
 }
 
 #[tauri::command]
-fn import_dates(dates: Vec<db::DateImport>) -> Result<ImportSummary, String> {
-    let mut db = open_database()?;
+fn import_dates(
+    dates: Vec<db::DateImport>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<ImportSummary, ToolboxError> {
+    let mut db = database.lock()?;
 
-    let (inserted, updated) = db
-        .upsert_dates(&dates)
-        .map_err(|e| format!("Failed to import dates: {}", e))?;
+    let (inserted, updated) = db.upsert_dates(&dates)?;
 
     Ok(ImportSummary { inserted, updated })
 }
@@ -197,102 +225,419 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn sync_grades(request: GradeSyncRequest) -> Result<GradeSyncSummary, String> {
-    if request.username.trim().is_empty() || request.password.trim().is_empty() {
-        return Err("请输入账号和密码".to_string());
+async fn sync_grades(
+    request: GradeSyncRequest,
+    window: tauri::Window,
+    sessions: tauri::State<'_, JwxtSessions>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<GradeSyncSummary, ToolboxError> {
+    let profile = jwxt::load_profile()?;
+    let result = run_sync_grades(
+        &database,
+        &sessions,
+        &profile,
+        &request.username,
+        &request.password,
+    )
+    .await;
+    emit_captcha_if_required(&window, &sessions, &request.username, result).await
+}
+
+#[tauri::command]
+async fn sync_grades_saved(
+    username: String,
+    window: tauri::Window,
+    sessions: tauri::State<'_, JwxtSessions>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<GradeSyncSummary, ToolboxError> {
+    let profile = jwxt::load_profile()?;
+    let result = run_sync_grades_saved(&database, &sessions, &profile, &username).await;
+    emit_captcha_if_required(&window, &sessions, &username, result).await
+}
+
+// 教务系统开启验证码时，把该账号会话取到的验证码图片推给前端，
+// 用户输入后调用 submit_jwxt_captcha 在同一会话内完成登录，再重新同步即可。
+async fn emit_captcha_if_required<T>(
+    window: &impl Emitter<tauri::Wry>,
+    sessions: &JwxtSessions,
+    username: &str,
+    result: Result<T, ToolboxError>,
+) -> Result<T, ToolboxError> {
+    if let Err(ToolboxError::CaptchaRequired) = &result {
+        if let Some(challenge) = sessions.take_captcha(username.trim()).await {
+            let _ = window.emit(CAPTCHA_EVENT, &challenge);
+        }
     }
-    let GradeFetchResult {
-        grades,
-        minor_codes,
-        minor_names,
-        major_plan,
-        minor_plan,
-    } = fetch_grades(request.username.trim(), request.password.trim()).await?;
-    let mut db = open_database()?;
-    let (inserted, updated) = db
-        .upsert_grades(request.username.trim(), &grades)
-        .map_err(|e| format!("Failed to save grades: {}", e))?;
-    db.update_minor_flags(request.username.trim(), &minor_codes, &minor_names)
-        .map_err(|e| format!("Failed to update minor flags: {}", e))?;
-    db.replace_plan_courses(request.username.trim(), false, &major_plan)
-        .map_err(|e| format!("Failed to save major plan: {}", e))?;
-    db.replace_plan_courses(request.username.trim(), true, &minor_plan)
-        .map_err(|e| format!("Failed to save minor plan: {}", e))?;
-    db.ensure_user_in_password_results(request.username.trim())
-        .map_err(|e| format!("Failed to update user: {}", e))?;
-    db.save_user_password(request.username.trim(), request.password.trim())
-        .map_err(|e| format!("Failed to save password: {}", e))?;
-    Ok(GradeSyncSummary {
-        inserted,
-        updated,
-        total: grades.len(),
-    })
+    result
 }
 
 #[tauri::command]
-async fn sync_grades_saved(username: String) -> Result<GradeSyncSummary, String> {
-    let username = username.trim().to_string();
+async fn submit_jwxt_captcha(
+    username: String,
+    code: String,
+    window: tauri::Window,
+    sessions: tauri::State<'_, JwxtSessions>,
+) -> Result<(), ToolboxError> {
+    let result = run_submit_captcha(&sessions, &username, &code).await;
+    emit_captcha_if_required(&window, &sessions, &username, result).await
+}
+
+async fn run_submit_captcha(
+    sessions: &JwxtSessions,
+    username: &str,
+    code: &str,
+) -> Result<(), ToolboxError> {
+    let session = sessions
+        .get(username.trim())
+        .await
+        .ok_or_else(|| ToolboxError::NotFound("该账号没有等待验证码的登录".to_string()))?;
+    let mut session = session.lock().await;
+    session.login_with_captcha(code).await
+}
+
+async fn run_sync_grades(
+    database: &SharedDatabase,
+    sessions: &JwxtSessions,
+    profile: &JwxtProfile,
+    username: &str,
+    password: &str,
+) -> Result<GradeSyncSummary, ToolboxError> {
+    let username = username.trim();
+    let password = password.trim();
+    if username.is_empty() || password.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号和密码".to_string()));
+    }
+    let session = sessions.acquire(profile, username, password).await?;
+    let fetch = fetch_grades(&mut *session.lock().await).await?;
+    let mut db = database.lock()?;
+    let summary = save_grade_fetch(&mut db, username, &fetch)?;
+    db.ensure_user_in_password_results(username)?;
+    db.save_user_password(username, password)?;
+    Ok(summary)
+}
+
+async fn run_sync_grades_saved(
+    database: &SharedDatabase,
+    sessions: &JwxtSessions,
+    profile: &JwxtProfile,
+    username: &str,
+) -> Result<GradeSyncSummary, ToolboxError> {
+    let username = username.trim();
     if username.is_empty() {
-        return Err("请输入账号".to_string());
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
     }
-    let password = {
-        let db = open_database()?;
-        db.get_saved_password(&username)
-            .map_err(|e| format!("Failed to read password: {}", e))?
-    };
+    let password = database.lock()?.get_saved_password(username)?;
     let password = password
         .filter(|value| !value.trim().is_empty())
-        .ok_or_else(|| "该账号未保存密码".to_string())?;
+        .ok_or_else(|| ToolboxError::NotFound("该账号未保存密码".to_string()))?;
+
+    let session = sessions.acquire(profile, username, password.trim()).await?;
+    let fetch = fetch_grades(&mut *session.lock().await).await?;
+    let mut db = database.lock()?;
+    save_grade_fetch(&mut db, username, &fetch)
+}
 
+fn save_grade_fetch(
+    db: &mut Database,
+    username: &str,
+    fetch: &GradeFetchResult,
+) -> Result<GradeSyncSummary, ToolboxError> {
     let GradeFetchResult {
         grades,
         minor_codes,
         minor_names,
         major_plan,
         minor_plan,
-    } = fetch_grades(&username, password.trim()).await?;
-    let mut db = open_database()?;
-    let (inserted, updated) = db
-        .upsert_grades(&username, &grades)
-        .map_err(|e| format!("Failed to save grades: {}", e))?;
-    db.update_minor_flags(&username, &minor_codes, &minor_names)
-        .map_err(|e| format!("Failed to update minor flags: {}", e))?;
-    db.replace_plan_courses(&username, false, &major_plan)
-        .map_err(|e| format!("Failed to save major plan: {}", e))?;
-    db.replace_plan_courses(&username, true, &minor_plan)
-        .map_err(|e| format!("Failed to save minor plan: {}", e))?;
+    } = fetch;
+    let baseline = db.count_user_relations(username)?.0 == 0;
+    let (inserted, updated, changes) = db.upsert_grades(username, grades)?;
+    db.update_minor_flags(username, minor_codes, minor_names)?;
+    db.replace_plan_courses(username, false, major_plan)?;
+    db.replace_plan_courses(username, true, minor_plan)?;
     Ok(GradeSyncSummary {
         inserted,
         updated,
         total: grades.len(),
+        changes,
+        baseline,
     })
 }
 
+#[derive(Deserialize)]
+struct GradeWatchRequest {
+    username: String,
+    interval_minutes: Option<u64>,
+}
+
+// 用已保存的密码定时同步成绩，有新成绩时发出 grade_published 事件；账号此前没有成绩时，
+// 首次同步只作为基线不提醒。单次失败只发出 grade_watch_error，任务继续按间隔重试，
+// 直到调用 stop_grade_watch、隐藏该账号或清除已保存的密码。
+#[tauri::command]
+fn start_grade_watch(
+    request: GradeWatchRequest,
+    app: tauri::AppHandle,
+    watchers: tauri::State<'_, GradeWatchers>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let username = request.username.trim().to_string();
+    if username.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
+    }
+    let period = grades::watch::interval(request.interval_minutes)?;
+    let saved = database.lock()?.get_saved_password(&username)?;
+    if saved.is_none_or(|value| value.trim().is_empty()) {
+        return Err(ToolboxError::NotFound("该账号未保存密码".to_string()));
+    }
+    let task_username = username.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            watch_grades_once(&app, &task_username).await;
+        }
+    });
+    watchers.start(&username, task);
+    Ok(())
+}
+
+async fn watch_grades_once(app: &tauri::AppHandle, username: &str) {
+    let sessions = app.state::<JwxtSessions>();
+    let database = app.state::<SharedDatabase>();
+    let result = async {
+        let profile = jwxt::load_profile()?;
+        run_sync_grades_saved(&database, &sessions, &profile, username).await
+    }
+    .await;
+    match emit_captcha_if_required(app, &sessions, username, result).await {
+        Ok(summary) if summary.baseline => {}
+        Ok(summary) => {
+            if let Some(published) = grades::watch::published(username, &summary.changes) {
+                let _ = app.emit(GRADE_PUBLISHED_EVENT, &published);
+            }
+        }
+        Err(error) => {
+            let failure = GradeWatchFailure {
+                username: username.to_string(),
+                error,
+            };
+            let _ = app.emit(GRADE_WATCH_ERROR_EVENT, &failure);
+        }
+    }
+}
+
+#[tauri::command]
+fn stop_grade_watch(
+    username: String,
+    watchers: tauri::State<'_, GradeWatchers>,
+) -> Result<bool, ToolboxError> {
+    Ok(watchers.stop(username.trim()))
+}
+
+#[tauri::command]
+fn get_grade_watches(watchers: tauri::State<'_, GradeWatchers>) -> Vec<String> {
+    watchers.usernames()
+}
+
+#[tauri::command]
+fn get_grade_history(
+    username: String,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::GradeHistoryEntry>, ToolboxError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
+    }
+    let db = database.lock()?;
+    db.get_grade_history(username)
+}
+
+#[tauri::command]
+fn get_jwxt_profile() -> Result<JwxtProfile, ToolboxError> {
+    jwxt::load_profile()
+}
+
+#[tauri::command]
+fn save_jwxt_profile(profile: JwxtProfile) -> Result<(), ToolboxError> {
+    jwxt::save_profile(&profile)
+}
+
 #[tauri::command]
-fn get_grade_users() -> Result<Vec<db::GradeUser>, String> {
-    let db = open_database()?;
+fn get_grade_users(
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::GradeUser>, ToolboxError> {
+    let db = database.lock()?;
     db.get_grade_users()
-        .map_err(|e| format!("Failed to query users: {}", e))
+}
+
+#[derive(Deserialize)]
+struct CompareGradeUsersRequest {
+    usernames: Vec<String>,
+    scale: Option<GradeScale>,
+}
+
+// 只能比较成绩列表中可见的账号，已隐藏的账号视为未同意参与比较。
+#[tauri::command]
+fn compare_grade_users(
+    request: CompareGradeUsersRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<GradeComparison, ToolboxError> {
+    let mut usernames: Vec<&str> = Vec::new();
+    for username in request.usernames.iter().map(|u| u.trim()) {
+        if !username.is_empty() && !usernames.contains(&username) {
+            usernames.push(username);
+        }
+    }
+    if usernames.len() < 2 {
+        return Err(ToolboxError::InvalidInput("请至少选择两个账号".to_string()));
+    }
+    let scale = request.scale.unwrap_or_default();
+    scale.validate()?;
+    let db = database.lock()?;
+    let visible = db.get_grade_users()?;
+    let mut users = Vec::new();
+    for username in usernames {
+        let user = visible
+            .iter()
+            .find(|user| user.username == username)
+            .ok_or_else(|| {
+                ToolboxError::InvalidInput(format!("账号 {} 不在成绩列表中，无法比较", username))
+            })?;
+        let records = db.get_grades_by_username(username)?;
+        let compared = ComparedUser {
+            username: user.username.clone(),
+            display_name: user.display_name.clone(),
+        };
+        users.push((compared, records));
+    }
+    Ok(grades::compare::compare(&users, &scale))
 }
 
 #[tauri::command]
-fn get_grades(username: Option<String>) -> Result<Vec<db::GradeRecord>, String> {
-    let db = open_database()?;
+fn get_grades(
+    username: Option<String>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::GradeRecord>, ToolboxError> {
+    let db = database.lock()?;
     match username {
-        Some(name) if !name.trim().is_empty() => db
-            .get_grades_by_username(name.trim())
-            .map_err(|e| format!("Failed to query grades: {}", e)),
-        _ => db
-            .get_all_grades()
-            .map_err(|e| format!("Failed to query grades: {}", e)),
+        Some(name) if !name.trim().is_empty() => db.get_grades_by_username(name.trim()),
+        _ => db.get_all_grades(),
     }
 }
 
 #[tauri::command]
-fn get_pending_courses(username: String, category: String) -> Result<Vec<db::PlanCourse>, String> {
+fn get_grade_summary(
+    username: String,
+    scale: Option<GradeScale>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<GradeSummary, ToolboxError> {
     let username = username.trim();
     if username.is_empty() {
-        return Err("请输入账号".to_string());
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
+    }
+    let scale = scale.unwrap_or_default();
+    scale.validate()?;
+    let db = database.lock()?;
+    let records = db.get_grades_by_username(username)?;
+    Ok(grades::analytics::summarize(username, &records, &scale))
+}
+
+#[derive(Deserialize)]
+struct ExportGradesRequest {
+    path: String,
+    username: String,
+    scale: Option<GradeScale>,
+    // 仅 PDF 使用，不填时查找系统自带的中文字体
+    font_path: Option<String>,
+}
+
+#[tauri::command]
+async fn export_grades(
+    request: ExportGradesRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let path = request.path.trim().to_string();
+    if path.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择导出路径".to_string()));
+    }
+    let format = TranscriptFormat::from_path(&path)?;
+    let username = request.username.trim();
+    if username.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
+    }
+    let scale = request.scale.unwrap_or_default();
+    scale.validate()?;
+    let db = database.lock()?;
+    let records = db.get_grades_by_username(username)?;
+    if records.is_empty() {
+        return Err(ToolboxError::NotFound("该账号没有成绩数据".to_string()));
+    }
+    let name = db
+        .get_result_by_username(username)?
+        .and_then(|result| result.name);
+    drop(db);
+    let transcript = Transcript::new(username, name.as_deref(), &records, &scale);
+    let font_path = request.font_path;
+    tokio::task::spawn_blocking(move || {
+        let bytes = transcript.render(format, font_path.as_deref())?;
+        tech_stack::write_bytes_atomic(&path, &bytes)
+    })
+    .await
+    .map_err(|e| ToolboxError::internal("导出任务失败", e))?
+}
+
+#[tauri::command]
+fn get_graduation_audit(
+    username: String,
+    scale: Option<GradeScale>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<GraduationAudit, ToolboxError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
+    }
+    let scale = scale.unwrap_or_default();
+    scale.validate()?;
+    let db = database.lock()?;
+    let plan = db.get_plan_courses(username)?;
+    let records = db.get_grades_by_username(username)?;
+    let requirements = db.get_graduation_requirements(username)?;
+    Ok(grades::audit::audit(
+        username,
+        &plan,
+        &records,
+        &requirements,
+        &scale,
+    ))
+}
+
+#[tauri::command]
+fn set_graduation_requirement(
+    username: String,
+    bucket: RequirementBucket,
+    credits: Option<f32>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
+    }
+    if credits.is_some_and(|value| !value.is_finite() || value < 0.0) {
+        return Err(ToolboxError::InvalidInput("学分要求不能为负".to_string()));
+    }
+    let mut db = database.lock()?;
+    db.set_graduation_requirement(username, bucket.as_str(), credits)
+}
+
+#[tauri::command]
+fn get_pending_courses(
+    username: String,
+    category: String,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::PlanCourse>, ToolboxError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
     }
     let flag = match category.as_str() {
         "minor" => 1,
@@ -300,20 +645,23 @@ fn get_pending_courses(username: String, category: String) -> Result<Vec<db::Pla
         "all" => -1,
         _ => 0,
     };
-    let db = open_database()?;
+    let db = database.lock()?;
     db.get_pending_courses(username, flag)
-        .map_err(|e| format!("Failed to query pending courses: {}", e))
 }
 
 #[tauri::command]
-fn hide_grade_user(username: String) -> Result<(), String> {
+fn hide_grade_user(
+    username: String,
+    watchers: tauri::State<'_, GradeWatchers>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
     let username = username.trim();
     if username.is_empty() {
-        return Err("请输入账号".to_string());
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
     }
-    let mut db = open_database()?;
+    watchers.stop(username);
+    let mut db = database.lock()?;
     db.hide_grade_user(username)
-        .map_err(|e| format!("Failed to delete user: {}", e))
 }
 
 #[derive(Deserialize)]
@@ -325,39 +673,52 @@ struct UpdatePasswordResultRequest {
 }
 
 #[tauri::command]
-fn update_password_result(request: UpdatePasswordResultRequest) -> Result<(), String> {
+fn update_password_result(
+    request: UpdatePasswordResultRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
     let username = request.username.trim();
     if username.is_empty() {
-        return Err("请输入账号".to_string());
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
     }
-    let mut db = open_database()?;
+    let mut db = database.lock()?;
     db.update_password_result(
         username,
         request.name.as_deref(),
         request.class_name.as_deref(),
         request.password_date.as_deref(),
     )
-    .map_err(|e| format!("Failed to update user: {}", e))
 }
 
 #[tauri::command]
-fn delete_password_result(username: String) -> Result<(), String> {
+fn clear_saved_credentials(
+    watchers: tauri::State<'_, GradeWatchers>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<usize, ToolboxError> {
+    // 没有密码后定时同步只会反复失败
+    watchers.stop_all();
+    let mut db = database.lock()?;
+    db.clear_saved_credentials()
+}
+
+#[tauri::command]
+fn delete_password_result(
+    username: String,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
     let username = username.trim();
     if username.is_empty() {
-        return Err("请输入账号".to_string());
+        return Err(ToolboxError::InvalidInput("请输入账号".to_string()));
     }
-    let mut db = open_database()?;
-    let (grade_count, plan_count) = db
-        .count_user_relations(username)
-        .map_err(|e| format!("Failed to check relations: {}", e))?;
+    let mut db = database.lock()?;
+    let (grade_count, plan_count) = db.count_user_relations(username)?;
     if grade_count > 0 || plan_count > 0 {
-        return Err(format!(
+        return Err(ToolboxError::Conflict(format!(
             "存在关联记录（成绩 {} 条，执行计划 {} 条），不允许删除",
             grade_count, plan_count
-        ));
+        )));
     }
     db.delete_password_result(username)
-        .map_err(|e| format!("Failed to delete user: {}", e))
 }
 
 #[derive(Deserialize)]
@@ -375,8 +736,11 @@ struct UpdateGradeRecordRequest {
 }
 
 #[tauri::command]
-fn update_grade_record(request: UpdateGradeRecordRequest) -> Result<(), String> {
-    let mut db = open_database()?;
+fn update_grade_record(
+    request: UpdateGradeRecordRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let mut db = database.lock()?;
     let input = db::UpdateGradeRecordInput {
         id: request.id,
         score: request.score,
@@ -390,14 +754,15 @@ fn update_grade_record(request: UpdateGradeRecordRequest) -> Result<(), String>
         course_nature: request.course_nature,
     };
     db.update_grade_record(&input)
-        .map_err(|e| format!("Failed to update grade: {}", e))
 }
 
 #[tauri::command]
-fn delete_grade_record(id: i32) -> Result<(), String> {
-    let mut db = open_database()?;
+fn delete_grade_record(
+    id: i32,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let mut db = database.lock()?;
     db.delete_grade_record(id)
-        .map_err(|e| format!("Failed to delete grade: {}", e))
 }
 
 #[derive(Deserialize)]
@@ -419,37 +784,397 @@ struct SyncScheduleRequest {
 }
 
 #[tauri::command]
-async fn sync_schedule(request: SyncScheduleRequest) -> Result<(), String> {
+async fn sync_schedule(
+    request: SyncScheduleRequest,
+    window: tauri::Window,
+    sessions: tauri::State<'_, JwxtSessions>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<ScheduleSyncSummary, ToolboxError> {
+    let profile = jwxt::load_profile()?;
+    let username = request.username.clone();
+    let result = run_sync_schedule(&database, &sessions, &profile, request).await;
+    emit_captcha_if_required(&window, &sessions, &username, result).await
+}
+
+async fn run_sync_schedule(
+    database: &SharedDatabase,
+    sessions: &JwxtSessions,
+    profile: &JwxtProfile,
+    request: SyncScheduleRequest,
+) -> Result<ScheduleSyncSummary, ToolboxError> {
     let username = request.username.trim();
     let password = request.password.trim();
     if username.is_empty() || password.is_empty() {
-        return Err("请输入账号和密码".to_string());
-    }
-    let fetch = schedule::fetch_schedule(username, password, request.term).await?;
-    let mut db = open_database()?;
-    db.upsert_schedule_terms(&fetch.terms)
-        .map_err(|e| format!("Failed to save terms: {}", e))?;
-    db.replace_schedule_entries(&fetch.term, &fetch.entries)
-        .map_err(|e| format!("Failed to save schedule: {}", e))?;
-    Ok(())
+        return Err(ToolboxError::InvalidInput("请输入账号和密码".to_string()));
+    }
+    let session = sessions.acquire(profile, username, password).await?;
+    let mut session = session.lock().await;
+    let fetch = schedule::fetch_schedule(&mut session, request.term).await?;
+    // 取不到周历时保留原有日期
+    let first_monday = schedule::fetch_term_start(&mut session, &fetch.term).await?;
+    drop(session);
+    let mut db = database.lock()?;
+    db.upsert_schedule_terms(&fetch.terms)?;
+    save_term_schedule(&mut db, fetch, first_monday)
+}
+
+fn save_term_schedule(
+    db: &mut Database,
+    fetch: schedule::ScheduleFetchResult,
+    first_monday: Option<chrono::NaiveDate>,
+) -> Result<ScheduleSyncSummary, ToolboxError> {
+    db.replace_schedule_entries(&fetch.term, &fetch.entries)?;
+    if let Some(date) = first_monday {
+        db.set_term_first_monday(
+            &fetch.term,
+            Some(&date.format("%Y-%m-%d").to_string()),
+            "jwxt",
+        )?;
+    }
+    Ok(ScheduleSyncSummary {
+        total: fetch.entries.len(),
+        term: fetch.term,
+        warnings: fetch.warnings,
+    })
+}
+
+#[derive(Deserialize)]
+struct SyncScheduleTermsRequest {
+    username: String,
+    password: String,
+    // 学期代码如 2023-2024-1，包含首尾；不填表示不限
+    from_term: Option<String>,
+    to_term: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ScheduleSyncProgress {
+    stage: String,
+    detail: String,
+    processed_terms: usize,
+    total_terms: usize,
 }
 
 #[tauri::command]
-fn get_schedule_terms() -> Result<Vec<db::ScheduleTerm>, String> {
-    let db = open_database()?;
+async fn sync_schedule_terms(
+    request: SyncScheduleTermsRequest,
+    window: tauri::Window,
+    sessions: tauri::State<'_, JwxtSessions>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<ScheduleSyncSummary>, ToolboxError> {
+    let profile = jwxt::load_profile()?;
+    let username = request.username.clone();
+    let result = run_sync_schedule_terms(&database, &sessions, &profile, request, |progress| {
+        let _ = window.emit(SCHEDULE_PROGRESS_EVENT, progress);
+    })
+    .await;
+    emit_captcha_if_required(&window, &sessions, &username, result).await
+}
+
+// 用同一个会话依次刷新范围内的每个学期，每个学期取完即入库，中途失败时已完成的学期保留。
+async fn run_sync_schedule_terms(
+    database: &SharedDatabase,
+    sessions: &JwxtSessions,
+    profile: &JwxtProfile,
+    request: SyncScheduleTermsRequest,
+    on_progress: impl Fn(&ScheduleSyncProgress),
+) -> Result<Vec<ScheduleSyncSummary>, ToolboxError> {
+    let username = request.username.trim();
+    let password = request.password.trim();
+    if username.is_empty() || password.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号和密码".to_string()));
+    }
+    let bound = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    let (from_term, to_term) = (bound(&request.from_term), bound(&request.to_term));
+    if let (Some(from), Some(to)) = (&from_term, &to_term) {
+        if from > to {
+            return Err(ToolboxError::InvalidInput(
+                "起始学期不能晚于结束学期".to_string(),
+            ));
+        }
+    }
+    let progress = |stage: &str, detail: &str, processed_terms: usize, total_terms: usize| {
+        on_progress(&ScheduleSyncProgress {
+            stage: stage.to_string(),
+            detail: detail.to_string(),
+            processed_terms,
+            total_terms,
+        })
+    };
+
+    let session = sessions.acquire(profile, username, password).await?;
+    let mut session = session.lock().await;
+    progress("获取学期", "正在读取学期列表", 0, 0);
+    let all_terms = schedule::fetch_terms(&mut session).await?;
+    // 学期代码按字典序即按时间先后
+    let terms = all_terms
+        .iter()
+        .filter(|term| from_term.as_ref().is_none_or(|from| *term >= from))
+        .filter(|term| to_term.as_ref().is_none_or(|to| *term <= to))
+        .cloned()
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        return Err(ToolboxError::NotFound("所选范围内没有学期".to_string()));
+    }
+    database.lock()?.upsert_schedule_terms(&all_terms)?;
+
+    let total = terms.len();
+    let mut summaries = Vec::new();
+    for (index, term) in terms.iter().enumerate() {
+        progress("同步课表", term, index, total);
+        let fetch = schedule::fetch_term_schedule(&mut session, term).await?;
+        let first_monday = schedule::fetch_term_start(&mut session, term).await?;
+        let mut db = database.lock()?;
+        summaries.push(save_term_schedule(&mut db, fetch, first_monday)?);
+    }
+    progress("完成", &format!("已同步 {} 个学期", total), total, total);
+    Ok(summaries)
+}
+
+fn term_calendar(db: &Database, term: &str) -> Result<TermCalendar, ToolboxError> {
+    match db.get_term_first_monday(term)? {
+        Some(date) => TermCalendar::parse(&date),
+        None => Err(ToolboxError::NotFound("该学期未设置开学日期".to_string())),
+    }
+}
+
+#[tauri::command]
+fn set_term_start(
+    term: String,
+    date: Option<String>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let term = term.trim();
+    if term.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择学期".to_string()));
+    }
+    let first_monday = match date.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        Some(date) => Some(
+            TermCalendar::parse(date)?
+                .first_monday()
+                .format("%Y-%m-%d")
+                .to_string(),
+        ),
+        None => None,
+    };
+    let mut db = database.lock()?;
+    db.set_term_first_monday(term, first_monday.as_deref(), "user")
+}
+
+#[tauri::command]
+fn get_term_week(
+    term: String,
+    date: Option<String>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<TermWeek, ToolboxError> {
+    let term = term.trim();
+    if term.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择学期".to_string()));
+    }
+    let date = match date.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| ToolboxError::InvalidInput("日期格式应为 YYYY-MM-DD".to_string()))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let db = database.lock()?;
+    Ok(term_calendar(&db, term)?.describe(term, date))
+}
+
+#[derive(Deserialize)]
+struct UpcomingClassesRequest {
+    now: Option<String>,
+    horizon_hours: Option<i64>,
+    term: Option<String>,
+    campus: Option<String>,
+}
+
+#[tauri::command]
+fn get_upcoming_classes(
+    request: UpcomingClassesRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<UpcomingClasses, ToolboxError> {
+    let now = match request
+        .now
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        Some(value) => chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+            .map_err(|_| ToolboxError::InvalidInput("时间格式应为 YYYY-MM-DDTHH:MM".to_string()))?,
+        None => chrono::Local::now().naive_local(),
+    };
+    let horizon = request.horizon_hours.unwrap_or(24);
+    if !(1..=24 * 30).contains(&horizon) {
+        return Err(ToolboxError::InvalidInput(
+            "查询范围需在 1 小时到 30 天之间".to_string(),
+        ));
+    }
+    let db = database.lock()?;
+    // 未指定学期时取开学日期不晚于今天的最近一个学期
+    let term = match request
+        .term
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        Some(term) => term.to_string(),
+        None => {
+            let today = now.date().format("%Y-%m-%d").to_string();
+            db.get_schedule_terms()?
+                .into_iter()
+                .filter_map(|t| t.first_monday.map(|date| (date, t.term)))
+                .filter(|(date, _)| *date <= today)
+                .max()
+                .map(|(_, term)| term)
+                .ok_or_else(|| ToolboxError::NotFound("没有已开学的学期".to_string()))?
+        }
+    };
+    let calendar = term_calendar(&db, &term)?;
+    let entries = db.get_schedule_entries(&term)?;
+    let timetable = db
+        .find_period_timetable(&term, request.campus.as_deref().map(str::trim))?
+        .ok_or_else(|| ToolboxError::NotFound("请先设置上课时间表".to_string()))?;
+    Ok(schedule::upcoming::upcoming_classes(
+        &calendar,
+        &timetable,
+        &entries,
+        now,
+        chrono::Duration::hours(horizon),
+    ))
+}
+
+#[derive(Deserialize)]
+struct ExportScheduleIcsRequest {
+    path: String,
+    term: String,
+    term_start: Option<String>,
+    campus: Option<String>,
+}
+
+#[tauri::command]
+async fn export_schedule_ics(
+    request: ExportScheduleIcsRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let path = request.path.trim().to_string();
+    if path.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择导出路径".to_string()));
+    }
+    if !path.to_lowercase().ends_with(".ics") {
+        return Err(ToolboxError::InvalidInput(
+            "仅支持导出为 .ics 文件".to_string(),
+        ));
+    }
+    let term = request.term.trim();
+    if term.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择学期".to_string()));
+    }
+    let db = database.lock()?;
+    let calendar = match request
+        .term_start
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        Some(date) => TermCalendar::parse(date)?,
+        None => term_calendar(&db, term)?,
+    };
+    let entries = db.get_schedule_entries(term)?;
+    let exams = db.get_exam_entries(Some(term))?;
+    if entries.is_empty() && exams.is_empty() {
+        return Err(ToolboxError::NotFound("该学期没有课表数据".to_string()));
+    }
+    let timetable = db
+        .find_period_timetable(term, request.campus.as_deref().map(str::trim))?
+        .ok_or_else(|| ToolboxError::NotFound("请先设置上课时间表".to_string()))?;
+    drop(db);
+    let content = schedule::ics::build_calendar(
+        term,
+        &calendar,
+        &timetable,
+        &entries,
+        &exams,
+        chrono::Utc::now(),
+    )?;
+    tokio::task::spawn_blocking(move || tech_stack::write_bytes_atomic(&path, content.as_bytes()))
+        .await
+        .map_err(|e| ToolboxError::internal("导出任务失败", e))?
+}
+
+#[tauri::command]
+fn get_period_timetables(
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::PeriodTimetable>, ToolboxError> {
+    let db = database.lock()?;
+    db.get_period_timetables()
+}
+
+#[tauri::command]
+fn save_period_timetable(
+    mut input: db::PeriodTimetableInput,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<i64, ToolboxError> {
+    input.name = input.name.trim().to_string();
+    for field in [&mut input.term, &mut input.campus] {
+        *field = field
+            .take()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+    }
+    schedule::periods::validate(&input)?;
+    let mut db = database.lock()?;
+    db.save_period_timetable(&input)
+}
+
+#[tauri::command]
+fn delete_period_timetable(
+    id: i64,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let mut db = database.lock()?;
+    db.delete_period_timetable(id)
+}
+
+#[tauri::command]
+fn get_schedule_terms(
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::ScheduleTerm>, ToolboxError> {
+    let db = database.lock()?;
     db.get_schedule_terms()
-        .map_err(|e| format!("Failed to query terms: {}", e))
 }
 
 #[tauri::command]
-fn get_schedule_entries(term: String) -> Result<Vec<db::ScheduleEntry>, String> {
+fn get_schedule_entries(
+    term: String,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::ScheduleEntry>, ToolboxError> {
     let term = term.trim();
     if term.is_empty() {
-        return Err("请选择学期".to_string());
+        return Err(ToolboxError::InvalidInput("请选择学期".to_string()));
     }
-    let db = open_database()?;
+    let db = database.lock()?;
     db.get_schedule_entries(term)
-        .map_err(|e| format!("Failed to query schedule: {}", e))
+}
+
+#[tauri::command]
+fn check_schedule_conflicts(
+    term: String,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<ConflictReport, ToolboxError> {
+    let term = term.trim();
+    if term.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择学期".to_string()));
+    }
+    let db = database.lock()?;
+    let entries = db.get_schedule_entries(term)?;
+    Ok(schedule::conflicts::check(term, &entries))
 }
 
 #[derive(Deserialize)]
@@ -458,35 +1183,160 @@ struct UpdateScheduleEntryRequest {
     course_name: Option<String>,
     teacher: Option<String>,
     location: Option<String>,
+    class_group: Option<String>,
     week_text: Option<String>,
     week_numbers: Option<Vec<i32>>,
 }
 
 #[tauri::command]
-fn update_schedule_entry(request: UpdateScheduleEntryRequest) -> Result<(), String> {
-    let mut db = open_database()?;
+fn update_schedule_entry(
+    request: UpdateScheduleEntryRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    // 只改周次文字时据此重算周次，只改周次时生成对应的文字，两者始终一致
+    let (week_text, week_numbers) = match (request.week_text, request.week_numbers) {
+        (Some(text), None) => {
+            let parsed = schedule::week_text::parse(&text);
+            if !parsed.warnings.is_empty() {
+                return Err(ToolboxError::InvalidInput(parsed.warnings.join("；")));
+            }
+            (Some(parsed.week_text), Some(parsed.week_numbers))
+        }
+        (None, Some(weeks)) => (Some(schedule::week_text::format(&weeks)), Some(weeks)),
+        other => other,
+    };
+    let mut db = database.lock()?;
     let input = db::UpdateScheduleEntryInput {
         id: request.id,
         course_name: request.course_name,
         teacher: request.teacher,
         location: request.location,
-        week_text: request.week_text,
-        week_numbers: request.week_numbers,
+        class_group: request.class_group,
+        week_text,
+        week_numbers,
     };
     db.update_schedule_entry(&input)
-        .map_err(|e| format!("Failed to update schedule: {}", e))
 }
 
 #[tauri::command]
-fn delete_schedule_entry(id: i32) -> Result<(), String> {
-    let mut db = open_database()?;
+fn delete_schedule_entry(
+    id: i32,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let mut db = database.lock()?;
     db.delete_schedule_entry(id)
-        .map_err(|e| format!("Failed to delete schedule: {}", e))
+}
+
+#[derive(Deserialize)]
+struct SyncExamsRequest {
+    username: String,
+    password: String,
+    term: Option<String>,
 }
 
 #[tauri::command]
-fn update_plan_course(request: UpdatePlanCourseRequest) -> Result<(), String> {
-    let mut db = open_database()?;
+async fn sync_exams(
+    request: SyncExamsRequest,
+    window: tauri::Window,
+    sessions: tauri::State<'_, JwxtSessions>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<ExamSyncSummary, ToolboxError> {
+    let profile = jwxt::load_profile()?;
+    let username = request.username.clone();
+    let result = run_sync_exams(&database, &sessions, &profile, request).await;
+    emit_captcha_if_required(&window, &sessions, &username, result).await
+}
+
+async fn run_sync_exams(
+    database: &SharedDatabase,
+    sessions: &JwxtSessions,
+    profile: &JwxtProfile,
+    request: SyncExamsRequest,
+) -> Result<ExamSyncSummary, ToolboxError> {
+    let username = request.username.trim();
+    let password = request.password.trim();
+    if username.is_empty() || password.is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入账号和密码".to_string()));
+    }
+    let session = sessions.acquire(profile, username, password).await?;
+    let mut session = session.lock().await;
+    let fetch = exams::fetch_exams(&mut session, request.term).await?;
+    drop(session);
+    let mut db = database.lock()?;
+    db.upsert_exam_entries(&fetch.term, &fetch.entries)?;
+    Ok(ExamSyncSummary {
+        total: fetch.entries.len(),
+        scheduled: fetch
+            .entries
+            .iter()
+            .filter(|entry| entry.starts_at.is_some())
+            .count(),
+        term: fetch.term,
+    })
+}
+
+#[tauri::command]
+fn get_exam_entries(
+    term: Option<String>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<db::ExamEntry>, ToolboxError> {
+    let term = term
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    let db = database.lock()?;
+    db.get_exam_entries(term)
+}
+
+#[derive(Deserialize)]
+struct UpdateExamEntryRequest {
+    id: i32,
+    exam_time: Option<String>,
+    room: Option<String>,
+    seat_number: Option<String>,
+    exam_type: Option<String>,
+    note: Option<String>,
+}
+
+#[tauri::command]
+fn update_exam_entry(
+    request: UpdateExamEntryRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    // 改考试时间时同步更新解析出的起止时间，无法识别的写法直接拒绝；传空字符串表示清空
+    let exam_time = request.exam_time.map(|v| v.trim().to_string());
+    let (starts_at, ends_at) = match exam_time.as_deref() {
+        Some("") => (Some(String::new()), Some(String::new())),
+        Some(text) => {
+            let (start, end) = exams::parse_exam_time(text).ok_or_else(|| {
+                ToolboxError::InvalidInput(
+                    "考试时间格式应为 YYYY-MM-DD HH:MM~HH:MM".to_string(),
+                )
+            })?;
+            (Some(start), Some(end))
+        }
+        None => (None, None),
+    };
+    let mut db = database.lock()?;
+    let input = db::UpdateExamEntryInput {
+        id: request.id,
+        exam_time,
+        starts_at,
+        ends_at,
+        room: request.room,
+        seat_number: request.seat_number,
+        exam_type: request.exam_type,
+        note: request.note,
+    };
+    db.update_exam_entry(&input)
+}
+
+#[tauri::command]
+fn update_plan_course(
+    request: UpdatePlanCourseRequest,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let mut db = database.lock()?;
     let input = db::UpdatePlanCourseInput {
         id: request.id,
         course_name: request.course_name,
@@ -497,14 +1347,95 @@ fn update_plan_course(request: UpdatePlanCourseRequest) -> Result<(), String> {
         course_attr: request.course_attr,
     };
     db.update_plan_course(&input)
-        .map_err(|e| format!("Failed to update plan: {}", e))
 }
 
 #[tauri::command]
-fn delete_plan_course(id: i32) -> Result<(), String> {
-    let mut db = open_database()?;
+fn delete_plan_course(
+    id: i32,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let mut db = database.lock()?;
     db.delete_plan_course(id)
-        .map_err(|e| format!("Failed to delete plan: {}", e))
+}
+
+#[derive(Serialize)]
+struct BackupResult {
+    path: String,
+}
+
+// dir 为空时备份到数据库所在目录下的 backups。
+#[tauri::command]
+async fn backup_database(
+    dir: Option<String>,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<BackupResult, ToolboxError> {
+    let db_path = database.path()?;
+    let dir = match dir.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => backup::default_backup_dir(&db_path),
+    };
+    tokio::task::spawn_blocking(move || backup::backup_database(&db_path, &dir, "toolbox"))
+        .await
+        .map_err(|e| ToolboxError::internal("备份任务失败", e))?
+        .map(|path| BackupResult {
+            path: path.to_string_lossy().to_string(),
+        })
+}
+
+// 返回恢复前自动另存的备份路径，便于用户撤销。恢复期间共享连接关闭，其他命令等待。
+#[tauri::command]
+async fn restore_database(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<BackupResult, ToolboxError> {
+    let source = path.trim().to_string();
+    if source.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择备份文件".to_string()));
+    }
+    tokio::task::spawn_blocking(move || {
+        app.state::<SharedDatabase>()
+            .with_closed(|db_path| backup::restore_database(db_path, Path::new(&source)))
+    })
+    .await
+    .map_err(|e| ToolboxError::internal("恢复任务失败", e))?
+    .map(|path| BackupResult {
+        path: path.to_string_lossy().to_string(),
+    })
+}
+
+#[tauri::command]
+async fn export_data_json(
+    path: String,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<(), ToolboxError> {
+    let path = path.trim().to_string();
+    if path.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择导出路径".to_string()));
+    }
+    let dump = backup::export_dump(&database.lock()?)?;
+    let bytes = serde_json::to_vec_pretty(&dump)
+        .map_err(|e| ToolboxError::internal("序列化数据失败", e))?;
+    tokio::task::spawn_blocking(move || tech_stack::write_bytes_atomic(&path, &bytes))
+        .await
+        .map_err(|e| ToolboxError::internal("导出任务失败", e))?
+}
+
+#[tauri::command]
+fn import_data_json(
+    path: String,
+    database: tauri::State<'_, SharedDatabase>,
+) -> Result<backup::DumpImportSummary, ToolboxError> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err(ToolboxError::InvalidInput("请选择导入文件".to_string()));
+    }
+    let text =
+        std::fs::read_to_string(path).map_err(|e| ToolboxError::io("读取导入文件失败", e))?;
+    let dump: backup::DataDump =
+        serde_json::from_str(&text).map_err(|e| ToolboxError::parse("导入文件格式错误", e))?;
+    let db_path = database.path()?;
+    let mut db = database.lock()?;
+    backup::import_dump(&db_path, &mut db, &dump)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -513,10 +1444,17 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_opener::init())
-        .setup(|_| {
+        .manage(JwxtSessions::default())
+        .manage(GradeWatchers::default())
+        .manage(SharedDatabase::default())
+        .setup(|app| {
             if let Err(err) = db::migrate_if_needed() {
                 eprintln!("Database migration failed: {}", err);
             }
+            // 启动时就打开并完成迁移，失败时留到首次使用再报给前端
+            if let Err(err) = app.state::<SharedDatabase>().lock() {
+                eprintln!("Database open failed: {}", err);
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -527,21 +1465,50 @@ pub fn run() {
             import_dates,
             sync_grades,
             sync_grades_saved,
+            submit_jwxt_captcha,
+            get_jwxt_profile,
+            save_jwxt_profile,
             get_grade_users,
             get_grades,
+            get_grade_history,
+            start_grade_watch,
+            stop_grade_watch,
+            get_grade_watches,
+            get_grade_summary,
+            compare_grade_users,
+            export_grades,
+            get_graduation_audit,
+            set_graduation_requirement,
             get_pending_courses,
             hide_grade_user,
             update_password_result,
             delete_password_result,
+            clear_saved_credentials,
             update_grade_record,
             delete_grade_record,
             update_plan_course,
             delete_plan_course,
             sync_schedule,
+            sync_schedule_terms,
             get_schedule_terms,
             get_schedule_entries,
             update_schedule_entry,
             delete_schedule_entry,
+            check_schedule_conflicts,
+            export_schedule_ics,
+            sync_exams,
+            get_exam_entries,
+            update_exam_entry,
+            set_term_start,
+            get_term_week,
+            get_upcoming_classes,
+            get_period_timetables,
+            save_period_timetable,
+            delete_period_timetable,
+            backup_database,
+            restore_database,
+            export_data_json,
+            import_data_json,
             scan_project_tree,
             save_tree_to_file,
             scan_tech_stack_local,
@@ -556,3 +1523,321 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::{MockJwxt, MockLoginMode};
+
+    const USERNAME: &str = "2023001";
+    const PASSWORD: &str = "20050101";
+
+    fn schedule_request(term: Option<&str>) -> SyncScheduleRequest {
+        SyncScheduleRequest {
+            username: USERNAME.to_string(),
+            password: PASSWORD.to_string(),
+            term: term.map(|t| t.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_grades_stores_grades_plans_and_password() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+
+        let summary = run_sync_grades(&database, &sessions, &mock.profile(), USERNAME, PASSWORD)
+            .await
+            .unwrap();
+        assert_eq!(summary.total, 6);
+        assert_eq!((summary.inserted, summary.updated), (6, 0));
+        assert_eq!(summary.changes.len(), 6);
+        assert!(summary.baseline);
+
+        let db = database.lock().unwrap();
+        let grades = db.get_grades_by_username(USERNAME).unwrap();
+        assert_eq!(grades.len(), 6);
+        let minors = grades
+            .iter()
+            .filter(|g| g.is_minor)
+            .map(|g| g.course_code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(minors, vec!["F0901001"]);
+
+        let mut pending = db
+            .get_pending_courses(USERNAME, -1)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.course_code, c.is_minor))
+            .collect::<Vec<_>>();
+        pending.sort();
+        assert_eq!(
+            pending,
+            vec![
+                ("C0501010".to_string(), false),
+                ("C0502020".to_string(), false),
+                ("F0901002".to_string(), true),
+            ]
+        );
+        assert_eq!(
+            db.get_saved_password(USERNAME).unwrap().as_deref(),
+            Some(PASSWORD)
+        );
+        assert_eq!(db.get_grade_users().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sync_grades_saved_uses_stored_password() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+
+        let err = run_sync_grades_saved(&database, &sessions, &mock.profile(), USERNAME)
+            .await
+            .unwrap_err();
+        assert_eq!(err, ToolboxError::NotFound("该账号未保存密码".to_string()));
+
+        run_sync_grades(&database, &sessions, &mock.profile(), USERNAME, PASSWORD)
+            .await
+            .unwrap();
+        let summary = run_sync_grades_saved(
+            &database,
+            &JwxtSessions::default(),
+            &mock.profile(),
+            USERNAME,
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.total, 6);
+        assert_eq!((summary.inserted, summary.updated), (0, 6));
+        assert!(summary.changes.is_empty());
+        assert!(!summary.baseline);
+        assert_eq!(mock.login_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_plan_fetch_keeps_saved_plan_and_minor_flags() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+        run_sync_grades(&database, &sessions, &mock.profile(), USERNAME, PASSWORD)
+            .await
+            .unwrap();
+
+        let mut profile = mock.profile();
+        profile.minor_plan_path = "/jsxsd/pyfa/missing".to_string();
+        let err = run_sync_grades_saved(&database, &JwxtSessions::default(), &profile, USERNAME)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolboxError::NotFound(_)), "{:?}", err);
+
+        let db = database.lock().unwrap();
+        let grades = db.get_grades_by_username(USERNAME).unwrap();
+        assert_eq!(grades.iter().filter(|g| g.is_minor).count(), 1);
+        assert_eq!(db.get_pending_courses(USERNAME, -1).unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn sync_reports_wrong_password_and_captcha() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+
+        let err = run_sync_grades(&database, &sessions, &mock.profile(), USERNAME, "wrong")
+            .await
+            .unwrap_err();
+        assert_eq!(err, ToolboxError::WrongPassword);
+
+        mock.set_login_mode(MockLoginMode::Captcha);
+        let err = run_sync_schedule(
+            &database,
+            &sessions,
+            &mock.profile(),
+            schedule_request(None),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err, ToolboxError::CaptchaRequired);
+        assert_eq!(mock.login_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn captcha_answer_completes_login_in_same_session() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        mock.set_login_mode(MockLoginMode::Captcha);
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+
+        let err = run_sync_grades(&database, &sessions, &mock.profile(), USERNAME, PASSWORD)
+            .await
+            .unwrap_err();
+        assert_eq!(err, ToolboxError::CaptchaRequired);
+        let challenge = sessions.take_captcha(USERNAME).await.unwrap();
+        assert_eq!(challenge.username, USERNAME);
+        assert!(challenge.image.starts_with("data:image/jpeg;base64,"));
+        assert!(sessions.take_captcha(USERNAME).await.is_none());
+
+        let err = run_submit_captcha(&sessions, USERNAME, "wrong")
+            .await
+            .unwrap_err();
+        assert_eq!(err, ToolboxError::CaptchaRequired);
+        assert!(sessions.take_captcha(USERNAME).await.is_some());
+
+        let answer = mock.captcha_answer().unwrap();
+        run_submit_captcha(&sessions, USERNAME, &answer)
+            .await
+            .unwrap();
+        let summary = run_sync_grades(&database, &sessions, &mock.profile(), USERNAME, PASSWORD)
+            .await
+            .unwrap();
+        assert_eq!(summary.total, 6);
+        assert_eq!(mock.login_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn grades_and_schedule_share_one_login() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+
+        run_sync_grades(&database, &sessions, &mock.profile(), USERNAME, PASSWORD)
+            .await
+            .unwrap();
+        let summary = run_sync_schedule(
+            &database,
+            &sessions,
+            &mock.profile(),
+            schedule_request(None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(mock.login_count(), 1);
+        assert_eq!(summary.total, 7);
+        assert!(summary.warnings.is_empty());
+
+        let db = database.lock().unwrap();
+        let terms = db
+            .get_schedule_terms()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.term)
+            .collect::<Vec<_>>();
+        assert_eq!(terms.len(), 4);
+        assert!(terms.contains(&"2024-2025-2".to_string()));
+        assert_eq!(db.get_schedule_entries("2024-2025-2").unwrap().len(), 7);
+        assert_eq!(
+            db.get_term_first_monday("2024-2025-2").unwrap().as_deref(),
+            Some("2025-02-24")
+        );
+    }
+
+    #[tokio::test]
+    async fn syncs_every_term_in_range_with_one_login() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+        let events = std::sync::Mutex::new(Vec::new());
+
+        let request = SyncScheduleTermsRequest {
+            username: USERNAME.to_string(),
+            password: PASSWORD.to_string(),
+            from_term: Some("2023-2024-2".to_string()),
+            to_term: None,
+        };
+        let summaries =
+            run_sync_schedule_terms(&database, &sessions, &mock.profile(), request, |p| {
+                events.lock().unwrap().push(p.clone())
+            })
+            .await
+            .unwrap();
+        assert_eq!(mock.login_count(), 1);
+        let synced = summaries
+            .iter()
+            .map(|s| (s.term.as_str(), s.total))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            synced,
+            vec![("2024-2025-2", 7), ("2024-2025-1", 0), ("2023-2024-2", 0)]
+        );
+        let events = events.into_inner().unwrap();
+        let last = events.last().unwrap();
+        assert_eq!((last.processed_terms, last.total_terms), (3, 3));
+        assert_eq!(events.iter().filter(|p| p.stage == "同步课表").count(), 3);
+
+        let db = database.lock().unwrap();
+        assert_eq!(db.get_schedule_terms().unwrap().len(), 4);
+        assert_eq!(db.get_schedule_entries("2024-2025-2").unwrap().len(), 7);
+        assert_eq!(
+            db.get_term_first_monday("2024-2025-2").unwrap().as_deref(),
+            Some("2025-02-24")
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_session_logs_in_again() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+
+        run_sync_schedule(
+            &database,
+            &sessions,
+            &mock.profile(),
+            schedule_request(None),
+        )
+        .await
+        .unwrap();
+        mock.expire_sessions();
+        run_sync_schedule(
+            &database,
+            &sessions,
+            &mock.profile(),
+            schedule_request(Some("2023-2024-1")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(mock.login_count(), 2);
+
+        let db = database.lock().unwrap();
+        assert!(db.get_schedule_entries("2023-2024-1").unwrap().is_empty());
+        assert_eq!(db.get_schedule_entries("2024-2025-2").unwrap().len(), 7);
+    }
+
+    #[tokio::test]
+    async fn syncs_exam_arrangement_for_selected_term() {
+        let mock = MockJwxt::start(USERNAME, PASSWORD).await;
+        let dir = tempfile::tempdir().unwrap();
+        let database = SharedDatabase::at(dir.path().join("toolbox.db"));
+        let sessions = JwxtSessions::default();
+
+        let request = SyncExamsRequest {
+            username: USERNAME.to_string(),
+            password: PASSWORD.to_string(),
+            term: None,
+        };
+        let summary = run_sync_exams(&database, &sessions, &mock.profile(), request)
+            .await
+            .unwrap();
+        assert_eq!(summary.term, "2024-2025-2");
+        assert_eq!((summary.total, summary.scheduled), (3, 2));
+
+        let db = database.lock().unwrap();
+        let exams = db.get_exam_entries(Some("2024-2025-2")).unwrap();
+        let order = exams
+            .iter()
+            .map(|e| e.course_code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec!["B0101002", "B0201004", "B0301001"]);
+        assert_eq!(exams[0].room.as_deref(), Some("博学楼101"));
+        assert_eq!(exams[0].seat_number.as_deref(), Some("23"));
+        assert!(db.get_exam_entries(Some("2024-2025-1")).unwrap().is_empty());
+    }
+}
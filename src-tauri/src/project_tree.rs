@@ -8,6 +8,8 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use crate::error::{Result, ToolboxError};
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum NodeKind {
     Dir,
@@ -44,26 +46,28 @@ pub struct ProjectTreeScanResult {
 }
 
 #[tauri::command]
-pub async fn scan_project_tree(root_path: String) -> Result<ProjectTreeScanResult, String> {
+pub async fn scan_project_tree(root_path: String) -> Result<ProjectTreeScanResult> {
     let root_path = root_path.trim().to_string();
     if root_path.is_empty() {
-        return Err("请输入项目根目录路径".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "请输入项目根目录路径".to_string(),
+        ));
     }
 
     let root = PathBuf::from(&root_path);
     if !root.exists() {
-        return Err("指定目录不存在".to_string());
+        return Err(ToolboxError::InvalidInput("指定目录不存在".to_string()));
     }
     if !root.is_dir() {
-        return Err("指定路径不是目录".to_string());
+        return Err(ToolboxError::InvalidInput("指定路径不是目录".to_string()));
     }
 
     tokio::task::spawn_blocking(move || scan_project_tree_blocking(&root))
         .await
-        .map_err(|e| format!("扫描任务失败: {}", e))?
+        .map_err(|e| ToolboxError::internal("扫描任务失败", e))?
 }
 
-fn scan_project_tree_blocking(root: &Path) -> Result<ProjectTreeScanResult, String> {
+fn scan_project_tree_blocking(root: &Path) -> Result<ProjectTreeScanResult> {
     let mut tree = Node::dir();
 
     let (used_git, file_paths) = get_git_tracked_files(root).unwrap_or((false, Vec::new()));
@@ -127,7 +131,7 @@ fn get_git_tracked_files(root: &Path) -> Option<(bool, Vec<String>)> {
     Some((true, files))
 }
 
-fn walk_filesystem(root: &Path, current: &Path, tree: &mut Node) -> Result<(), String> {
+fn walk_filesystem(root: &Path, current: &Path, tree: &mut Node) -> Result<()> {
     let entries = match fs::read_dir(current) {
         Ok(read_dir) => read_dir,
         Err(_) => return Ok(()),
@@ -248,43 +252,45 @@ fn render_children(node: &Node, prefix: &str, path_stack: &mut Vec<String>, line
 }
 
 #[tauri::command]
-pub async fn save_tree_to_file(path: String, content: String) -> Result<(), String> {
+pub async fn save_tree_to_file(path: String, content: String) -> Result<()> {
     let path = path.trim().to_string();
     if path.is_empty() {
-        return Err("请选择保存路径".to_string());
+        return Err(ToolboxError::InvalidInput("请选择保存路径".to_string()));
     }
 
     tokio::task::spawn_blocking(move || save_tree_to_file_blocking(&path, &content))
         .await
-        .map_err(|e| format!("保存任务失败: {}", e))?
+        .map_err(|e| ToolboxError::internal("保存任务失败", e))?
 }
 
-fn save_tree_to_file_blocking(path: &str, content: &str) -> Result<(), String> {
+fn save_tree_to_file_blocking(path: &str, content: &str) -> Result<()> {
     let target = PathBuf::from(path);
     let ext = target
         .extension()
         .and_then(|v| v.to_str())
         .map(|s| s.to_ascii_lowercase())
-        .ok_or_else(|| "仅支持保存为 .txt 或 .md 文件".to_string())?;
+        .ok_or_else(|| ToolboxError::InvalidInput("仅支持保存为 .txt 或 .md 文件".to_string()))?;
 
     if ext != "txt" && ext != "md" {
-        return Err("仅支持保存为 .txt 或 .md 文件".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "仅支持保存为 .txt 或 .md 文件".to_string(),
+        ));
     }
 
     let parent = target
         .parent()
-        .ok_or_else(|| "保存路径无效".to_string())?;
+        .ok_or_else(|| ToolboxError::InvalidInput("保存路径无效".to_string()))?;
     if !parent.exists() {
-        return Err("保存目录不存在".to_string());
+        return Err(ToolboxError::InvalidInput("保存目录不存在".to_string()));
     }
 
     let file_name = target
         .file_name()
         .and_then(|v| v.to_str())
-        .ok_or_else(|| "保存路径无效".to_string())?;
+        .ok_or_else(|| ToolboxError::InvalidInput("保存路径无效".to_string()))?;
     let nonce = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|_| "无法生成临时文件名".to_string())?
+        .map_err(|e| ToolboxError::internal("无法生成临时文件名", e))?
         .as_nanos();
     let tmp_name = format!(".{}.{}.tmp", file_name, nonce);
     let tmp_path = parent.join(tmp_name);
@@ -294,17 +300,18 @@ fn save_tree_to_file_blocking(path: &str, content: &str) -> Result<(), String> {
         .write(true)
         .truncate(true)
         .open(&tmp_path)
-        .map_err(|e| format!("无法写入文件: {}", e))?;
+        .map_err(|e| ToolboxError::io("无法写入文件", e))?;
     file.write_all(content.as_bytes())
-        .map_err(|e| format!("写入失败: {}", e))?;
-    file.sync_all().map_err(|e| format!("写入失败: {}", e))?;
+        .map_err(|e| ToolboxError::io("写入失败", e))?;
+    file.sync_all()
+        .map_err(|e| ToolboxError::io("写入失败", e))?;
     drop(file);
 
     if target.exists() {
         let _ = fs::remove_file(&target);
     }
 
-    fs::rename(&tmp_path, &target).map_err(|e| format!("保存失败: {}", e))?;
+    fs::rename(&tmp_path, &target).map_err(|e| ToolboxError::io("保存失败", e))?;
     Ok(())
 }
 
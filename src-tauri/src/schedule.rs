@@ -1,77 +1,25 @@
-use base64::{engine::general_purpose, Engine as _};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use scraper::{Html, Selector};
+pub mod conflicts;
+pub mod ics;
+pub mod periods;
+pub mod upcoming;
+pub mod week_text;
+pub mod weeks;
+
+use chrono::NaiveDate;
+use scraper::{ElementRef, Html, Selector};
 use std::collections::HashSet;
 
 use crate::db::ScheduleEntryInput;
-
-const LOGIN_URL: &str = "http://jwxt.ahut.edu.cn/jsxsd/xk/LoginToXk";
-const SCHEDULE_URL: &str = "http://jwxt.ahut.edu.cn/jsxsd/xskb/xskb_list.do";
+use crate::error::{Result, ToolboxError};
+use crate::jwxt::JwxtSession;
+use weeks::TermCalendar;
 
 pub struct ScheduleFetchResult {
     pub term: String,
     pub terms: Vec<String>,
     pub entries: Vec<ScheduleEntryInput>,
-}
-
-fn build_client() -> Result<reqwest::Client, String> {
-    let headers = [
-        ("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/136.0.0.0 Safari/537.36"),
-        ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"),
-        ("Accept-Language", "zh-CN,zh-TW;q=0.9,zh-HK;q=0.8,zh;q=0.7"),
-    ];
-
-    let mut default_headers = HeaderMap::new();
-    for (key, value) in &headers {
-        let header_name = HeaderName::from_bytes(key.as_bytes())
-            .map_err(|e| format!("Invalid header name {}: {}", key, e))?;
-        let header_value =
-            HeaderValue::from_str(value).map_err(|e| format!("Invalid header value: {}", e))?;
-        default_headers.insert(header_name, header_value);
-    }
-
-    reqwest::Client::builder()
-        .default_headers(default_headers)
-        .cookie_store(true)
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))
-}
-
-fn encode_login_params(username: &str, password: &str) -> String {
-    let username_encoded = general_purpose::STANDARD.encode(username.as_bytes());
-    let password_encoded = general_purpose::STANDARD.encode(password.as_bytes());
-    format!("{}%%%{}", username_encoded, password_encoded)
-}
-
-async fn login(username: &str, password: &str, client: &reqwest::Client) -> Result<(), String> {
-    let encoded_value = encode_login_params(username, password);
-    let params = [
-        ("userAccount", username),
-        ("userPassword", ""),
-        ("encoded", encoded_value.as_str()),
-        ("pwdstr1", ""),
-        ("pwdstr2", ""),
-    ];
-
-    let response = client
-        .post(LOGIN_URL)
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| format!("Login request failed: {}", e))?;
-
-    let text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read login response: {}", e))?;
-
-    if text.contains("用户名或密码错误") {
-        return Err("用户名或密码错误".to_string());
-    }
-    if text.contains("验证码") {
-        return Err("登录需要验证码".to_string());
-    }
-    Ok(())
+    // 周次无法完整识别的课程，课程本身仍会保存
+    pub warnings: Vec<String>,
 }
 
 fn normalize_text(value: &str) -> String {
@@ -83,9 +31,12 @@ fn normalize_text(value: &str) -> String {
         .join(" ")
 }
 
-fn parse_terms(html: &str) -> (Vec<String>, Option<String>) {
+fn parse_terms(html: &str, term_field: &str) -> (Vec<String>, Option<String>) {
     let document = Html::parse_document(html);
-    let selector = Selector::parse("#xnxq01id option").unwrap();
+    let selector = match Selector::parse(&format!("#{} option", term_field)) {
+        Ok(selector) => selector,
+        Err(_) => return (Vec::new(), None),
+    };
     let mut terms = Vec::new();
     let mut selected = None;
     for option in document.select(&selector) {
@@ -117,55 +68,91 @@ fn parse_period_index(label: &str) -> Option<i32> {
     num.parse::<i32>().ok()
 }
 
-fn parse_week_numbers(week_text: &str) -> Vec<i32> {
-    let mut filtered = week_text.to_string();
-    let is_odd = filtered.contains("单周");
-    let is_even = filtered.contains("双周");
-    if let Some(idx) = filtered.find('周') {
-        filtered = filtered[..idx].to_string();
+// “第五大节(09,10,11小节)”括号里是这一大节包含的小节；没有括号时只占 period_index 一节。
+pub(crate) fn period_sections(label: &str, period_index: Option<i32>) -> Vec<i32> {
+    let inner = label
+        .split_once(['(', '（'])
+        .and_then(|(_, rest)| rest.split([')', '）']).next())
+        .unwrap_or_default();
+    let sections = inner
+        .split([',', '，'])
+        .filter_map(|part| {
+            let digits = part
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .collect::<String>();
+            digits.parse::<i32>().ok()
+        })
+        .collect::<Vec<_>>();
+    if sections.is_empty() {
+        period_index.into_iter().collect()
+    } else {
+        sections
     }
-    let mut cleaned = String::new();
-    for ch in filtered.chars() {
-        if ch.is_ascii_digit() || ch == '-' || ch == ',' || ch == '，' {
-            cleaned.push(if ch == '，' { ',' } else { ch });
-        } else {
-            cleaned.push(' ');
-        }
+}
+
+// 单元格中的一行：带 title 的 <font title='老师'>张三</font> 字段，或无标记的纯文本。
+struct CellLine {
+    title: Option<String>,
+    text: String,
+}
+
+enum CellField {
+    Teacher,
+    Weeks,
+    Location,
+    ClassGroup,
+}
+
+fn cell_field(title: &str) -> Option<CellField> {
+    if title.contains("老师") || title.contains("教师") {
+        Some(CellField::Teacher)
+    } else if title.contains("周次") {
+        Some(CellField::Weeks)
+    } else if title.contains("教室") || title.contains("地点") {
+        Some(CellField::Location)
+    } else if title.contains("班") || title.contains("分组") || title.contains("课堂") {
+        Some(CellField::ClassGroup)
+    } else {
+        None
     }
-    let mut weeks = Vec::new();
-    for part in cleaned.split_whitespace() {
-        for token in part.split(',') {
-            let token = token.trim();
-            if token.is_empty() {
-                continue;
-            }
-            if let Some((start, end)) = token.split_once('-') {
-                if let (Ok(s), Ok(e)) = (start.parse::<i32>(), end.parse::<i32>()) {
-                    let (min, max) = if s <= e { (s, e) } else { (e, s) };
-                    for n in min..=max {
-                        weeks.push(n);
-                    }
-                }
-            } else if let Ok(n) = token.parse::<i32>() {
-                weeks.push(n);
-            }
+}
+
+// “1-16(周)[01-02节]”后面的节次与所在行重复，只保留周次部分。
+fn strip_sections(text: &str) -> String {
+    text.split_once('[')
+        .map(|(weeks, _)| weeks.trim())
+        .unwrap_or(text)
+        .to_string()
+}
+
+fn cell_lines(div: ElementRef) -> Vec<CellLine> {
+    let mut lines = Vec::new();
+    for child in div.children() {
+        if let Some(text) = child.value().as_text() {
+            lines.push(CellLine {
+                title: None,
+                text: normalize_text(text),
+            });
+        } else if let Some(element) = ElementRef::wrap(child) {
+            lines.push(CellLine {
+                title: element
+                    .value()
+                    .attr("title")
+                    .map(normalize_text)
+                    .filter(|v| !v.is_empty()),
+                text: normalize_text(&element.text().collect::<Vec<_>>().join(" ")),
+            });
         }
     }
-    if is_odd {
-        weeks.retain(|n| n % 2 == 1);
-    } else if is_even {
-        weeks.retain(|n| n % 2 == 0);
-    }
-    weeks.sort();
-    weeks.dedup();
-    weeks
+    lines
 }
 
-fn split_blocks(lines: &[String]) -> Vec<Vec<String>> {
+fn split_blocks(lines: Vec<CellLine>) -> Vec<Vec<CellLine>> {
     let mut blocks = Vec::new();
     let mut current = Vec::new();
     for line in lines {
-        let trimmed = line.trim();
+        let trimmed = line.text.trim();
         if trimmed.chars().all(|c| c == '-') && trimmed.len() >= 5 {
             if !current.is_empty() {
                 blocks.push(current);
@@ -176,7 +163,7 @@ fn split_blocks(lines: &[String]) -> Vec<Vec<String>> {
         if trimmed.is_empty() {
             continue;
         }
-        current.push(trimmed.to_string());
+        current.push(line);
     }
     if !current.is_empty() {
         blocks.push(current);
@@ -189,45 +176,50 @@ fn parse_cell_entries(
     weekday: i32,
     period_label: &str,
     period_index: Option<i32>,
-    cell: scraper::element_ref::ElementRef,
+    cell: ElementRef,
 ) -> Vec<ScheduleEntryInput> {
-    let div_selector = Selector::parse("div.kbcontent1").unwrap();
+    let full_selector = Selector::parse("div.kbcontent").unwrap();
+    let brief_selector = Selector::parse("div.kbcontent1").unwrap();
+    let has_text = |div: &ElementRef| !normalize_text(&div.text().collect::<String>()).is_empty();
+    // 隐藏的 kbcontent 才带教师、班级等完整信息，只有 kbcontent1 的旧页面退回简略版
+    let mut divs = cell
+        .select(&full_selector)
+        .filter(has_text)
+        .collect::<Vec<_>>();
+    if divs.is_empty() {
+        divs = cell.select(&brief_selector).filter(has_text).collect();
+    }
     let mut entries = Vec::new();
     let mut seen = HashSet::new();
-    for div in cell.select(&div_selector) {
-        let raw = div.text().collect::<Vec<_>>().join("\n");
-        if raw.trim().is_empty() {
-            continue;
-        }
-        let lines = raw
-            .split('\n')
-            .map(|line| normalize_text(line))
-            .collect::<Vec<_>>();
-        let blocks = split_blocks(&lines);
-        for block in blocks {
-            if block.is_empty() {
+    for div in divs {
+        for block in split_blocks(cell_lines(div)) {
+            let mut lines = block.into_iter();
+            let Some(course_name) = lines.next().map(|line| line.text) else {
                 continue;
-            }
-            let course_name = block.first().cloned().unwrap_or_default();
-            if course_name.is_empty() {
-                continue;
-            }
+            };
+            let mut teacher = None;
             let mut week_text = None;
             let mut location = None;
-            for line in block.iter().skip(1) {
-                if line.contains('周') {
-                    week_text = Some(line.clone());
-                } else {
-                    location = Some(line.clone());
+            let mut class_group = None;
+            for line in lines {
+                match line.title.as_deref().and_then(cell_field) {
+                    Some(CellField::Teacher) => teacher = Some(line.text),
+                    Some(CellField::Weeks) => week_text = Some(strip_sections(&line.text)),
+                    Some(CellField::Location) => location = Some(line.text),
+                    Some(CellField::ClassGroup) => class_group = Some(line.text),
+                    None if line.text.contains('周') => {
+                        week_text = Some(strip_sections(&line.text))
+                    }
+                    None => location = Some(line.text),
                 }
             }
             let week_numbers = week_text
                 .as_deref()
-                .map(parse_week_numbers)
+                .map(|text| week_text::parse(text).week_numbers)
                 .unwrap_or_default();
             let key = format!(
-                "{}|{}|{}|{}|{:?}",
-                term, weekday, period_label, course_name, week_text
+                "{}|{}|{}|{}|{:?}|{:?}",
+                term, weekday, period_label, course_name, week_text, class_group
             );
             if seen.contains(&key) {
                 continue;
@@ -239,8 +231,9 @@ fn parse_cell_entries(
                 period_label: period_label.to_string(),
                 period_index,
                 course_name,
-                teacher: None,
+                teacher,
                 location,
+                class_group,
                 week_text,
                 week_numbers,
             });
@@ -249,14 +242,12 @@ fn parse_cell_entries(
     entries
 }
 
-fn parse_schedule_entries(html: &str, term: &str) -> Result<Vec<ScheduleEntryInput>, String> {
+fn parse_schedule_entries(html: &str, term: &str) -> Result<Vec<ScheduleEntryInput>> {
     let document = Html::parse_document(html);
-    let row_selector = Selector::parse("#kbtable tr")
-        .map_err(|e| format!("Invalid selector: {}", e))?;
-    let th_selector = Selector::parse("th")
-        .map_err(|e| format!("Invalid selector: {}", e))?;
-    let td_selector = Selector::parse("td")
-        .map_err(|e| format!("Invalid selector: {}", e))?;
+    let row_selector =
+        Selector::parse("#kbtable tr").map_err(|e| ToolboxError::internal("选择器无效", e))?;
+    let th_selector = Selector::parse("th").map_err(|e| ToolboxError::internal("选择器无效", e))?;
+    let td_selector = Selector::parse("td").map_err(|e| ToolboxError::internal("选择器无效", e))?;
 
     let mut entries = Vec::new();
     let mut weekday_count = 0usize;
@@ -299,48 +290,180 @@ fn parse_schedule_entries(html: &str, term: &str) -> Result<Vec<ScheduleEntryInp
     Ok(entries)
 }
 
+// 教学周历中每个日期格的 title 形如“2025年02月24日”，最早一天所在周即第一周。
+fn parse_term_start(html: &str) -> Option<NaiveDate> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("#kbtable td[title]").ok()?;
+    document
+        .select(&selector)
+        .filter_map(|cell| cell.value().attr("title"))
+        .filter_map(|title| NaiveDate::parse_from_str(title.trim(), "%Y年%m月%d日").ok())
+        .min()
+        .map(|date| TermCalendar::starting(date).first_monday())
+}
+
+// 部分学校没有教务周历页面，页面不存在或解析不出日期时返回空，登录、网络等错误照常返回。
+pub async fn fetch_term_start(session: &mut JwxtSession, term: &str) -> Result<Option<NaiveDate>> {
+    session.ensure_login().await?;
+    let profile = session.profile().clone();
+    let params = [(profile.fields.schedule_term.as_str(), term)];
+    match session.post_form(&profile.calendar_url(), &params).await {
+        Ok(html) => Ok(parse_term_start(&html)),
+        Err(ToolboxError::NotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn week_warnings(entries: &[ScheduleEntryInput]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for entry in entries {
+        let Some(text) = entry.week_text.as_deref() else {
+            continue;
+        };
+        for warning in week_text::parse(text).warnings {
+            warnings.push(format!(
+                "{}（{}）：{}",
+                entry.course_name, entry.period_label, warning
+            ));
+        }
+    }
+    warnings
+}
+
+async fn post_term(session: &mut JwxtSession, term: &str) -> Result<String> {
+    let profile = session.profile().clone();
+    let fields = &profile.fields;
+    let params = [
+        (fields.schedule_term.as_str(), term),
+        (fields.schedule_week.as_str(), ""),
+        ("sfFD", "1"),
+    ];
+    session.post_form(&profile.schedule_url(), &params).await
+}
+
 pub async fn fetch_schedule(
-    username: &str,
-    password: &str,
+    session: &mut JwxtSession,
     term: Option<String>,
-) -> Result<ScheduleFetchResult, String> {
-    let client = build_client()?;
-    login(username, password, &client).await?;
-
-    let initial_html = client
-        .get(SCHEDULE_URL)
-        .send()
-        .await
-        .map_err(|e| format!("Schedule request failed: {}", e))?
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read schedule response: {}", e))?;
-
-    let (terms, selected_term) = parse_terms(&initial_html);
+) -> Result<ScheduleFetchResult> {
+    session.ensure_login().await?;
+    let profile = session.profile().clone();
+    let schedule_url = profile.schedule_url();
+    let fields = &profile.fields;
+
+    let initial_html = session.get(&schedule_url).await?;
+
+    let (terms, selected_term) = parse_terms(&initial_html, &fields.schedule_term);
     let selected_term = term.or(selected_term).unwrap_or_default();
     if selected_term.is_empty() {
-        return Err("未获取到学期信息".to_string());
+        return Err(ToolboxError::Parse {
+            message: "未获取到学期信息".to_string(),
+            details: None,
+        });
     }
 
     let html = if terms.is_empty() || selected_term.is_empty() {
         initial_html
     } else {
-        let params = [("xnxq01id", selected_term.as_str()), ("zc", ""), ("sfFD", "1")];
-        client
-            .post(SCHEDULE_URL)
-            .form(&params)
-            .send()
-            .await
-            .map_err(|e| format!("Schedule request failed: {}", e))?
-            .text()
-            .await
-            .map_err(|e| format!("Failed to read schedule response: {}", e))?
+        post_term(session, &selected_term).await?
     };
 
     let entries = parse_schedule_entries(&html, &selected_term)?;
+    let warnings = week_warnings(&entries);
     Ok(ScheduleFetchResult {
         term: selected_term,
         terms,
         entries,
+        warnings,
     })
 }
+
+// 课表页下拉框中的全部学期，按页面顺序（通常新学期在前）。
+pub async fn fetch_terms(session: &mut JwxtSession) -> Result<Vec<String>> {
+    session.ensure_login().await?;
+    let profile = session.profile().clone();
+    let html = session.get(&profile.schedule_url()).await?;
+    Ok(parse_terms(&html, &profile.fields.schedule_term).0)
+}
+
+pub async fn fetch_term_schedule(
+    session: &mut JwxtSession,
+    term: &str,
+) -> Result<ScheduleFetchResult> {
+    let html = post_term(session, term).await?;
+    let entries = parse_schedule_entries(&html, term)?;
+    let warnings = week_warnings(&entries);
+    Ok(ScheduleFetchResult {
+        term: term.to_string(),
+        terms: Vec::new(),
+        entries,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::assert_golden;
+
+    #[test]
+    fn parses_term_options() {
+        let html = include_str!("../tests/fixtures/jwxt/xskb_list.do.html");
+        let (terms, selected) = parse_terms(html, "xnxq01id");
+        assert_eq!(
+            terms,
+            vec!["2024-2025-2", "2024-2025-1", "2023-2024-2", "2023-2024-1"]
+        );
+        assert_eq!(selected.as_deref(), Some("2024-2025-2"));
+    }
+
+    #[test]
+    fn parses_timetable_fixture() {
+        let html = include_str!("../tests/fixtures/jwxt/xskb_list.do.html");
+        let entries = parse_schedule_entries(html, "2024-2025-2").unwrap();
+        assert_golden(
+            &entries,
+            include_str!("../tests/fixtures/jwxt/xskb_list.do.golden.json"),
+        );
+    }
+
+    #[test]
+    fn falls_back_to_brief_cell_without_titles() {
+        let html = r#"<table id="kbtable">
+<tr><th></th><th>星期一</th></tr>
+<tr><th>第一大节(01,02小节)</th><td>
+<div class="kbcontent1">线性代数<br/>1-8,10-16(周)[01-02节]<br/>博学楼203<br/></div>
+</td></tr></table>"#;
+        let entries = parse_schedule_entries(html, "2024-2025-2").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].teacher, None);
+        assert_eq!(entries[0].week_text.as_deref(), Some("1-8,10-16(周)"));
+        assert_eq!(entries[0].location.as_deref(), Some("博学楼203"));
+    }
+
+    #[test]
+    fn parses_empty_term_timetable() {
+        let html = include_str!("../tests/fixtures/jwxt/xskb_list.do_empty.html");
+        let (_, selected) = parse_terms(html, "xnxq01id");
+        assert_eq!(selected.as_deref(), Some("2023-2024-1"));
+        let entries = parse_schedule_entries(html, "2023-2024-1").unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn reads_term_start_from_calendar() {
+        let html = include_str!("../tests/fixtures/jwxt/jxzl_query.html");
+        assert_eq!(parse_term_start(html), NaiveDate::from_ymd_opt(2025, 2, 24));
+        let empty = include_str!("../tests/fixtures/jwxt/jxzl_query_empty.html");
+        assert_eq!(parse_term_start(empty), None);
+    }
+
+    #[test]
+    fn reads_sections_from_period_label() {
+        assert_eq!(
+            period_sections("第五大节(09,10,11小节)", Some(9)),
+            vec![9, 10, 11]
+        );
+        assert_eq!(period_sections("第一大节", Some(1)), vec![1]);
+        assert!(period_sections("中午", None).is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+
+use super::period_sections;
+use super::week_text;
+use crate::db::ScheduleEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryIssueKind {
+    EmptyWeeks,
+    UnparsedWeekText,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryIssue {
+    pub entry_id: i32,
+    pub course_name: String,
+    pub kind: EntryIssueKind,
+    pub week_text: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+// 同一天、节次有交叉且周次有重叠的两条课程。
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleConflict {
+    pub weekday: i32,
+    pub entry_ids: [i32; 2],
+    pub course_names: [String; 2],
+    pub sections: Vec<i32>,
+    pub weeks: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictReport {
+    pub term: String,
+    pub conflicts: Vec<ScheduleConflict>,
+    pub issues: Vec<EntryIssue>,
+    // 需要在课表上标出的课程，便于前端直接高亮
+    pub flagged_entry_ids: Vec<i32>,
+}
+
+fn intersect(a: &[i32], b: &[i32]) -> Vec<i32> {
+    let b = b.iter().collect::<HashSet<_>>();
+    a.iter()
+        .filter(|n| b.contains(n))
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+pub fn check(term: &str, entries: &[ScheduleEntry]) -> ConflictReport {
+    let mut issues = Vec::new();
+    for entry in entries {
+        let issue = |kind, warnings| EntryIssue {
+            entry_id: entry.id,
+            course_name: entry.course_name.clone(),
+            kind,
+            week_text: entry.week_text.clone(),
+            warnings,
+        };
+        if entry.week_numbers.is_empty() {
+            issues.push(issue(EntryIssueKind::EmptyWeeks, Vec::new()));
+        }
+        let warnings = week_text::parse(entry.week_text.as_deref().unwrap_or("")).warnings;
+        if !warnings.is_empty() {
+            issues.push(issue(EntryIssueKind::UnparsedWeekText, warnings));
+        }
+    }
+
+    let sections = entries
+        .iter()
+        .map(|entry| period_sections(&entry.period_label, entry.period_index))
+        .collect::<Vec<_>>();
+    let mut conflicts = Vec::new();
+    for (i, first) in entries.iter().enumerate() {
+        for (j, second) in entries.iter().enumerate().skip(i + 1) {
+            if first.weekday != second.weekday {
+                continue;
+            }
+            let shared_sections = intersect(&sections[i], &sections[j]);
+            if shared_sections.is_empty() {
+                continue;
+            }
+            let shared_weeks = intersect(&first.week_numbers, &second.week_numbers);
+            if shared_weeks.is_empty() {
+                continue;
+            }
+            conflicts.push(ScheduleConflict {
+                weekday: first.weekday,
+                entry_ids: [first.id, second.id],
+                course_names: [first.course_name.clone(), second.course_name.clone()],
+                sections: shared_sections,
+                weeks: shared_weeks,
+            });
+        }
+    }
+
+    let flagged_entry_ids = conflicts
+        .iter()
+        .flat_map(|conflict| conflict.entry_ids)
+        .chain(issues.iter().map(|issue| issue.entry_id))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    ConflictReport {
+        term: term.to_string(),
+        conflicts,
+        issues,
+        flagged_entry_ids,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::schedule_entry;
+
+    #[test]
+    fn reports_overlapping_weeks_in_shared_periods() {
+        let odd = (1..=15).step_by(2).collect::<Vec<_>>();
+        let even = (2..=16).step_by(2).collect::<Vec<_>>();
+        let entries = vec![
+            schedule_entry(1, 1, "第一大节(01,02小节)", &odd)
+                .week_text("1-15(单周)")
+                .build(),
+            // 单双周交替上课，不算冲突
+            schedule_entry(2, 1, "第一大节(01,02小节)", &even)
+                .week_text("2-16(双周)")
+                .build(),
+            // 三小节的大节与前一大节只重叠第 2 小节
+            schedule_entry(3, 1, "第一大节(02,03,04小节)", &[5, 6])
+                .week_text("5-6(周)")
+                .build(),
+            schedule_entry(4, 2, "第一大节(01,02小节)", &odd)
+                .week_text("1-15(单周)")
+                .build(),
+        ];
+        let report = check("2024-2025-2", &entries);
+        let pairs = report
+            .conflicts
+            .iter()
+            .map(|c| (c.entry_ids, c.sections.clone(), c.weeks.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            vec![([1, 3], vec![2], vec![5]), ([2, 3], vec![2], vec![6])]
+        );
+        assert!(report.issues.is_empty());
+        assert_eq!(report.flagged_entry_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn flags_entries_without_usable_weeks() {
+        let entries = vec![
+            schedule_entry(1, 3, "第二大节(03,04小节)", &[])
+                .week_text("待定")
+                .build(),
+            schedule_entry(2, 3, "第二大节(03,04小节)", &[])
+                .week_text("1-8(周)")
+                .build(),
+            schedule_entry(3, 4, "第二大节(03,04小节)", &[1, 2, 3])
+                .week_text("1-8(周)")
+                .build(),
+        ];
+        let report = check("2024-2025-2", &entries);
+        let issues = report
+            .issues
+            .iter()
+            .map(|i| (i.entry_id, i.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (1, EntryIssueKind::EmptyWeeks),
+                (1, EntryIssueKind::UnparsedWeekText),
+                (2, EntryIssueKind::EmptyWeeks),
+            ]
+        );
+        // 周次为空的课程无从判断冲突
+        assert!(report.conflicts.is_empty());
+        assert_eq!(report.flagged_entry_ids, vec![1, 2]);
+    }
+}
@@ -0,0 +1,403 @@
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Utc};
+
+use super::periods::entry_times;
+use super::weeks::TermCalendar;
+use crate::db::{ExamEntry, PeriodTimetable, ScheduleEntry};
+use crate::error::{Result, ToolboxError};
+
+const TZID: &str = "Asia/Shanghai";
+
+fn format_local(value: NaiveDateTime) -> String {
+    value.format("%Y%m%dT%H%M%S").to_string()
+}
+
+// RFC 5545 3.3.11：文本中的反斜杠、分号、逗号和换行需要转义。
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+// 每行不超过 75 个字节，续行以空格开头，且不能截断 UTF-8 字符。
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for ch in line.chars() {
+        let len = ch.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += len;
+    }
+    out.push_str("\r\n");
+}
+
+// 上课时间相同的连续若干周。
+struct Segment {
+    start: NaiveTime,
+    end: NaiveTime,
+    weeks: Vec<i32>,
+}
+
+fn segments(
+    timetable: &PeriodTimetable,
+    entry: &ScheduleEntry,
+    calendar: &TermCalendar,
+) -> Result<Vec<Segment>> {
+    let mut weeks = entry
+        .week_numbers
+        .iter()
+        .copied()
+        .filter(|week| *week > 0)
+        .collect::<Vec<_>>();
+    weeks.sort();
+    weeks.dedup();
+    let mut segments: Vec<Segment> = Vec::new();
+    for week in weeks {
+        let Some((start, end)) = entry_times(timetable, entry, calendar, week)? else {
+            continue;
+        };
+        match segments.last_mut() {
+            Some(last) if last.start == start.time() && last.end == end.time() => {
+                last.weeks.push(week)
+            }
+            _ => segments.push(Segment {
+                start: start.time(),
+                end: end.time(),
+                weeks: vec![week],
+            }),
+        }
+    }
+    Ok(segments)
+}
+
+fn parse_exam_datetime(value: &str) -> Result<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").map_err(|_| {
+        ToolboxError::InvalidInput(format!("考试时间格式应为 YYYY-MM-DD HH:MM:SS：{}", value))
+    })
+}
+
+fn push_exam_event(out: &mut String, exam: &ExamEntry, stamp: &str) -> Result<()> {
+    // 尚未安排时间的考试不导出
+    let (Some(starts_at), Some(ends_at)) = (exam.starts_at.as_deref(), exam.ends_at.as_deref())
+    else {
+        return Ok(());
+    };
+    let (start, end) = (
+        parse_exam_datetime(starts_at)?,
+        parse_exam_datetime(ends_at)?,
+    );
+    let mut description = Vec::new();
+    if let Some(exam_type) = exam.exam_type.as_deref().filter(|v| !v.trim().is_empty()) {
+        description.push(format!("考试性质：{}", exam_type));
+    }
+    if let Some(seat) = exam.seat_number.as_deref().filter(|v| !v.trim().is_empty()) {
+        description.push(format!("座位号：{}", seat));
+    }
+    if let Some(note) = exam.note.as_deref().filter(|v| !v.trim().is_empty()) {
+        description.push(format!("备注：{}", note));
+    }
+    push_line(out, "BEGIN:VEVENT");
+    push_line(
+        out,
+        &format!("UID:{}-exam-{}@my-toolbox", exam.term, exam.course_code),
+    );
+    push_line(out, &format!("DTSTAMP:{}", stamp));
+    push_line(
+        out,
+        &format!("DTSTART;TZID={}:{}", TZID, format_local(start)),
+    );
+    push_line(out, &format!("DTEND;TZID={}:{}", TZID, format_local(end)));
+    push_line(
+        out,
+        &format!(
+            "SUMMARY:{}",
+            escape_text(&format!("{}（考试）", exam.course_name))
+        ),
+    );
+    if let Some(room) = exam.room.as_deref().filter(|v| !v.trim().is_empty()) {
+        push_line(out, &format!("LOCATION:{}", escape_text(room)));
+    }
+    if !description.is_empty() {
+        push_line(
+            out,
+            &format!("DESCRIPTION:{}", escape_text(&description.join("\n"))),
+        );
+    }
+    push_line(out, "END:VEVENT");
+    Ok(())
+}
+
+// 每条课程生成按周重复的事件：从第一次上课的周开始，到最后一周结束，中间不上课的周
+// 用 EXDATE 排除；夏令、冬令作息切换导致上课时间变化时拆成多个事件。考试各生成一个单次事件。
+pub fn build_calendar(
+    term: &str,
+    calendar: &TermCalendar,
+    timetable: &PeriodTimetable,
+    entries: &[ScheduleEntry],
+    exams: &[ExamEntry],
+    stamp: DateTime<Utc>,
+) -> Result<String> {
+    let stamp = stamp.format("%Y%m%dT%H%M%SZ").to_string();
+
+    let mut out = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//my-toolbox//schedule//CN",
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+    ] {
+        push_line(&mut out, line);
+    }
+    push_line(
+        &mut out,
+        &format!("X-WR-CALNAME:{}", escape_text(&format!("{} 课表", term))),
+    );
+    for line in [
+        "BEGIN:VTIMEZONE",
+        &format!("TZID:{}", TZID),
+        "BEGIN:STANDARD",
+        "DTSTART:19700101T000000",
+        "TZOFFSETFROM:+0800",
+        "TZOFFSETTO:+0800",
+        "TZNAME:CST",
+        "END:STANDARD",
+        "END:VTIMEZONE",
+    ] {
+        push_line(&mut out, line);
+    }
+
+    for entry in entries {
+        let mut description = Vec::new();
+        if let Some(teacher) = entry.teacher.as_deref().filter(|v| !v.trim().is_empty()) {
+            description.push(format!("教师：{}", teacher));
+        }
+        if let Some(week_text) = entry.week_text.as_deref().filter(|v| !v.trim().is_empty()) {
+            description.push(format!("周次：{}", week_text));
+        }
+        description.push(format!("节次：{}", entry.period_label));
+        let description = escape_text(&description.join("\n"));
+
+        for (index, segment) in segments(timetable, entry, calendar)?.iter().enumerate() {
+            let (first_week, last_week) =
+                (segment.weeks[0], segment.weeks[segment.weeks.len() - 1]);
+            let day_of = |week: i32| calendar.date_for(week, entry.weekday);
+            let first_day = day_of(first_week);
+            push_line(&mut out, "BEGIN:VEVENT");
+            push_line(
+                &mut out,
+                &format!("UID:{}-{}-{}@my-toolbox", term, entry.id, index + 1),
+            );
+            push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+            push_line(
+                &mut out,
+                &format!(
+                    "DTSTART;TZID={}:{}",
+                    TZID,
+                    format_local(first_day.and_time(segment.start))
+                ),
+            );
+            push_line(
+                &mut out,
+                &format!(
+                    "DTEND;TZID={}:{}",
+                    TZID,
+                    format_local(first_day.and_time(segment.end))
+                ),
+            );
+            if last_week > first_week {
+                push_line(
+                    &mut out,
+                    &format!("RRULE:FREQ=WEEKLY;COUNT={}", last_week - first_week + 1),
+                );
+                let skipped = (first_week..=last_week)
+                    .filter(|week| segment.weeks.binary_search(week).is_err())
+                    .map(|week| format_local(day_of(week).and_time(segment.start)))
+                    .collect::<Vec<_>>();
+                if !skipped.is_empty() {
+                    push_line(
+                        &mut out,
+                        &format!("EXDATE;TZID={}:{}", TZID, skipped.join(",")),
+                    );
+                }
+            }
+            push_line(
+                &mut out,
+                &format!("SUMMARY:{}", escape_text(&entry.course_name)),
+            );
+            if let Some(location) = entry.location.as_deref().filter(|v| !v.trim().is_empty()) {
+                push_line(&mut out, &format!("LOCATION:{}", escape_text(location)));
+            }
+            push_line(&mut out, &format!("DESCRIPTION:{}", description));
+            push_line(&mut out, "END:VEVENT");
+        }
+    }
+    for exam in exams {
+        push_exam_event(&mut out, exam, &stamp)?;
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PeriodTime;
+    use crate::test_support::schedule_entry;
+    use chrono::TimeZone;
+
+    fn entry(id: i32, weekday: i32, label: &str, name: &str, weeks: &[i32]) -> ScheduleEntry {
+        schedule_entry(id, weekday, label, weeks)
+            .name(name)
+            .period_index(1)
+            .teacher("张老师")
+            .location("外语楼B210")
+            .week_text("1-8,10-16(周)")
+            .build()
+    }
+
+    fn timetable(summer: &[(i32, &str, &str)]) -> PeriodTimetable {
+        let period = |(period, start, end): &(i32, &str, &str), season: Option<&str>| PeriodTime {
+            period: *period,
+            start: start.to_string(),
+            end: end.to_string(),
+            season: season.map(str::to_string),
+        };
+        let mut periods = [(1, "08:00", "08:45"), (2, "08:50", "09:35")]
+            .iter()
+            .map(|p| period(p, None))
+            .collect::<Vec<_>>();
+        periods.extend(summer.iter().map(|p| period(p, Some("summer"))));
+        PeriodTimetable {
+            id: 1,
+            name: "默认".to_string(),
+            term: None,
+            campus: None,
+            summer_start: (!summer.is_empty()).then(|| "05-01".to_string()),
+            winter_start: (!summer.is_empty()).then(|| "10-01".to_string()),
+            periods,
+            updated_at: String::new(),
+        }
+    }
+
+    fn exam(id: i32, name: &str, starts_at: Option<&str>, ends_at: Option<&str>) -> ExamEntry {
+        ExamEntry {
+            id,
+            term: "2024-2025-2".to_string(),
+            course_code: "B0101002".to_string(),
+            course_name: name.to_string(),
+            teacher: None,
+            exam_time: None,
+            starts_at: starts_at.map(str::to_string),
+            ends_at: ends_at.map(str::to_string),
+            room: Some("博学楼101".to_string()),
+            seat_number: Some("23".to_string()),
+            exam_type: Some("正常考试".to_string()),
+            note: None,
+            updated_at: String::new(),
+        }
+    }
+
+    fn build_all(
+        timetable: &PeriodTimetable,
+        entries: &[ScheduleEntry],
+        exams: &[ExamEntry],
+    ) -> Result<String> {
+        let stamp = Utc.with_ymd_and_hms(2025, 2, 20, 0, 0, 0).unwrap();
+        // 学期从周三开始，周次仍按所在周的周一计算
+        let calendar = TermCalendar::parse("2025-02-26").unwrap();
+        build_calendar("2024-2025-2", &calendar, timetable, entries, exams, stamp)
+    }
+
+    fn build_with(timetable: &PeriodTimetable, entries: &[ScheduleEntry]) -> Result<String> {
+        build_all(timetable, entries, &[])
+    }
+
+    fn build(entries: &[ScheduleEntry]) -> Result<String> {
+        build_with(&timetable(&[]), entries)
+    }
+
+    #[test]
+    fn recurring_event_excludes_skipped_weeks() {
+        let weeks = (1..=16).filter(|w| *w != 9).collect::<Vec<_>>();
+        let ics = build(&[entry(7, 2, "第一大节(01,02小节)", "大学英语Ⅱ", &weeks)]).unwrap();
+        let lines = ics.split("\r\n").collect::<Vec<_>>();
+        assert!(lines.contains(&"UID:2024-2025-2-7-1@my-toolbox"));
+        assert!(lines.contains(&"DTSTART;TZID=Asia/Shanghai:20250225T080000"));
+        assert!(lines.contains(&"DTEND;TZID=Asia/Shanghai:20250225T093500"));
+        assert!(lines.contains(&"RRULE:FREQ=WEEKLY;COUNT=16"));
+        assert!(lines.contains(&"EXDATE;TZID=Asia/Shanghai:20250422T080000"));
+        assert!(lines.contains(&"LOCATION:外语楼B210"));
+        assert!(ics.contains("DESCRIPTION:教师：张老师\\n周次：1-8\\,10-16(周)"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn folds_long_lines_on_character_boundaries() {
+        let mut long = entry(1, 1, "第一大节", "很长的课程名称".repeat(6).as_str(), &[3]);
+        long.location = Some("博学楼101;102".to_string());
+        let ics = build(&[long]).unwrap();
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        assert!(ics.contains("LOCATION:博学楼101\\;102"));
+        assert!(!ics.contains("RRULE"));
+    }
+
+    #[test]
+    fn season_switch_splits_recurring_event() {
+        let summer = timetable(&[(1, "07:50", "08:35"), (2, "08:40", "09:25")]);
+        let weeks = (8..=12).collect::<Vec<_>>();
+        // 第 10 周周四是 5 月 1 日，之后按夏令作息上课
+        let ics = build_with(
+            &summer,
+            &[entry(3, 4, "第一大节(01,02小节)", "数据结构", &weeks)],
+        )
+        .unwrap();
+        let lines = ics.split("\r\n").collect::<Vec<_>>();
+        assert!(lines.contains(&"DTSTART;TZID=Asia/Shanghai:20250417T080000"));
+        assert!(lines.contains(&"RRULE:FREQ=WEEKLY;COUNT=2"));
+        assert!(lines.contains(&"UID:2024-2025-2-3-2@my-toolbox"));
+        assert!(lines.contains(&"DTSTART;TZID=Asia/Shanghai:20250501T075000"));
+        assert!(lines.contains(&"DTEND;TZID=Asia/Shanghai:20250501T092500"));
+        assert!(lines.contains(&"RRULE:FREQ=WEEKLY;COUNT=3"));
+    }
+
+    #[test]
+    fn missing_period_time_is_rejected() {
+        let err = build(&[entry(1, 1, "第二大节(03,04小节)", "电路分析", &[2, 4])]).unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+        assert!(err.message().contains("第 3 节"));
+    }
+
+    #[test]
+    fn exams_become_single_events() {
+        let exams = [
+            exam(
+                4,
+                "高等数学A（下）",
+                Some("2025-06-23 09:00:00"),
+                Some("2025-06-23 11:00:00"),
+            ),
+            exam(5, "线性代数", None, None),
+        ];
+        let ics = build_all(&timetable(&[]), &[], &exams).unwrap();
+        let lines = ics.split("\r\n").collect::<Vec<_>>();
+        assert!(lines.contains(&"UID:2024-2025-2-exam-B0101002@my-toolbox"));
+        assert!(lines.contains(&"DTSTART;TZID=Asia/Shanghai:20250623T090000"));
+        assert!(lines.contains(&"DTEND;TZID=Asia/Shanghai:20250623T110000"));
+        assert!(lines.contains(&"SUMMARY:高等数学A（下）（考试）"));
+        assert!(lines.contains(&"DESCRIPTION:考试性质：正常考试\\n座位号：23"));
+        assert!(!ics.contains("线性代数"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+    }
+}
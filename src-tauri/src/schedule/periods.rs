@@ -0,0 +1,256 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::{HashMap, HashSet};
+
+use super::period_sections;
+use super::weeks::TermCalendar;
+use crate::db::{PeriodTimetable, PeriodTimetableInput, ScheduleEntry};
+use crate::error::{Result, ToolboxError};
+
+const SEASONS: [&str; 2] = ["summer", "winter"];
+
+type MonthDay = (u32, u32);
+
+fn parse_clock(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| ToolboxError::InvalidInput(format!("时间格式应为 HH:MM：{}", value)))
+}
+
+fn parse_month_day(value: &str) -> Result<MonthDay> {
+    let invalid = || ToolboxError::InvalidInput(format!("切换日期格式应为 MM-DD：{}", value));
+    let (month, day) = value.trim().split_once('-').ok_or_else(invalid)?;
+    let month = month.parse::<u32>().map_err(|_| invalid())?;
+    let day = day.parse::<u32>().map_err(|_| invalid())?;
+    // 用闰年校验，允许 02-29
+    NaiveDate::from_ymd_opt(2024, month, day).ok_or_else(invalid)?;
+    Ok((month, day))
+}
+
+fn switch_dates(
+    summer_start: Option<&str>,
+    winter_start: Option<&str>,
+) -> Result<Option<(MonthDay, MonthDay)>> {
+    let summer = summer_start.filter(|v| !v.trim().is_empty());
+    let winter = winter_start.filter(|v| !v.trim().is_empty());
+    match (summer, winter) {
+        (Some(summer), Some(winter)) => {
+            let summer = parse_month_day(summer)?;
+            let winter = parse_month_day(winter)?;
+            if summer >= winter {
+                return Err(ToolboxError::InvalidInput(
+                    "夏令作息开始日期需早于冬令作息".to_string(),
+                ));
+            }
+            Ok(Some((summer, winter)))
+        }
+        (None, None) => Ok(None),
+        _ => Err(ToolboxError::InvalidInput(
+            "夏令与冬令作息的切换日期需同时设置".to_string(),
+        )),
+    }
+}
+
+pub fn validate(input: &PeriodTimetableInput) -> Result<()> {
+    if input.name.trim().is_empty() {
+        return Err(ToolboxError::InvalidInput("请输入时间表名称".to_string()));
+    }
+    let seasonal = switch_dates(input.summer_start.as_deref(), input.winter_start.as_deref())?;
+    let mut seen = HashSet::new();
+    for period in &input.periods {
+        if period.period <= 0 {
+            return Err(ToolboxError::InvalidInput("节次需为正整数".to_string()));
+        }
+        let season = period.season.as_deref().unwrap_or("");
+        if !season.is_empty() && !SEASONS.contains(&season) {
+            return Err(ToolboxError::InvalidInput(format!(
+                "未知的作息类型：{}",
+                season
+            )));
+        }
+        if !season.is_empty() && seasonal.is_none() {
+            return Err(ToolboxError::InvalidInput(
+                "区分夏令、冬令作息时需设置切换日期".to_string(),
+            ));
+        }
+        if parse_clock(&period.end)? <= parse_clock(&period.start)? {
+            return Err(ToolboxError::InvalidInput(format!(
+                "第 {} 节的下课时间需晚于上课时间",
+                period.period
+            )));
+        }
+        if !seen.insert((season, period.period)) {
+            return Err(ToolboxError::InvalidInput(format!(
+                "第 {} 节的时间重复设置",
+                period.period
+            )));
+        }
+    }
+    Ok(())
+}
+
+// 夏令作息从 summer_start 当天起到 winter_start 前一天，其余日期按冬令作息。
+fn season_on(timetable: &PeriodTimetable, date: NaiveDate) -> Result<Option<&'static str>> {
+    let dates = switch_dates(
+        timetable.summer_start.as_deref(),
+        timetable.winter_start.as_deref(),
+    )?;
+    Ok(dates.map(|(summer, winter)| {
+        let today = (date.month(), date.day());
+        if today >= summer && today < winter {
+            "summer"
+        } else {
+            "winter"
+        }
+    }))
+}
+
+// 某一天实际生效的各节上下课时间：通用节次打底，当季节次覆盖同名节次。
+pub fn periods_on(
+    timetable: &PeriodTimetable,
+    date: NaiveDate,
+) -> Result<HashMap<i32, (NaiveTime, NaiveTime)>> {
+    let season = season_on(timetable, date)?;
+    let mut table = HashMap::new();
+    for period in timetable.periods.iter().filter(|p| p.season.is_none()) {
+        table.insert(
+            period.period,
+            (parse_clock(&period.start)?, parse_clock(&period.end)?),
+        );
+    }
+    for period in timetable
+        .periods
+        .iter()
+        .filter(|p| p.season.is_some() && p.season.as_deref() == season)
+    {
+        table.insert(
+            period.period,
+            (parse_clock(&period.start)?, parse_clock(&period.end)?),
+        );
+    }
+    Ok(table)
+}
+
+// 课程在第 week 周的具体上下课时间；该周不上这门课时返回 None。
+pub fn entry_times(
+    timetable: &PeriodTimetable,
+    entry: &ScheduleEntry,
+    calendar: &TermCalendar,
+    week: i32,
+) -> Result<Option<(NaiveDateTime, NaiveDateTime)>> {
+    if !entry.week_numbers.contains(&week) || !(1..=7).contains(&entry.weekday) {
+        return Ok(None);
+    }
+    let sections = period_sections(&entry.period_label, entry.period_index);
+    let (Some(first), Some(last)) = (sections.first(), sections.last()) else {
+        return Ok(None);
+    };
+    let date = calendar.date_for(week, entry.weekday);
+    let table = periods_on(timetable, date)?;
+    let missing = |period: &i32| {
+        ToolboxError::InvalidInput(format!(
+            "缺少第 {} 节的上课时间（{}）",
+            period, entry.course_name
+        ))
+    };
+    let start = table.get(first).ok_or_else(|| missing(first))?.0;
+    let end = table.get(last).ok_or_else(|| missing(last))?.1;
+    Ok(Some((date.and_time(start), date.and_time(end))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PeriodTime;
+    use crate::test_support::schedule_entry;
+
+    fn period(period: i32, start: &str, end: &str, season: Option<&str>) -> PeriodTime {
+        PeriodTime {
+            period,
+            start: start.to_string(),
+            end: end.to_string(),
+            season: season.map(str::to_string),
+        }
+    }
+
+    fn seasonal_timetable() -> PeriodTimetable {
+        PeriodTimetable {
+            id: 1,
+            name: "默认".to_string(),
+            term: None,
+            campus: None,
+            summer_start: Some("05-01".to_string()),
+            winter_start: Some("10-01".to_string()),
+            periods: vec![
+                period(1, "08:00", "08:45", None),
+                period(2, "08:55", "09:40", None),
+                period(5, "14:00", "14:45", Some("winter")),
+                period(6, "14:55", "15:40", Some("winter")),
+                period(5, "14:30", "15:15", Some("summer")),
+                period(6, "15:25", "16:10", Some("summer")),
+            ],
+            updated_at: String::new(),
+        }
+    }
+
+    fn entry(label: &str, period_index: i32, weekday: i32, weeks: &[i32]) -> ScheduleEntry {
+        schedule_entry(1, weekday, label, weeks)
+            .name("数据结构")
+            .period_index(period_index)
+            .build()
+    }
+
+    fn at(date: (i32, u32, u32), time: (u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+            .unwrap()
+            .and_hms_opt(time.0, time.1, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn afternoon_periods_follow_season_switch() {
+        let timetable = seasonal_timetable();
+        let calendar = TermCalendar::starting(NaiveDate::from_ymd_opt(2025, 2, 24).unwrap());
+        let afternoon = entry("第三大节(05,06小节)", 5, 3, &[9, 10]);
+        // 第 9 周周三是 4 月 23 日，第 10 周周三是 4 月 30 日，5 月 1 日起换夏令时间
+        assert_eq!(
+            entry_times(&timetable, &afternoon, &calendar, 10).unwrap(),
+            Some((at((2025, 4, 30), (14, 0)), at((2025, 4, 30), (15, 40))))
+        );
+        let thursday = entry("第三大节(05,06小节)", 5, 4, &[10]);
+        assert_eq!(
+            entry_times(&timetable, &thursday, &calendar, 10).unwrap(),
+            Some((at((2025, 5, 1), (14, 30)), at((2025, 5, 1), (16, 10))))
+        );
+        let morning = entry("第一大节(01,02小节)", 1, 1, &[1]);
+        assert_eq!(
+            entry_times(&timetable, &morning, &calendar, 1).unwrap(),
+            Some((at((2025, 2, 24), (8, 0)), at((2025, 2, 24), (9, 40))))
+        );
+        assert_eq!(
+            entry_times(&timetable, &morning, &calendar, 2).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_inconsistent_timetables() {
+        let valid = seasonal_timetable();
+        let input = |summer: Option<&str>, periods: Vec<PeriodTime>| PeriodTimetableInput {
+            name: valid.name.clone(),
+            term: None,
+            campus: None,
+            summer_start: summer.map(str::to_string),
+            winter_start: valid.winter_start.clone(),
+            periods,
+        };
+        assert!(validate(&input(Some("05-01"), valid.periods.clone())).is_ok());
+        assert!(validate(&input(None, valid.periods.clone())).is_err());
+        assert!(validate(&input(Some("11-01"), valid.periods.clone())).is_err());
+        let backwards = vec![period(1, "09:00", "08:00", None)];
+        assert!(validate(&input(Some("05-01"), backwards)).is_err());
+        let twice = vec![
+            period(1, "08:00", "08:45", None),
+            period(1, "08:10", "08:55", None),
+        ];
+        assert!(validate(&input(Some("05-01"), twice)).is_err());
+    }
+}
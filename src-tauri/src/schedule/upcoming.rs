@@ -0,0 +1,205 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::periods::entry_times;
+use super::weeks::TermCalendar;
+use crate::db::{PeriodTimetable, ScheduleEntry};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpcomingClass {
+    pub entry_id: i32,
+    pub term: String,
+    pub course_name: String,
+    pub week: i32,
+    pub weekday: i32,
+    pub period_label: String,
+    pub start: String,
+    pub end: String,
+    pub location: Option<String>,
+    pub teacher: Option<String>,
+    pub in_progress: bool,
+}
+
+// 算不出上课时间的课程（如时间表缺少对应节次）跳过，每门课给出一条提示。
+#[derive(Debug, Clone, Serialize)]
+pub struct UpcomingClasses {
+    pub classes: Vec<UpcomingClass>,
+    pub warnings: Vec<String>,
+}
+
+fn format_datetime(value: NaiveDateTime) -> String {
+    value.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+// now 之后 horizon 时间内的课程（含正在上的），按上课时间排序。
+pub fn upcoming_classes(
+    calendar: &TermCalendar,
+    timetable: &PeriodTimetable,
+    entries: &[ScheduleEntry],
+    now: NaiveDateTime,
+    horizon: Duration,
+) -> UpcomingClasses {
+    let until = now + horizon;
+    let Some(last_week) = calendar.week_of(until.date()) else {
+        return UpcomingClasses {
+            classes: Vec::new(),
+            warnings: Vec::new(),
+        };
+    };
+    let first_week = calendar.week_of(now.date()).unwrap_or(1);
+
+    let mut sessions = Vec::new();
+    let mut warnings = Vec::new();
+    let mut skipped = HashSet::new();
+    for week in first_week..=last_week {
+        for entry in entries {
+            if skipped.contains(&entry.id) {
+                continue;
+            }
+            let (start, end) = match entry_times(timetable, entry, calendar, week) {
+                Ok(Some(times)) => times,
+                Ok(None) => continue,
+                Err(err) => {
+                    skipped.insert(entry.id);
+                    warnings.push(err.message().to_string());
+                    continue;
+                }
+            };
+            if end <= now || start >= until {
+                continue;
+            }
+            sessions.push((
+                start,
+                UpcomingClass {
+                    entry_id: entry.id,
+                    term: entry.term.clone(),
+                    course_name: entry.course_name.clone(),
+                    week,
+                    weekday: entry.weekday,
+                    period_label: entry.period_label.clone(),
+                    start: format_datetime(start),
+                    end: format_datetime(end),
+                    location: entry.location.clone(),
+                    teacher: entry.teacher.clone(),
+                    in_progress: start <= now,
+                },
+            ));
+        }
+    }
+    sessions.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then_with(|| a.1.course_name.cmp(&b.1.course_name))
+    });
+    UpcomingClasses {
+        classes: sessions.into_iter().map(|(_, class)| class).collect(),
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PeriodTime;
+    use crate::test_support::schedule_entry;
+    use chrono::NaiveDate;
+
+    fn entry(id: i32, weekday: i32, label: &str, name: &str, weeks: &[i32]) -> ScheduleEntry {
+        schedule_entry(id, weekday, label, weeks)
+            .name(name)
+            .teacher("李老师")
+            .location("博学楼101")
+            .build()
+    }
+
+    fn timetable() -> PeriodTimetable {
+        let periods = [
+            (1, "08:00", "08:45"),
+            (2, "08:50", "09:35"),
+            (3, "10:00", "10:45"),
+            (4, "10:50", "11:35"),
+        ];
+        PeriodTimetable {
+            id: 1,
+            name: "默认".to_string(),
+            term: None,
+            campus: None,
+            summer_start: None,
+            winter_start: None,
+            periods: periods
+                .iter()
+                .map(|(period, start, end)| PeriodTime {
+                    period: *period,
+                    start: start.to_string(),
+                    end: end.to_string(),
+                    season: None,
+                })
+                .collect(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn at(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn lists_current_and_next_sessions_in_order() {
+        let calendar = TermCalendar::parse("2025-02-24").unwrap();
+        let entries = vec![
+            entry(1, 1, "第一大节(01,02小节)", "高等数学A（下）", &[1, 2, 3]),
+            entry(2, 1, "第二大节(03,04小节)", "大学物理实验", &[1, 3]),
+            entry(3, 2, "第一大节(01,02小节)", "大学英语Ⅱ", &[2, 3]),
+            entry(4, 2, "第三大节(05,06小节)", "体育Ⅱ", &[2, 3]),
+        ];
+        // 第 2 周周一 9:00，高数正在上，物理实验本周不上；时间表没有第 5 节，体育跳过
+        let upcoming = upcoming_classes(
+            &calendar,
+            &timetable(),
+            &entries,
+            at(3, 3, 9, 0),
+            Duration::hours(30),
+        );
+        assert_eq!(upcoming.warnings, vec!["缺少第 5 节的上课时间（体育Ⅱ）"]);
+        let classes = upcoming.classes;
+        let summary = classes
+            .iter()
+            .map(|c| (c.entry_id, c.week, c.start.as_str(), c.in_progress))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 2, "2025-03-03T08:00:00", true),
+                (3, 2, "2025-03-04T08:00:00", false),
+            ]
+        );
+        assert_eq!(classes[0].teacher.as_deref(), Some("李老师"));
+
+        // 跨周：第 2 周周二晚上往后两天，能看到第 3 周周一的课
+        let next_week = upcoming_classes(
+            &calendar,
+            &timetable(),
+            &entries,
+            at(3, 9, 20, 0),
+            Duration::hours(16),
+        );
+        let ids = next_week
+            .classes
+            .iter()
+            .map(|c| c.entry_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2]);
+
+        let before_term = upcoming_classes(
+            &calendar,
+            &timetable(),
+            &entries,
+            at(2, 1, 9, 0),
+            Duration::hours(24),
+        );
+        assert!(before_term.classes.is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use serde::Serialize;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// 周次表达式的解析结果。week_text 是由 week_numbers 重新生成的规范写法，
+// 再次解析可得到相同的周次；无法识别的部分记入 warnings，不会静默丢弃。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WeekExpr {
+    pub week_numbers: Vec<i32>,
+    pub week_text: String,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Parity {
+    Odd,
+    Even,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(String),
+    Dash,
+    Separator,
+    Parity(Parity),
+    Week,
+    Unknown(String),
+}
+
+// 一学期不会超过这么多周，更大的周次视为录入错误，避免展开出超长的周次列表。
+const MAX_WEEK: i32 = 60;
+
+struct Clause {
+    start: i32,
+    end: i32,
+    parity: Option<Parity>,
+}
+
+fn is_dash(ch: char) -> bool {
+    matches!(ch, '-' | '－' | '—' | '~' | '～' | '至')
+}
+
+fn is_separator(ch: char) -> bool {
+    ch.is_whitespace() || matches!(ch, ',' | '，' | '、' | ';' | '；')
+}
+
+// 括号和“第”只起修饰作用；方括号里是节次（如“[01-02节]”），整段跳过。
+fn is_ignored(ch: char) -> bool {
+    matches!(ch, '(' | ')' | '（' | '）' | '第')
+}
+
+fn skip_brackets(chars: &mut Peekable<Chars>) {
+    for ch in chars.by_ref() {
+        if ch == ']' || ch == '】' {
+            break;
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            '0'..='9' => {
+                let mut digits = ch.to_string();
+                while let Some(next) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(next);
+                }
+                Token::Number(digits)
+            }
+            '单' => Token::Parity(Parity::Odd),
+            '双' => Token::Parity(Parity::Even),
+            '周' => Token::Week,
+            '[' | '【' => {
+                skip_brackets(&mut chars);
+                continue;
+            }
+            _ if is_dash(ch) => Token::Dash,
+            _ if is_separator(ch) => Token::Separator,
+            _ if is_ignored(ch) => continue,
+            _ => {
+                let mut unknown = ch.to_string();
+                while let Some(next) = chars.next_if(|c| {
+                    !c.is_ascii_digit()
+                        && !is_dash(*c)
+                        && !is_separator(*c)
+                        && !is_ignored(*c)
+                        && !matches!(c, '单' | '双' | '周' | '[' | '【')
+                }) {
+                    unknown.push(next);
+                }
+                Token::Unknown(unknown)
+            }
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+// 语法：
+//   expr   = clause { sep clause }
+//   clause = number [ "-" number ] [ "单" | "双" ] [ "周" ]
+// 单双标记作用于上一个标记之后的全部范围，如“1-8,10-16(单)周”两段都取单周；
+// 紧跟在“周”之后的标记作用于刚结束的一组，如“1-16周(双)”。
+fn parse_clauses(tokens: Vec<Token>, warnings: &mut Vec<String>) -> Vec<Clause> {
+    let mut clauses: Vec<Clause> = Vec::new();
+    let mut pending_from = 0;
+    let mut closed_from: Option<usize> = None;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Number(digits) => {
+                let Ok(start) = digits.parse::<i32>() else {
+                    warnings.push(format!("周次“{}”过大", digits));
+                    continue;
+                };
+                let mut end = start;
+                if tokens.next_if_eq(&Token::Dash).is_some() {
+                    match tokens.next_if(|t| matches!(t, Token::Number(_))) {
+                        Some(Token::Number(digits)) => match digits.parse::<i32>() {
+                            Ok(value) => end = value,
+                            Err(_) => {
+                                warnings.push(format!("周次“{}”过大", digits));
+                                continue;
+                            }
+                        },
+                        _ => {
+                            warnings.push(format!("“{}-”缺少结束周", start));
+                            continue;
+                        }
+                    }
+                }
+                if start < 1 || end < 1 {
+                    warnings.push("周次需从第 1 周开始".to_string());
+                    continue;
+                }
+                if start.max(end) > MAX_WEEK {
+                    warnings.push(format!("周次“{}”过大", start.max(end)));
+                    continue;
+                }
+                if start > end {
+                    warnings.push(format!("周次范围“{}-{}”起止颠倒", start, end));
+                }
+                clauses.push(Clause {
+                    start: start.min(end),
+                    end: start.max(end),
+                    parity: None,
+                });
+            }
+            Token::Dash => warnings.push("多余的“-”".to_string()),
+            Token::Separator => {}
+            Token::Parity(parity) => {
+                let from = if pending_from < clauses.len() {
+                    Some(pending_from)
+                } else {
+                    closed_from
+                };
+                match from {
+                    Some(from) => {
+                        for clause in &mut clauses[from..] {
+                            clause.parity = Some(parity);
+                        }
+                    }
+                    None => warnings.push("单双周标记前没有周次".to_string()),
+                }
+                pending_from = clauses.len();
+                closed_from = None;
+            }
+            Token::Week => {
+                if pending_from < clauses.len() {
+                    closed_from = Some(pending_from);
+                    pending_from = clauses.len();
+                }
+            }
+            Token::Unknown(text) => warnings.push(format!("无法识别“{}”", text)),
+        }
+    }
+    clauses
+}
+
+pub fn parse(text: &str) -> WeekExpr {
+    let mut warnings = Vec::new();
+    let clauses = parse_clauses(tokenize(text), &mut warnings);
+    let mut weeks = Vec::new();
+    for clause in &clauses {
+        let before = weeks.len();
+        weeks.extend(
+            (clause.start..=clause.end).filter(|week| match clause.parity {
+                Some(Parity::Odd) => week % 2 == 1,
+                Some(Parity::Even) => week % 2 == 0,
+                None => true,
+            }),
+        );
+        if weeks.len() == before {
+            let range = if clause.start == clause.end {
+                clause.start.to_string()
+            } else {
+                format!("{}-{}", clause.start, clause.end)
+            };
+            let parity = if clause.parity == Some(Parity::Odd) {
+                "单"
+            } else {
+                "双"
+            };
+            warnings.push(format!("“{}”中没有{}周", range, parity));
+        }
+    }
+    weeks.sort();
+    weeks.dedup();
+    if weeks.is_empty() && warnings.is_empty() && !text.trim().is_empty() {
+        warnings.push("未识别到周次".to_string());
+    }
+    WeekExpr {
+        week_text: format(&weeks),
+        week_numbers: weeks,
+        warnings,
+    }
+}
+
+// 连续的周写成“1-8”，隔周且至少三周写成“1-15(单)”，其余逐个列出，末尾加“周”。
+pub fn format(weeks: &[i32]) -> String {
+    let mut weeks = weeks.iter().copied().filter(|w| *w > 0).collect::<Vec<_>>();
+    weeks.sort();
+    weeks.dedup();
+    // 从 weeks[from] 开始每次递增 step 的最长一段
+    let run = |from: usize, step: i32| {
+        1 + weeks[from + 1..]
+            .iter()
+            .zip(1..)
+            .take_while(|(week, n)| **week == weeks[from] + step * n)
+            .count()
+    };
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < weeks.len() {
+        let consecutive = run(i, 1);
+        let alternate = run(i, 2);
+        let (len, part) = if consecutive >= 2 && consecutive >= alternate {
+            (
+                consecutive,
+                format!("{}-{}", weeks[i], weeks[i + consecutive - 1]),
+            )
+        } else if alternate >= 3 {
+            let parity = if weeks[i] % 2 == 1 { "单" } else { "双" };
+            (
+                alternate,
+                format!("{}-{}({})", weeks[i], weeks[i + alternate - 1], parity),
+            )
+        } else {
+            (1, weeks[i].to_string())
+        };
+        parts.push(part);
+        i += len;
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{}周", parts.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weeks(text: &str) -> Vec<i32> {
+        let parsed = parse(text);
+        assert!(
+            parsed.warnings.is_empty(),
+            "{}: {:?}",
+            text,
+            parsed.warnings
+        );
+        parsed.week_numbers
+    }
+
+    #[test]
+    fn parses_odd_even_and_split_weeks() {
+        assert_eq!(weeks("1-15(单周)"), vec![1, 3, 5, 7, 9, 11, 13, 15]);
+        assert_eq!(weeks("2-8(双周)"), vec![2, 4, 6, 8]);
+        assert_eq!(weeks("1-3,5,7-8(周)"), vec![1, 2, 3, 5, 7, 8]);
+        assert_eq!(weeks("第3周"), vec![3]);
+        assert_eq!(weeks("1-16(周)[01-02节]").len(), 16);
+    }
+
+    #[test]
+    fn parity_applies_to_its_own_group() {
+        assert_eq!(weeks("1-8,10-16(单)周"), vec![1, 3, 5, 7, 11, 13, 15]);
+        assert_eq!(weeks("3-7单,4-8双"), vec![3, 4, 5, 6, 7, 8]);
+        assert_eq!(weeks("1-4周,9-14周(双)"), vec![1, 2, 3, 4, 10, 12, 14]);
+        assert_eq!(weeks("1-2周 5-6周"), vec![1, 2, 5, 6]);
+    }
+
+    #[test]
+    fn formats_round_trip_text() {
+        for (text, expected) in [
+            ("1-8,10-16(周)", "1-8,10-16周"),
+            ("1-15(单周)", "1-15(单)周"),
+            ("1-8,10-16(单)周", "1-7(单),11-15(单)周"),
+            ("3-15单,4-16双", "3-16周"),
+            ("第3周", "3周"),
+            ("1,3,6-7", "1,3,6-7周"),
+        ] {
+            let parsed = parse(text);
+            assert_eq!(parsed.week_text, expected, "{}", text);
+            assert_eq!(parse(&parsed.week_text).week_numbers, parsed.week_numbers);
+        }
+        assert_eq!(format(&[]), "");
+    }
+
+    #[test]
+    fn reports_unparsed_parts() {
+        let parsed = parse("1-8周,待定");
+        assert_eq!(parsed.week_numbers, (1..=8).collect::<Vec<_>>());
+        assert_eq!(parsed.warnings, vec!["无法识别“待定”"]);
+        assert_eq!(parse("5-").warnings, vec!["“5-”缺少结束周"]);
+        assert_eq!(parse("2单").warnings, vec!["“2”中没有单周"]);
+        assert_eq!(parse("周").warnings, vec!["未识别到周次"]);
+        assert!(parse("").warnings.is_empty());
+    }
+
+    #[test]
+    fn rejects_huge_week_ranges() {
+        let parsed = parse("1-2000000000周,3周");
+        assert_eq!(parsed.week_numbers, vec![3]);
+        assert_eq!(parsed.warnings, vec!["周次“2000000000”过大"]);
+        assert_eq!(parse("61周").warnings, vec!["周次“61”过大"]);
+        assert_eq!(weeks("1-60周").len(), 60);
+    }
+}
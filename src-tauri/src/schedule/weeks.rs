@@ -0,0 +1,90 @@
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use serde::Serialize;
+
+use crate::error::{Result, ToolboxError};
+
+// 以第一周周一为基准的教学周换算。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermCalendar {
+    first_monday: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TermWeek {
+    pub term: String,
+    pub first_monday: String,
+    pub date: String,
+    pub week: Option<i32>,
+    pub weekday: i32,
+    pub current_week: Option<i32>,
+}
+
+impl TermCalendar {
+    // 任意一天都会对齐到所在周的周一，兼容开学典礼不在周一的学期。
+    pub fn starting(date: NaiveDate) -> Self {
+        TermCalendar {
+            first_monday: date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+            .map(Self::starting)
+            .map_err(|_| ToolboxError::InvalidInput("开学日期格式应为 YYYY-MM-DD".to_string()))
+    }
+
+    pub fn first_monday(&self) -> NaiveDate {
+        self.first_monday
+    }
+
+    // 第 week 周星期 weekday（1 为周一）的日期。
+    pub fn date_for(&self, week: i32, weekday: i32) -> NaiveDate {
+        self.first_monday + Duration::days(((week - 1) * 7 + weekday - 1) as i64)
+    }
+
+    // 开学之前返回 None。
+    pub fn week_of(&self, date: NaiveDate) -> Option<i32> {
+        let days = (date - self.first_monday).num_days();
+        (days >= 0).then(|| (days / 7 + 1) as i32)
+    }
+
+    pub fn current_week(&self) -> Option<i32> {
+        self.week_of(Local::now().date_naive())
+    }
+
+    pub fn describe(&self, term: &str, date: NaiveDate) -> TermWeek {
+        TermWeek {
+            term: term.to_string(),
+            first_monday: self.first_monday.format("%Y-%m-%d").to_string(),
+            date: date.format("%Y-%m-%d").to_string(),
+            week: self.week_of(date),
+            weekday: date.weekday().number_from_monday() as i32,
+            current_week: self.current_week(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    #[test]
+    fn converts_between_dates_and_weeks() {
+        let calendar = TermCalendar::parse("2025-02-26").unwrap();
+        assert_eq!(calendar.first_monday(), day(2, 24));
+        assert_eq!(calendar.date_for(1, 1), day(2, 24));
+        assert_eq!(calendar.date_for(9, 2), day(4, 22));
+        assert_eq!(calendar.week_of(day(2, 23)), None);
+        assert_eq!(calendar.week_of(day(3, 2)), Some(1));
+        assert_eq!(calendar.week_of(day(3, 3)), Some(2));
+        assert_eq!(calendar.week_of(day(4, 22)), Some(9));
+
+        let info = calendar.describe("2024-2025-2", day(3, 5));
+        assert_eq!((info.week, info.weekday), (Some(2), 3));
+        assert!(TermCalendar::parse("2025/02/24").is_err());
+    }
+}
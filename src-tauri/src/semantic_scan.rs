@@ -14,6 +14,8 @@ use reqwest::header::USER_AGENT;
 use std::io::Cursor;
 use zip::ZipArchive;
 
+use crate::error::{Result, ToolboxError};
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LanguageStat {
@@ -191,7 +193,7 @@ pub trait Detector: Send + Sync {
         -> Result<(), DetectError>;
 }
 
-pub fn scan_semantic_repo(root: &Path, config: SemanticScanConfig) -> Result<SemanticReport, String> {
+pub fn scan_semantic_repo(root: &Path, config: SemanticScanConfig) -> Result<SemanticReport> {
     let ctx = DetectContext {
         repo_root: root.to_path_buf(),
         config,
@@ -240,8 +242,7 @@ pub fn scan_semantic_repo(root: &Path, config: SemanticScanConfig) -> Result<Sem
             }
         }
 
-        let module_stats =
-            scan_module_files(&ctx, &module_ctx).map_err(|e| format!("扫描模块失败: {}", e))?;
+        let module_stats = scan_module_files(&ctx, &module_ctx)?;
         report.languages = module_stats.languages;
         report.generated = module_stats.generated;
         report.assets = module_stats.assets;
@@ -503,7 +504,7 @@ struct ModuleScanStats {
     assets: Option<AssetSummary>,
 }
 
-fn scan_module_files(ctx: &DetectContext, module: &ModuleContext) -> Result<ModuleScanStats, String> {
+fn scan_module_files(ctx: &DetectContext, module: &ModuleContext) -> Result<ModuleScanStats> {
     let files = list_files(&module.abs_path, &ctx.config, &ctx.repo_root)?;
     let total_size: u64 = files.iter().map(|f| f.size).sum();
 
@@ -628,14 +629,14 @@ struct FileEntry {
     size: u64,
 }
 
-fn list_files(root: &Path, cfg: &SemanticScanConfig, repo_root: &Path) -> Result<Vec<FileEntry>, String> {
+fn list_files(root: &Path, cfg: &SemanticScanConfig, repo_root: &Path) -> Result<Vec<FileEntry>> {
     let mut out = Vec::new();
     let ignore_dirs_set: HashSet<String> =
         cfg.ignore_dirs.iter().map(|s| s.to_ascii_lowercase()).collect();
 
     let mut builder = WalkBuilder::new(root);
     builder.hidden(false).parents(true);
-    let repo_canon = fs::canonicalize(repo_root).map_err(|e| format!("路径无效: {}", e))?;
+    let repo_canon = fs::canonicalize(repo_root).map_err(|e| ToolboxError::io("路径无效", e))?;
     let repo_canon_for_filter = repo_canon.clone();
 
     let walker = builder.filter_entry(move |entry| {
@@ -824,7 +825,7 @@ fn compute_language_percentages(
     out
 }
 
-fn compute_generated_kloc(paths: &[PathBuf], ctx: &DetectContext) -> Result<f32, String> {
+fn compute_generated_kloc(paths: &[PathBuf], ctx: &DetectContext) -> Result<f32> {
     let mut total_lines: u64 = 0;
     let mut total_bytes: u64 = 0;
     let byte_budget = 16 * 1024 * 1024u64;
@@ -2186,41 +2187,45 @@ impl Detector for PackageJsonDepsDetector {
 }
 
 #[tauri::command]
-pub async fn scan_semantic_local(root_path: String) -> Result<SemanticReport, String> {
+pub async fn scan_semantic_local(root_path: String) -> Result<SemanticReport> {
     let root_path = root_path.trim().to_string();
     if root_path.is_empty() {
-        return Err("请输入项目根目录".to_string());
+        return Err(ToolboxError::InvalidInput("请输入项目根目录".to_string()));
     }
     let root = PathBuf::from(&root_path);
     if !root.exists() {
-        return Err("指定目录不存在".to_string());
+        return Err(ToolboxError::InvalidInput("指定目录不存在".to_string()));
     }
     if !root.is_dir() {
-        return Err("指定路径不是目录".to_string());
+        return Err(ToolboxError::InvalidInput("指定路径不是目录".to_string()));
     }
     tokio::task::spawn_blocking(move || scan_semantic_repo(&root, SemanticScanConfig::default()))
         .await
-        .map_err(|e| format!("扫描任务失败: {}", e))?
+        .map_err(|e| ToolboxError::internal("扫描任务失败", e))?
 }
 
 #[tauri::command]
-pub async fn scan_semantic_github(repo_url: String) -> Result<SemanticReport, String> {
+pub async fn scan_semantic_github(repo_url: String) -> Result<SemanticReport> {
     let repo_url = repo_url.trim().to_string();
     if repo_url.is_empty() {
-        return Err("请输入 GitHub 仓库链接".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "请输入 GitHub 仓库链接".to_string(),
+        ));
     }
     let parsed = parse_github_input(&repo_url)?;
     let zip_bytes = download_github_zip(&parsed).await?;
-    let temp = tempfile::tempdir().map_err(|e| format!("创建临时目录失败: {}", e))?;
-    let extracted_root = extract_zip_to_dir(&zip_bytes, temp.path())
-        .map_err(|e| format!("解压失败: {}", e))?;
+    let temp = tempfile::tempdir().map_err(|e| ToolboxError::io("创建临时目录失败", e))?;
+    let extracted_root =
+        extract_zip_to_dir(&zip_bytes, temp.path()).map_err(|e| ToolboxError::io("解压失败", e))?;
     let scan_root = if let Some(subdir) = &parsed.subdir {
         extracted_root.join(subdir)
     } else {
         extracted_root.clone()
     };
     if !scan_root.exists() || !scan_root.is_dir() {
-        return Err("指定的子目录不存在或不是目录".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "指定的子目录不存在或不是目录".to_string(),
+        ));
     }
     let display = parsed.display.clone();
     tokio::task::spawn_blocking(move || {
@@ -2232,45 +2237,48 @@ pub async fn scan_semantic_github(repo_url: String) -> Result<SemanticReport, St
         })
     })
     .await
-    .map_err(|e| format!("扫描任务失败: {}", e))?
+    .map_err(|e| ToolboxError::internal("扫描任务失败", e))?
 }
 
 #[tauri::command]
-pub async fn export_semantic_json(path: String, report: SemanticReport) -> Result<(), String> {
+pub async fn export_semantic_json(path: String, report: SemanticReport) -> Result<()> {
     let path = path.trim().to_string();
     if path.is_empty() {
-        return Err("请选择导出路径".to_string());
+        return Err(ToolboxError::InvalidInput("请选择导出路径".to_string()));
     }
     if !path.to_lowercase().ends_with(".json") {
-        return Err("仅支持导出为 .json 文件".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "仅支持导出为 .json 文件".to_string(),
+        ));
     }
-    let content =
-        serde_json::to_string_pretty(&report).map_err(|e| format!("序列化失败: {}", e))?;
+    let content = serde_json::to_string_pretty(&report)
+        .map_err(|e| ToolboxError::internal("序列化失败", e))?;
     tokio::task::spawn_blocking(move || write_bytes_atomic(&path, content.as_bytes()))
         .await
-        .map_err(|e| format!("导出任务失败: {}", e))?
+        .map_err(|e| ToolboxError::internal("导出任务失败", e))?
 }
 
 #[tauri::command]
-pub async fn export_semantic_schema() -> Result<String, String> {
+pub async fn export_semantic_schema() -> Result<String> {
     Ok(schema_as_json_string())
 }
 
-fn write_bytes_atomic(path: &str, bytes: &[u8]) -> Result<(), String> {
+fn write_bytes_atomic(path: &str, bytes: &[u8]) -> Result<()> {
     let target = PathBuf::from(path);
     let parent = target
         .parent()
-        .ok_or_else(|| "导出路径无效".to_string())?;
+        .ok_or_else(|| ToolboxError::InvalidInput("导出路径无效".to_string()))?;
     if !parent.exists() {
-        return Err("导出目录不存在".to_string());
+        return Err(ToolboxError::InvalidInput("导出目录不存在".to_string()));
     }
     let tmp = parent.join(format!(".semantic-export-{}.tmp", Utc::now().timestamp_millis()));
     {
-        let mut file = fs::File::create(&tmp).map_err(|e| format!("写入失败: {}", e))?;
-        file.write_all(bytes).map_err(|e| format!("写入失败: {}", e))?;
+        let mut file = fs::File::create(&tmp).map_err(|e| ToolboxError::io("写入失败", e))?;
+        file.write_all(bytes)
+            .map_err(|e| ToolboxError::io("写入失败", e))?;
         let _ = file.sync_all();
     }
-    fs::rename(&tmp, &target).map_err(|e| format!("保存失败: {}", e))?;
+    fs::rename(&tmp, &target).map_err(|e| ToolboxError::io("保存失败", e))?;
     Ok(())
 }
 
@@ -2283,7 +2291,7 @@ struct ParsedGithubInput {
     display: String,
 }
 
-fn parse_github_input(input: &str) -> Result<ParsedGithubInput, String> {
+fn parse_github_input(input: &str) -> Result<ParsedGithubInput> {
     let s = input.trim();
     let s = s
         .trim_start_matches("https://")
@@ -2297,7 +2305,9 @@ fn parse_github_input(input: &str) -> Result<ParsedGithubInput, String> {
     };
     let parts: Vec<&str> = s.split('/').filter(|p| !p.is_empty()).collect();
     if parts.len() < 2 {
-        return Err("GitHub 链接格式不正确".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "GitHub 链接格式不正确".to_string(),
+        ));
     }
     let owner = parts[0].to_string();
     let repo = parts[1].to_string();
@@ -2325,7 +2335,7 @@ fn parse_github_input(input: &str) -> Result<ParsedGithubInput, String> {
     })
 }
 
-async fn download_github_zip(parsed: &ParsedGithubInput) -> Result<Vec<u8>, String> {
+async fn download_github_zip(parsed: &ParsedGithubInput) -> Result<Vec<u8>> {
     let url = format!(
         "https://codeload.github.com/{}/{}/zip/{}",
         parsed.owner, parsed.repo, parsed.reference
@@ -2333,20 +2343,23 @@ async fn download_github_zip(parsed: &ParsedGithubInput) -> Result<Vec<u8>, Stri
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::limited(10))
         .build()
-        .map_err(|e| format!("HTTP 客户端初始化失败: {}", e))?;
+        .map_err(|e| ToolboxError::internal("HTTP 客户端初始化失败", e))?;
     let res = client
         .get(&url)
         .header(USER_AGENT, "my-toolbox/semantic-scan")
         .send()
         .await
-        .map_err(|e| format!("下载失败: {}", e))?;
+        .map_err(|e| ToolboxError::network("下载失败", e))?;
     if !res.status().is_success() {
-        return Err(format!("下载失败，HTTP {}", res.status()));
+        return Err(ToolboxError::Network {
+            message: "下载失败".to_string(),
+            details: Some(format!("HTTP {}", res.status())),
+        });
     }
     res.bytes()
         .await
         .map(|b| b.to_vec())
-        .map_err(|e| format!("读取下载内容失败: {}", e))
+        .map_err(|e| ToolboxError::network("读取下载内容失败", e))
 }
 
 fn extract_zip_to_dir(zip_bytes: &[u8], target: &Path) -> Result<PathBuf, std::io::Error> {
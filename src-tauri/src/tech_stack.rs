@@ -9,11 +9,12 @@ use std::{
     time::UNIX_EPOCH,
     time::Instant,
 };
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 use ignore::WalkBuilder;
 use printpdf::{BuiltinFont, Mm, PdfDocument, PdfDocumentReference};
 use zip::ZipArchive;
-use crate::db::{resolve_db_path, Database};
+use crate::db::SharedDatabase;
+use crate::error::{Result, ToolboxError};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TechStackScanOptions {
@@ -107,25 +108,27 @@ pub async fn scan_tech_stack_local(
     root_path: String,
     options: Option<TechStackScanOptions>,
     window: tauri::Window,
-) -> Result<TechStackReport, String> {
+) -> Result<TechStackReport> {
     let root_path = root_path.trim().to_string();
     if root_path.is_empty() {
-        return Err("请输入项目根目录路径".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "请输入项目根目录路径".to_string(),
+        ));
     }
 
     let root = PathBuf::from(&root_path);
     if !root.exists() {
-        return Err("指定目录不存在".to_string());
+        return Err(ToolboxError::InvalidInput("指定目录不存在".to_string()));
     }
     if !root.is_dir() {
-        return Err("指定路径不是目录".to_string());
+        return Err(ToolboxError::InvalidInput("指定路径不是目录".to_string()));
     }
 
     let options = options.unwrap_or_default();
     let root_display = root.to_string_lossy().to_string();
     tokio::task::spawn_blocking(move || scan_local_blocking(root, root_display, options, window))
         .await
-        .map_err(|e| format!("扫描任务失败: {}", e))?
+        .map_err(|e| ToolboxError::internal("扫描任务失败", e))?
 }
 
 #[tauri::command]
@@ -133,10 +136,12 @@ pub async fn scan_tech_stack_github(
     repo_url: String,
     options: Option<TechStackScanOptions>,
     window: tauri::Window,
-) -> Result<TechStackReport, String> {
+) -> Result<TechStackReport> {
     let repo_url = repo_url.trim().to_string();
     if repo_url.is_empty() {
-        return Err("请输入 GitHub 仓库链接".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "请输入 GitHub 仓库链接".to_string(),
+        ));
     }
     let parsed = parse_github_input(&repo_url)?;
     emit_progress(
@@ -150,16 +155,17 @@ pub async fn scan_tech_stack_github(
     let zip_bytes = download_github_zip(&parsed, &window).await?;
     emit_progress(Some(&window), "解压仓库", "正在解压到临时目录", 0, None);
 
-    let temp = tempfile::tempdir().map_err(|e| format!("创建临时目录失败: {}", e))?;
-    let extracted_root = extract_zip_to_dir(&zip_bytes, temp.path())
-        .map_err(|e| format!("解压失败: {}", e))?;
+    let temp = tempfile::tempdir().map_err(|e| ToolboxError::io("创建临时目录失败", e))?;
+    let extracted_root = extract_zip_to_dir(&zip_bytes, temp.path())?;
     let scan_root = if let Some(subdir) = &parsed.subdir {
         extracted_root.join(subdir)
     } else {
         extracted_root.clone()
     };
     if !scan_root.exists() || !scan_root.is_dir() {
-        return Err("指定的子目录不存在或不是目录".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "指定的子目录不存在或不是目录".to_string(),
+        ));
     }
 
     let root_display = parsed.display.clone();
@@ -177,40 +183,44 @@ pub async fn scan_tech_stack_github(
         )
     })
     .await
-    .map_err(|e| format!("扫描任务失败: {}", e))?
+    .map_err(|e| ToolboxError::internal("扫描任务失败", e))?
 }
 
 #[tauri::command]
-pub async fn export_tech_stack_json(path: String, report: TechStackReport) -> Result<(), String> {
+pub async fn export_tech_stack_json(path: String, report: TechStackReport) -> Result<()> {
     let path = path.trim().to_string();
     if path.is_empty() {
-        return Err("请选择导出路径".to_string());
+        return Err(ToolboxError::InvalidInput("请选择导出路径".to_string()));
     }
     if !path.to_lowercase().ends_with(".json") {
-        return Err("仅支持导出为 .json 文件".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "仅支持导出为 .json 文件".to_string(),
+        ));
     }
-    let content =
-        serde_json::to_string_pretty(&report).map_err(|e| format!("序列化失败: {}", e))?;
+    let content = serde_json::to_string_pretty(&report)
+        .map_err(|e| ToolboxError::internal("序列化失败", e))?;
     tokio::task::spawn_blocking(move || write_bytes_atomic(&path, content.as_bytes()))
         .await
-        .map_err(|e| format!("导出任务失败: {}", e))?
+        .map_err(|e| ToolboxError::internal("导出任务失败", e))?
 }
 
 #[tauri::command]
-pub async fn export_tech_stack_pdf(path: String, report: TechStackReport) -> Result<(), String> {
+pub async fn export_tech_stack_pdf(path: String, report: TechStackReport) -> Result<()> {
     let path = path.trim().to_string();
     if path.is_empty() {
-        return Err("请选择导出路径".to_string());
+        return Err(ToolboxError::InvalidInput("请选择导出路径".to_string()));
     }
     if !path.to_lowercase().ends_with(".pdf") {
-        return Err("仅支持导出为 .pdf 文件".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "仅支持导出为 .pdf 文件".to_string(),
+        ));
     }
     tokio::task::spawn_blocking(move || {
         let bytes = build_pdf_bytes(&report)?;
         write_bytes_atomic(&path, &bytes)
     })
     .await
-    .map_err(|e| format!("导出任务失败: {}", e))?
+    .map_err(|e| ToolboxError::internal("导出任务失败", e))?
 }
 
 fn scan_local_blocking(
@@ -218,7 +228,7 @@ fn scan_local_blocking(
     root_display: String,
     options: TechStackScanOptions,
     window: tauri::Window,
-) -> Result<TechStackReport, String> {
+) -> Result<TechStackReport> {
     let input_value = root_display.clone();
     scan_dir_blocking("local", &input_value, root, root_display, options, window)
 }
@@ -230,7 +240,7 @@ fn scan_dir_blocking(
     root_display: String,
     options: TechStackScanOptions,
     window: tauri::Window,
-) -> Result<TechStackReport, String> {
+) -> Result<TechStackReport> {
     let start = Instant::now();
     emit_progress(
         Some(&window),
@@ -241,7 +251,8 @@ fn scan_dir_blocking(
     );
 
     let fingerprint = build_scan_fingerprint(input_kind, input_value, &root);
-    if let Some(db) = open_cache_db() {
+    let cache = window.state::<SharedDatabase>();
+    if let Ok(db) = cache.lock() {
         if let Ok(Some(cached_json)) = db.get_tech_stack_scan_json(input_kind, &fingerprint) {
             if let Ok(mut report) = serde_json::from_str::<TechStackReport>(&cached_json) {
                 report.warnings.push("缓存命中".to_string());
@@ -400,7 +411,7 @@ fn scan_dir_blocking(
         warnings,
     };
 
-    if let Some(db) = open_cache_db() {
+    if let Ok(db) = cache.lock() {
         if let Ok(json) = serde_json::to_string(&report) {
             let elapsed = report.elapsed_ms.min(i64::MAX as u128) as i64;
             let _ = db.upsert_tech_stack_scan_json(
@@ -416,17 +427,17 @@ fn scan_dir_blocking(
     Ok(report)
 }
 
-fn write_bytes_atomic(path: &str, bytes: &[u8]) -> Result<(), String> {
+pub(crate) fn write_bytes_atomic(path: &str, bytes: &[u8]) -> Result<()> {
     let target = PathBuf::from(path);
     let parent = target
         .parent()
-        .ok_or_else(|| "导出路径无效".to_string())?;
+        .ok_or_else(|| ToolboxError::InvalidInput("导出路径无效".to_string()))?;
     if !parent.exists() {
-        return Err("导出目录不存在".to_string());
+        return Err(ToolboxError::InvalidInput("导出目录不存在".to_string()));
     }
 
     let tmp_name = format!(
-        ".tech-stack-export-{}.tmp",
+        ".toolbox-export-{}.tmp",
         std::time::SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_millis())
@@ -434,19 +445,20 @@ fn write_bytes_atomic(path: &str, bytes: &[u8]) -> Result<(), String> {
     );
     let tmp = parent.join(tmp_name);
     {
-        let mut file = fs::File::create(&tmp).map_err(|e| format!("写入失败: {}", e))?;
-        file.write_all(bytes).map_err(|e| format!("写入失败: {}", e))?;
+        let mut file = fs::File::create(&tmp).map_err(|e| ToolboxError::io("写入失败", e))?;
+        file.write_all(bytes)
+            .map_err(|e| ToolboxError::io("写入失败", e))?;
         let _ = file.sync_all();
     }
-    fs::rename(&tmp, &target).map_err(|e| format!("保存失败: {}", e))?;
+    fs::rename(&tmp, &target).map_err(|e| ToolboxError::io("保存失败", e))?;
     Ok(())
 }
 
-fn build_pdf_bytes(report: &TechStackReport) -> Result<Vec<u8>, String> {
+fn build_pdf_bytes(report: &TechStackReport) -> Result<Vec<u8>> {
     let (doc, page1, layer1) = PdfDocument::new("Tech Stack Report", Mm(210.0), Mm(297.0), "L1");
     let font = doc
         .add_builtin_font(BuiltinFont::Helvetica)
-        .map_err(|e| format!("PDF 字体初始化失败: {}", e))?;
+        .map_err(|e| ToolboxError::internal("PDF 字体初始化失败", e))?;
 
     let mut pages: Vec<(printpdf::PdfPageIndex, printpdf::PdfLayerIndex)> = vec![(page1, layer1)];
     let mut current = 0usize;
@@ -520,10 +532,10 @@ fn build_pdf_bytes(report: &TechStackReport) -> Result<Vec<u8>, String> {
     let writer = Cursor::new(Vec::<u8>::new());
     let mut buf = std::io::BufWriter::new(writer);
     doc.save(&mut buf)
-        .map_err(|e| format!("PDF 生成失败: {}", e))?;
+        .map_err(|e| ToolboxError::internal("PDF 生成失败", e))?;
     let cursor = buf
         .into_inner()
-        .map_err(|e| format!("PDF 写入失败: {}", e))?;
+        .map_err(|e| ToolboxError::internal("PDF 写入失败", e))?;
     Ok(cursor.into_inner())
 }
 
@@ -567,10 +579,12 @@ struct ParsedGithubInput {
     display: String,
 }
 
-fn parse_github_input(input: &str) -> Result<ParsedGithubInput, String> {
+fn parse_github_input(input: &str) -> Result<ParsedGithubInput> {
     let s = input.trim();
     if s.is_empty() {
-        return Err("请输入 GitHub 仓库链接".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "请输入 GitHub 仓库链接".to_string(),
+        ));
     }
 
     let s = s
@@ -587,12 +601,16 @@ fn parse_github_input(input: &str) -> Result<ParsedGithubInput, String> {
 
     let parts: Vec<&str> = s.split('/').filter(|p| !p.is_empty()).collect();
     if parts.len() < 2 {
-        return Err("GitHub 链接格式不正确，应为 owner/repo".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "GitHub 链接格式不正确，应为 owner/repo".to_string(),
+        ));
     }
     let owner = parts[0].to_string();
     let repo = parts[1].trim_end_matches(".git").to_string();
     if owner.is_empty() || repo.is_empty() {
-        return Err("GitHub 链接格式不正确，应为 owner/repo".to_string());
+        return Err(ToolboxError::InvalidInput(
+            "GitHub 链接格式不正确，应为 owner/repo".to_string(),
+        ));
     }
 
     let mut reference = "HEAD".to_string();
@@ -624,7 +642,7 @@ fn parse_github_input(input: &str) -> Result<ParsedGithubInput, String> {
 async fn download_github_zip(
     parsed: &ParsedGithubInput,
     window: &tauri::Window,
-) -> Result<Vec<u8>, String> {
+) -> Result<Vec<u8>> {
     let url = format!(
         "https://codeload.github.com/{}/{}/zip/{}",
         parsed.owner, parsed.repo, parsed.reference
@@ -633,22 +651,27 @@ async fn download_github_zip(
         .user_agent("my-toolbox/tech-stack-scanner")
         .timeout(std::time::Duration::from_secs(20))
         .build()
-        .map_err(|e| format!("创建网络客户端失败: {}", e))?;
+        .map_err(|e| ToolboxError::internal("创建网络客户端失败", e))?;
 
     let mut resp = client
         .get(url)
         .send()
         .await
-        .map_err(|e| format!("下载失败: {}", e))?;
+        .map_err(|e| ToolboxError::network("下载失败", e))?;
     if !resp.status().is_success() {
-        return Err(format!("下载失败: HTTP {}", resp.status().as_u16()));
+        return Err(ToolboxError::Network {
+            message: "下载失败".to_string(),
+            details: Some(format!("HTTP {}", resp.status().as_u16())),
+        });
     }
 
     let content_len = resp.content_length();
     let limit: u64 = 80 * 1024 * 1024;
     if let Some(len) = content_len {
         if len > limit {
-            return Err("仓库压缩包过大，已拒绝下载".to_string());
+            return Err(ToolboxError::InvalidInput(
+                "仓库压缩包过大，已拒绝下载".to_string(),
+            ));
         }
     }
 
@@ -657,11 +680,13 @@ async fn download_github_zip(
     while let Some(chunk) = resp
         .chunk()
         .await
-        .map_err(|e| format!("下载失败: {}", e))?
+        .map_err(|e| ToolboxError::network("下载失败", e))?
     {
         downloaded = downloaded.saturating_add(chunk.len() as u64);
         if downloaded > limit {
-            return Err("仓库压缩包过大，已中止下载".to_string());
+            return Err(ToolboxError::InvalidInput(
+                "仓库压缩包过大，已中止下载".to_string(),
+            ));
         }
         buf.extend_from_slice(&chunk);
         if downloaded % (2 * 1024 * 1024) < 65536 {
@@ -678,14 +703,15 @@ async fn download_github_zip(
     Ok(buf)
 }
 
-fn extract_zip_to_dir(zip_bytes: &[u8], dest: &Path) -> Result<PathBuf, String> {
+fn extract_zip_to_dir(zip_bytes: &[u8], dest: &Path) -> Result<PathBuf> {
     let reader = Cursor::new(zip_bytes);
-    let mut archive = ZipArchive::new(reader).map_err(|e| format!("无法读取 zip: {}", e))?;
+    let mut archive =
+        ZipArchive::new(reader).map_err(|e| ToolboxError::parse("无法读取 zip", e))?;
 
     for i in 0..archive.len() {
         let mut file = archive
             .by_index(i)
-            .map_err(|e| format!("读取 zip 条目失败: {}", e))?;
+            .map_err(|e| ToolboxError::parse("读取 zip 条目失败", e))?;
         let name = file.name().to_string();
         let out_rel = sanitize_zip_path(&name)?;
         let out_path = dest.join(out_rel);
@@ -698,10 +724,18 @@ fn extract_zip_to_dir(zip_bytes: &[u8], dest: &Path) -> Result<PathBuf, String>
         if let Some(parent) = out_path.parent() {
             let _ = fs::create_dir_all(parent);
         }
-        let mut out = fs::File::create(&out_path)
-            .map_err(|e| format!("写入解压文件失败: {}: {}", out_path.to_string_lossy(), e))?;
-        std::io::copy(&mut file, &mut out)
-            .map_err(|e| format!("写入解压文件失败: {}: {}", out_path.to_string_lossy(), e))?;
+        let mut out = fs::File::create(&out_path).map_err(|e| {
+            ToolboxError::io(
+                format!("写入解压文件失败: {}", out_path.to_string_lossy()),
+                e,
+            )
+        })?;
+        std::io::copy(&mut file, &mut out).map_err(|e| {
+            ToolboxError::io(
+                format!("写入解压文件失败: {}", out_path.to_string_lossy()),
+                e,
+            )
+        })?;
         let _ = out.flush();
     }
 
@@ -721,14 +755,18 @@ fn extract_zip_to_dir(zip_bytes: &[u8], dest: &Path) -> Result<PathBuf, String>
     }
 }
 
-fn sanitize_zip_path(name: &str) -> Result<PathBuf, String> {
+fn sanitize_zip_path(name: &str) -> Result<PathBuf> {
     let p = Path::new(name);
     let mut out = PathBuf::new();
     for comp in p.components() {
         match comp {
             std::path::Component::Normal(s) => out.push(s),
             std::path::Component::CurDir => {}
-            _ => return Err("zip 条目路径不安全，已拒绝解压".to_string()),
+            _ => {
+                return Err(ToolboxError::InvalidInput(
+                    "zip 条目路径不安全，已拒绝解压".to_string(),
+                ))
+            }
         }
     }
     Ok(out)
@@ -1594,11 +1632,6 @@ fn parse_git_submodules(root: &Path) -> Vec<GitSubmodule> {
     out
 }
 
-fn open_cache_db() -> Option<Database> {
-    let path = resolve_db_path().ok()?;
-    Database::new(&path).ok()
-}
-
 fn build_scan_fingerprint(input_kind: &str, input_value: &str, root: &Path) -> String {
     let mut files: BTreeMap<String, Value> = BTreeMap::new();
     for rel in [
@@ -0,0 +1,490 @@
+use crate::db::{GradeRecord, ScheduleEntry};
+use crate::jwxt::{encode_login_params, JwxtProfile};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// 将解析结果序列化为格式化 JSON 后与 tests/fixtures 下的 golden 文件逐字比较。
+pub fn assert_golden<T: Serialize + ?Sized>(actual: &T, golden: &str) {
+    let actual = serde_json::to_string_pretty(actual).expect("serialize parser output");
+    assert_eq!(actual.trim(), golden.trim());
+}
+
+// 成绩类测试共用的成绩记录：默认账号 2023001、2 学分，课程名称与课程代码相同。
+pub struct GradeRecordBuilder(GradeRecord);
+
+pub fn grade_record(term: &str, code: &str, score: &str) -> GradeRecordBuilder {
+    GradeRecordBuilder(GradeRecord {
+        id: 0,
+        username: "2023001".to_string(),
+        term: term.to_string(),
+        course_code: code.to_string(),
+        course_name: code.to_string(),
+        group_name: String::new(),
+        score: Some(score.to_string()),
+        score_flag: None,
+        credit: Some(2.0),
+        total_hours: None,
+        gpa: None,
+        makeup_term: None,
+        exam_mode: None,
+        exam_type: None,
+        course_attr: None,
+        course_nature: None,
+        general_type: None,
+        is_minor: false,
+        updated_at: String::new(),
+    })
+}
+
+impl GradeRecordBuilder {
+    pub fn username(mut self, username: &str) -> Self {
+        self.0.username = username.to_string();
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.0.course_name = name.to_string();
+        self
+    }
+
+    pub fn credit(mut self, credit: f32) -> Self {
+        self.0.credit = Some(credit);
+        self
+    }
+
+    pub fn nature(mut self, nature: &str) -> Self {
+        self.0.course_nature = Some(nature.to_string());
+        self
+    }
+
+    pub fn attr(mut self, attr: &str) -> Self {
+        self.0.course_attr = Some(attr.to_string());
+        self
+    }
+
+    pub fn makeup_term(mut self, term: &str) -> Self {
+        self.0.makeup_term = Some(term.to_string());
+        self
+    }
+
+    pub fn minor(mut self) -> Self {
+        self.0.is_minor = true;
+        self
+    }
+
+    pub fn build(self) -> GradeRecord {
+        self.0
+    }
+}
+
+// 课表类测试共用的课程：默认学期 2024-2025-2，课程名称为“课程{id}”。
+pub struct ScheduleEntryBuilder(ScheduleEntry);
+
+pub fn schedule_entry(id: i32, weekday: i32, label: &str, weeks: &[i32]) -> ScheduleEntryBuilder {
+    ScheduleEntryBuilder(ScheduleEntry {
+        id,
+        term: "2024-2025-2".to_string(),
+        weekday,
+        period_label: label.to_string(),
+        period_index: None,
+        course_name: format!("课程{}", id),
+        teacher: None,
+        location: None,
+        class_group: None,
+        week_text: None,
+        week_numbers: weeks.to_vec(),
+        updated_at: String::new(),
+    })
+}
+
+impl ScheduleEntryBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.0.course_name = name.to_string();
+        self
+    }
+
+    pub fn period_index(mut self, period_index: i32) -> Self {
+        self.0.period_index = Some(period_index);
+        self
+    }
+
+    pub fn teacher(mut self, teacher: &str) -> Self {
+        self.0.teacher = Some(teacher.to_string());
+        self
+    }
+
+    pub fn location(mut self, location: &str) -> Self {
+        self.0.location = Some(location.to_string());
+        self
+    }
+
+    pub fn week_text(mut self, week_text: &str) -> Self {
+        self.0.week_text = Some(week_text.to_string());
+        self
+    }
+
+    pub fn build(self) -> ScheduleEntry {
+        self.0
+    }
+}
+
+const CJCX_LIST: &str = include_str!("../tests/fixtures/jwxt/cjcx_list.html");
+const PYFA_QUERY: &str = include_str!("../tests/fixtures/jwxt/pyfa_query.html");
+const FXPYFA_QUERY: &str = include_str!("../tests/fixtures/jwxt/fxpyfa_query.html");
+const XSKB_LIST: &str = include_str!("../tests/fixtures/jwxt/xskb_list.do.html");
+const XSKB_LIST_EMPTY: &str = include_str!("../tests/fixtures/jwxt/xskb_list.do_empty.html");
+const JXZL_QUERY: &str = include_str!("../tests/fixtures/jwxt/jxzl_query.html");
+const JXZL_QUERY_EMPTY: &str = include_str!("../tests/fixtures/jwxt/jxzl_query_empty.html");
+const XSKSAP_LIST: &str = include_str!("../tests/fixtures/jwxt/xsksap_list.html");
+const LOGIN_PAGE: &str = r#"<html><body><form action="/jsxsd/xk/LoginToXk" method="post">
+<input type="text" name="userAccount" id="userAccount"><input type="password" name="userPassword">
+</form></body></html>"#;
+const MAIN_PAGE: &str = "<html><body>学生个人中心</body></html>";
+const SESSION_COOKIE: &str = "JSESSIONID";
+const CAPTCHA_IMAGE: &[u8] = b"\xff\xd8\xff\xe0mock-captcha\xff\xd9";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MockLoginMode {
+    Normal,
+    Captcha,
+}
+
+struct MockState {
+    username: String,
+    password: String,
+    mode: Mutex<MockLoginMode>,
+    sessions: Mutex<HashSet<String>>,
+    captchas: Mutex<HashMap<String, String>>,
+    logins: AtomicUsize,
+    issued: AtomicUsize,
+}
+
+// 仅供测试使用的强智教务系统替身：登录、成绩、执行计划、课表、周历与考试安排页面均返回 fixtures。
+pub struct MockJwxt {
+    base_url: String,
+    state: Arc<MockState>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockJwxt {
+    pub async fn start(username: &str, password: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock jwxt");
+        let addr = listener.local_addr().expect("mock jwxt addr");
+        let state = Arc::new(MockState {
+            username: username.to_string(),
+            password: password.to_string(),
+            mode: Mutex::new(MockLoginMode::Normal),
+            sessions: Mutex::new(HashSet::new()),
+            captchas: Mutex::new(HashMap::new()),
+            logins: AtomicUsize::new(0),
+            issued: AtomicUsize::new(0),
+        });
+        let server_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, &state).await;
+                });
+            }
+        });
+        MockJwxt {
+            base_url: format!("http://{}", addr),
+            state,
+            task,
+        }
+    }
+
+    pub fn profile(&self) -> JwxtProfile {
+        JwxtProfile {
+            base_url: self.base_url.clone(),
+            ..JwxtProfile::default()
+        }
+    }
+
+    pub fn login_count(&self) -> usize {
+        self.state.logins.load(Ordering::SeqCst)
+    }
+
+    pub fn set_login_mode(&self, mode: MockLoginMode) {
+        *self.state.mode.lock().unwrap() = mode;
+    }
+
+    pub fn expire_sessions(&self) {
+        self.state.sessions.lock().unwrap().clear();
+    }
+
+    // 最近一次下发的验证码答案，模拟用户看图输入。
+    pub fn captcha_answer(&self) -> Option<String> {
+        let issued = self.state.issued.load(Ordering::SeqCst);
+        (issued > 0).then(|| captcha_code(issued))
+    }
+}
+
+impl Drop for MockJwxt {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct MockRequest {
+    method: String,
+    path: String,
+    cookie: Option<String>,
+    form: HashMap<String, String>,
+}
+
+struct MockResponse {
+    status: &'static str,
+    content_type: &'static str,
+    location: Option<String>,
+    set_cookie: Option<String>,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    fn html(body: &str) -> Self {
+        MockResponse {
+            status: "200 OK",
+            content_type: "text/html;charset=UTF-8",
+            location: None,
+            set_cookie: None,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn redirect(location: &str) -> Self {
+        MockResponse {
+            status: "302 Found",
+            content_type: "text/html;charset=UTF-8",
+            location: Some(location.to_string()),
+            set_cookie: None,
+            body: Vec::new(),
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: &MockState) -> std::io::Result<()> {
+    let request = match read_request(&mut stream).await? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    let response = route(&request, state);
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    if let Some(location) = &response.location {
+        head.push_str(&format!("Location: {}\r\n", location));
+    }
+    if let Some(cookie) = &response.set_cookie {
+        head.push_str(&format!(
+            "Set-Cookie: {}={}; Path=/jsxsd\r\n",
+            SESSION_COOKIE, cookie
+        ));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.shutdown().await
+}
+
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<MockRequest>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut content_length = 0usize;
+    let mut cookie = None;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("cookie") {
+                cookie = value
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(key, _)| *key == SESSION_COOKIE)
+                    .map(|(_, value)| value.to_string());
+            }
+        }
+    }
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body = String::from_utf8_lossy(&buffer[header_end..]).to_string();
+    Ok(Some(MockRequest {
+        method,
+        path,
+        cookie,
+        form: parse_form(&body),
+    }))
+}
+
+fn parse_form(body: &str) -> HashMap<String, String> {
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn route(request: &MockRequest, state: &MockState) -> MockResponse {
+    let profile = JwxtProfile::default();
+    if request.path == profile.login_path {
+        return login(request, state);
+    }
+    if request.path == "/jsxsd/" || request.path == "/jsxsd" {
+        return MockResponse::html(LOGIN_PAGE);
+    }
+    if request.path == profile.captcha_path {
+        return captcha(request, state);
+    }
+
+    let authorized = request
+        .cookie
+        .as_ref()
+        .is_some_and(|cookie| state.sessions.lock().unwrap().contains(cookie));
+    if !authorized {
+        return MockResponse::redirect("/jsxsd/");
+    }
+
+    let path = request.path.as_str();
+    if path == profile.main_path {
+        MockResponse::html(MAIN_PAGE)
+    } else if path == profile.grades_path {
+        MockResponse::html(CJCX_LIST)
+    } else if path == profile.major_plan_path {
+        MockResponse::html(PYFA_QUERY)
+    } else if path == profile.minor_plan_path {
+        MockResponse::html(FXPYFA_QUERY)
+    } else if path == profile.schedule_path {
+        let term = request.form.get(&profile.fields.schedule_term);
+        match (request.method.as_str(), term.map(|t| t.as_str())) {
+            ("POST", Some(term)) if term != "2024-2025-2" => MockResponse::html(XSKB_LIST_EMPTY),
+            _ => MockResponse::html(XSKB_LIST),
+        }
+    } else if path == profile.calendar_path {
+        match request
+            .form
+            .get(&profile.fields.schedule_term)
+            .map(|t| t.as_str())
+        {
+            Some("2024-2025-2") => MockResponse::html(JXZL_QUERY),
+            _ => MockResponse::html(JXZL_QUERY_EMPTY),
+        }
+    } else if path == profile.exam_path {
+        MockResponse::html(XSKSAP_LIST)
+    } else {
+        MockResponse {
+            status: "404 Not Found",
+            content_type: "text/html;charset=UTF-8",
+            location: None,
+            set_cookie: None,
+            body: Vec::new(),
+        }
+    }
+}
+
+fn captcha_code(index: usize) -> String {
+    format!("k{}m", index)
+}
+
+// 验证码与取图时的 Cookie 绑定，换了会话就得重新取图。
+fn captcha(request: &MockRequest, state: &MockState) -> MockResponse {
+    let index = state.issued.fetch_add(1, Ordering::SeqCst) + 1;
+    let cookie = request
+        .cookie
+        .clone()
+        .unwrap_or_else(|| format!("mock-captcha-{}", index));
+    state
+        .captchas
+        .lock()
+        .unwrap()
+        .insert(cookie.clone(), captcha_code(index));
+    MockResponse {
+        status: "200 OK",
+        content_type: "image/jpeg",
+        location: None,
+        set_cookie: Some(cookie),
+        body: CAPTCHA_IMAGE.to_vec(),
+    }
+}
+
+fn login(request: &MockRequest, state: &MockState) -> MockResponse {
+    if *state.mode.lock().unwrap() == MockLoginMode::Captcha {
+        let expected = request
+            .cookie
+            .as_ref()
+            .and_then(|cookie| state.captchas.lock().unwrap().remove(cookie));
+        let answer = request.form.get("RANDOMCODE");
+        if expected.is_none() || expected.as_ref() != answer {
+            return MockResponse::html("<html><body>请输入验证码</body></html>");
+        }
+    }
+    let expected = encode_login_params(&state.username, &state.password);
+    let account = request.form.get("userAccount").map(String::as_str);
+    let encoded = request.form.get("encoded").map(String::as_str);
+    if account != Some(state.username.as_str()) || encoded != Some(expected.as_str()) {
+        return MockResponse::html(
+            "<html><body><font color=\"red\">用户名或密码错误</font></body></html>",
+        );
+    }
+    let count = state.logins.fetch_add(1, Ordering::SeqCst) + 1;
+    let session_id = format!("mock-session-{}", count);
+    state.sessions.lock().unwrap().insert(session_id.clone());
+    let mut response = MockResponse::redirect(&JwxtProfile::default().main_path);
+    response.set_cookie = Some(session_id);
+    response
+}
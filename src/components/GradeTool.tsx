@@ -21,6 +21,7 @@ import {
 } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { ToolLayout } from "@/components/ToolLayout";
+import { errorMessage } from "@/lib/utils";
 
 // --- Types ---
 interface GradeUser {
@@ -278,7 +279,7 @@ export default function GradeTool() {
       }
       setTimeout(() => setShowImportModal(false), 1500);
     } catch (error) {
-      setImportMessage(`同步失败: ${error}`);
+      setImportMessage(`同步失败: ${errorMessage(error)}`);
     } finally {
       setIsSyncing(false);
     }
@@ -292,7 +293,7 @@ export default function GradeTool() {
       await loadGrades(selectedUser);
       await loadPendingCourses(selectedUser, gradeCategory);
     } catch (error) {
-      alert(`更新失败: ${error}`);
+      alert(`更新失败: ${errorMessage(error)}`);
     } finally {
       setIsSyncing(false);
     }
@@ -326,7 +327,7 @@ export default function GradeTool() {
       });
       await loadUsers();
     } catch (error) {
-      alert(`更新失败: ${error}`);
+      alert(`更新失败: ${errorMessage(error)}`);
     }
   };
 
@@ -338,7 +339,7 @@ export default function GradeTool() {
       await invoke("hide_grade_user", { username: user.username });
       await loadUsers();
     } catch (error) {
-      alert(`移除失败: ${error}`);
+      alert(`移除失败: ${errorMessage(error)}`);
     }
   };
 
@@ -525,7 +526,7 @@ export default function GradeTool() {
       });
       loadGrades(selectedUser);
     } catch (e) {
-      alert(e);
+      alert(errorMessage(e));
     }
   };
 
@@ -567,7 +568,7 @@ export default function GradeTool() {
       });
       loadPendingCourses(selectedUser, gradeCategory);
     } catch (error) {
-      alert(`更新失败: ${error}`);
+      alert(`更新失败: ${errorMessage(error)}`);
     }
   };
 
@@ -577,7 +578,7 @@ export default function GradeTool() {
       await invoke("delete_plan_course", { id });
       loadPendingCourses(selectedUser, gradeCategory);
     } catch (error) {
-      alert(`删除失败: ${error}`);
+      alert(`删除失败: ${errorMessage(error)}`);
     }
   };
 
@@ -18,6 +18,7 @@ import {
 } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { ToolLayout } from "@/components/ToolLayout";
+import { errorMessage } from "@/lib/utils";
 
 // --- Types ---
 interface CrackProgress {
@@ -36,6 +37,8 @@ interface PasswordResult {
   class_name?: string | null;
   password_date?: string | null;
   created_at: string;
+  // 密钥更换后已保存的密码无法解密
+  credentials_unreadable?: boolean;
 }
 
 // --- Components ---
@@ -161,7 +164,7 @@ export default function PasswordCrackerTool() {
       addLog(`完成：${result}`);
       loadHistory();
     } catch (error) {
-      addLog(`失败：${error}`);
+      addLog(`失败：${errorMessage(error)}`);
     } finally {
       setIsRunning(false);
     }
@@ -191,7 +194,7 @@ export default function PasswordCrackerTool() {
       });
       await loadHistory();
     } catch (error) {
-      alert(`更新失败: ${error}`);
+      alert(`更新失败: ${errorMessage(error)}`);
     }
   };
 
@@ -201,7 +204,7 @@ export default function PasswordCrackerTool() {
       await invoke("delete_password_result", { username: record.username });
       await loadHistory();
     } catch (error) {
-      alert(`删除失败: ${error}`);
+      alert(`删除失败: ${errorMessage(error)}`);
     }
   };
 
@@ -422,7 +425,9 @@ export default function PasswordCrackerTool() {
               </div>
               <div className="flex items-center gap-2">
                 <div className="text-sm font-mono bg-primary/10 text-primary px-2 py-1 rounded">
-                  {record.password_date || "无"}
+                  {record.credentials_unreadable
+                    ? "无法解密，请清除后重新输入"
+                    : record.password_date || "无"}
                 </div>
                 <Button
                   size="icon"
@@ -487,7 +492,9 @@ export default function PasswordCrackerTool() {
                       <td className="px-6 py-4">{record.name || "-"}</td>
                       <td className="px-6 py-4">{record.class_name || "-"}</td>
                       <td className="px-6 py-4 font-mono">
-                        {record.password_date || "-"}
+                        {record.credentials_unreadable
+                          ? "无法解密，请清除后重新输入"
+                          : record.password_date || "-"}
                       </td>
                       <td className="px-6 py-4 text-muted-foreground">
                         {new Date(record.created_at).toLocaleString()}
@@ -4,6 +4,7 @@ import { open, save } from "@tauri-apps/plugin-dialog";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
 import { Button } from "@/components/ui/button";
 import { ToolLayout } from "@/components/ToolLayout";
+import { errorMessage } from "@/lib/utils";
 import { Copy, FolderOpen, RefreshCw, Save } from "lucide-react";
 
 type ProjectTreeScanResult = {
@@ -71,7 +72,7 @@ export default function ProjectTreeScannerTool() {
       setCounts({ files: result.file_count, dirs: result.dir_count });
       setStatus("扫描完成");
     } catch (error) {
-      setStatus(`扫描失败: ${String(error)}`);
+      setStatus(`扫描失败: ${errorMessage(error)}`);
     } finally {
       setIsScanning(false);
     }
@@ -87,7 +88,7 @@ export default function ProjectTreeScannerTool() {
       await writeText(tree);
       setStatus("已复制到剪贴板");
     } catch (error) {
-      setStatus(`复制失败: ${String(error)}`);
+      setStatus(`复制失败: ${errorMessage(error)}`);
     }
   };
 
@@ -112,7 +113,7 @@ export default function ProjectTreeScannerTool() {
       await invoke("save_tree_to_file", { path: target, content });
       setStatus("已保存");
     } catch (error) {
-      setStatus(`保存失败: ${String(error)}`);
+      setStatus(`保存失败: ${errorMessage(error)}`);
     }
   };
 
@@ -9,6 +9,7 @@ import {
 } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { ToolLayout } from "@/components/ToolLayout";
+import { errorMessage } from "@/lib/utils";
 
 interface ScheduleTerm {
   term: string;
@@ -151,7 +152,7 @@ export default function ScheduleTool() {
       setSyncPassword("");
       setTimeout(() => setShowSyncModal(false), 1200);
     } catch (error) {
-      setSyncMessage(`同步失败：${error}`);
+      setSyncMessage(`同步失败：${errorMessage(error)}`);
     } finally {
       setIsSyncing(false);
     }
@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { open, save } from "@tauri-apps/plugin-dialog";
 import { Button } from "@/components/ui/button";
 import { ToolLayout } from "@/components/ToolLayout";
+import { errorMessage } from "@/lib/utils";
 import {
   Download,
   FileJson,
@@ -235,7 +236,7 @@ export default function TechStackDetectorTool() {
       setStatus("扫描完成");
     } catch (error) {
       if (cancelTokenRef.current !== token) return;
-      setStatus(`扫描失败: ${String(error)}`);
+      setStatus(`扫描失败: ${errorMessage(error)}`);
     } finally {
       if (cancelTokenRef.current !== token) return;
       setIsScanning(false);
@@ -261,7 +262,7 @@ export default function TechStackDetectorTool() {
       await invoke("export_semantic_json", { path: target, report });
       setStatus("已导出 JSON");
     } catch (error) {
-      setStatus(`导出失败: ${String(error)}`);
+      setStatus(`导出失败: ${errorMessage(error)}`);
     }
   };
 
@@ -4,3 +4,22 @@ import { twMerge } from "tailwind-merge"
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+export type ToolboxError = {
+  code: string
+  message: string
+  details: string | null
+}
+
+export function isToolboxError(error: unknown): error is ToolboxError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "code" in error &&
+    "message" in error
+  )
+}
+
+export function errorMessage(error: unknown): string {
+  return isToolboxError(error) ? error.message : String(error)
+}